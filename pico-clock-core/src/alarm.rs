@@ -0,0 +1,342 @@
+//! Alarm clock: Alarms configured by the user, scheduled using the alarms of the RTC.
+//!
+//! Only the next due alarm is programmed into the RTC, snoozing uses a second alarm. The alarm
+//! flags of the RTC are polled by the display FSM, which checks the time by software for the
+//! alarms not supported by the RTC.
+
+use crate::rtc::{self, AlarmSlot, RtcError, TimeSource};
+use crate::settings::store::{Reader, Writer};
+use crate::settings::FixedText;
+use crate::timezone::{self, TimeZone};
+
+use ds323x::{Datelike, NaiveDateTime, NaiveTime, Timelike};
+
+use core::fmt;
+
+// Concurrency (critical sections provided by the firmware or the host)
+use core::cell::Cell;
+use critical_section::Mutex;
+
+pub const MAX_ALARMS: usize = 8;
+pub const MAX_LABEL_LEN: usize = 20;
+pub const SNOOZE_MINUTES: u32 = 5;
+
+pub type AlarmLabel = FixedText<MAX_LABEL_LEN>;
+pub type Alarms = [Option<Alarm>; MAX_ALARMS];
+
+/// Set of weekdays (bit 0 is Monday, bit 6 is Sunday).
+#[derive(Clone, Copy, PartialEq)]
+pub struct Weekdays(u8);
+
+const WEEKDAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+impl Weekdays {
+    pub const ALL: Weekdays = Weekdays(0x7F);
+    pub const WORKDAYS: Weekdays = Weekdays(0x1F);
+    pub const WEEKEND: Weekdays = Weekdays(0x60);
+
+    /// Parses 'daily', 'weekdays', 'weekend' or a comma separated list like 'mon,wed,fri'.
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "daily" => Some(Self::ALL),
+            "weekdays" => Some(Self::WORKDAYS),
+            "weekend" => Some(Self::WEEKEND),
+            _ => {
+                let mut mask = 0;
+                for name in input.split(',') {
+                    let index = WEEKDAY_NAMES.iter().position(|x| *x == name)?;
+                    mask |= 1 << index;
+                }
+                Some(Self(mask))
+            }
+        }
+    }
+
+    /// Checks for the weekday given as number of days from Monday (0..=6).
+    pub fn contains(&self, days_from_monday: u32) -> bool {
+        self.0 & (1 << days_from_monday) != 0
+    }
+}
+
+impl fmt::Display for Weekdays {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ALL => write!(f, "daily"),
+            Self::WORKDAYS => write!(f, "weekdays"),
+            Self::WEEKEND => write!(f, "weekend"),
+            _ => {
+                let mut first = true;
+                for (index, name) in WEEKDAY_NAMES.iter().enumerate() {
+                    if self.contains(index as u32) {
+                        write!(f, "{}{}", if first { "" } else { "," }, name)?;
+                        first = false;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Alarm {
+    pub time: NaiveTime,
+    pub weekdays: Weekdays,
+    pub label: AlarmLabel,
+}
+
+impl Alarm {
+    /// Checks whether the alarm is due at the given minute (local time).
+    pub fn matches(&self, datetime: &NaiveDateTime) -> bool {
+        self.weekdays
+            .contains(datetime.weekday().num_days_from_monday())
+            && datetime.hour() == self.time.hour()
+            && datetime.minute() == self.time.minute()
+    }
+
+    /// Finds the first time the alarm is due after the given time (local time).
+    fn next_occurrence(&self, after: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut date = after.date();
+        // One more day than a week because the alarm may be due earlier on the same weekday
+        for _ in 0..8 {
+            let occurrence = date.and_time(self.time);
+            if occurrence > *after
+                && self
+                    .weekdays
+                    .contains(date.weekday().num_days_from_monday())
+            {
+                return Some(occurrence);
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn serialize(&self, writer: &mut Writer) {
+        writer.write_u8(self.time.hour() as u8);
+        writer.write_u8(self.time.minute() as u8);
+        writer.write_u8(self.weekdays.0);
+        self.label.serialize(writer);
+    }
+
+    /// Reads an alarm. Returns 'None' at the end of the payload and 'Some(None)' if the alarm
+    /// is invalid (it is read completely anyway).
+    fn deserialize(reader: &mut Reader) -> Option<Option<Self>> {
+        let hour = reader.read_u8()?;
+        let minute = reader.read_u8()?;
+        let weekdays = reader.read_u8()?;
+        let label_len = reader.read_u8()? as usize;
+        let label = reader.read_bytes(label_len)?;
+
+        Some(Self::from_raw(hour, minute, weekdays, label))
+    }
+
+    fn from_raw(hour: u8, minute: u8, weekdays: u8, label: &[u8]) -> Option<Self> {
+        Some(Self {
+            time: NaiveTime::from_hms_opt(hour as u32, minute as u32, 0)?,
+            weekdays: Weekdays(weekdays & Weekdays::ALL.0),
+            label: AlarmLabel::new(core::str::from_utf8(label).ok()?)?,
+        })
+    }
+}
+
+pub fn serialize_alarms(alarms: &Alarms, writer: &mut Writer) {
+    writer.write_u8(alarms.iter().flatten().count() as u8);
+    for alarm in alarms.iter().flatten() {
+        alarm.serialize(writer);
+    }
+}
+
+/// Returns 'None' if any of the alarms is invalid. All of them are read anyway, so the fields
+/// following them are read from the right position.
+pub fn deserialize_alarms(reader: &mut Reader) -> Option<Alarms> {
+    let mut alarms = [None; MAX_ALARMS];
+
+    let count = reader.read_u8()? as usize;
+    let mut valid = count <= MAX_ALARMS;
+    for index in 0..count {
+        match (Alarm::deserialize(reader)?, alarms.get_mut(index)) {
+            (Some(alarm), Some(x)) => *x = Some(alarm),
+            _ => valid = false,
+        }
+    }
+
+    valid.then_some(alarms)
+}
+
+/// Programs the RTC to the next due alarm.
+///
+/// Must be called whenever the alarms, the time or the time zone have been changed and after an
+/// alarm went off. If no alarm is configured, the alarm of the RTC keeps its last value (a match
+/// is ignored then). Succeeds without programming if the RTC has no alarms.
+pub fn program_next<R: TimeSource>(
+    rtc: &mut R,
+    alarms: &Alarms,
+    time_zone: &TimeZone,
+) -> Result<(), RtcError> {
+    let now = rtc::get_local_datetime(rtc, time_zone)?;
+    match rtc.clear_alarm(AlarmSlot::Next) {
+        Err(RtcError::Unsupported) => return Ok(()),
+        result => result?,
+    }
+
+    let next = alarms
+        .iter()
+        .flatten()
+        .filter_map(|x| x.next_occurrence(&now))
+        .min();
+
+    // The RTC keeps UTC (the offset is taken at the time of the alarm because of daylight
+    // saving time)
+    if let Some(next) = next.map(|x| time_zone.local_to_utc(&x)) {
+        rtc.set_alarm(AlarmSlot::Next, &next)?;
+    }
+
+    Ok(())
+}
+
+/// Programs the RTC to go off after the snooze time (if supported) and returns that time (UTC,
+/// to be checked by software otherwise).
+pub fn program_snooze<R: TimeSource>(rtc: &mut R) -> Result<NaiveDateTime, RtcError> {
+    let now = rtc.get_datetime()?;
+    // Cannot fail because 0 is a valid second
+    let due = timezone::add_seconds(&now.with_second(0).unwrap(), SNOOZE_MINUTES as i64 * 60);
+
+    match rtc.set_alarm(AlarmSlot::Snooze, &due) {
+        Ok(()) | Err(RtcError::Unsupported) => Ok(due),
+        Err(x) => Err(x),
+    }
+}
+
+/// Action requested by the user while an alarm is ringing.
+#[derive(Clone, Copy)]
+pub enum AlarmRequest {
+    Snooze,
+    Dismiss,
+}
+
+static ALARM_REQUEST: Mutex<Cell<Option<AlarmRequest>>> = Mutex::new(Cell::new(None));
+
+/// Requests to stop the ringing alarm (ignored if no alarm is ringing).
+pub fn request(request: AlarmRequest) {
+    critical_section::with(|cs| ALARM_REQUEST.borrow(cs).set(Some(request)));
+}
+
+/// Takes the request that has been made since the last call (if any).
+pub fn take_request() -> Option<AlarmRequest> {
+    critical_section::with(|cs| ALARM_REQUEST.borrow(cs).take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ds323x::NaiveDate;
+
+    fn datetime(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 6 May 2024 is a Monday
+        NaiveDate::from_ymd_opt(2024, 5, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn alarm(hour: u32, minute: u32, weekdays: &str) -> Alarm {
+        Alarm {
+            time: NaiveTime::from_hms_opt(hour, minute, 0).unwrap(),
+            weekdays: Weekdays::parse(weekdays).unwrap(),
+            label: AlarmLabel::new("").unwrap(),
+        }
+    }
+
+    #[test]
+    fn daily_alarm_is_due_today_or_after_midnight() {
+        let alarm = alarm(7, 0, "daily");
+        assert_eq!(
+            alarm.next_occurrence(&datetime(6, 6, 59)),
+            Some(datetime(6, 7, 0))
+        );
+        // Not again in the same minute
+        assert_eq!(
+            alarm.next_occurrence(&datetime(6, 7, 0)),
+            Some(datetime(7, 7, 0))
+        );
+        assert_eq!(
+            alarm.next_occurrence(&datetime(6, 23, 59)),
+            Some(datetime(7, 7, 0))
+        );
+    }
+
+    #[test]
+    fn alarm_on_workdays_skips_the_weekend() {
+        let alarm = alarm(6, 30, "weekdays");
+        // Friday after the alarm
+        assert_eq!(
+            alarm.next_occurrence(&datetime(10, 8, 0)),
+            Some(datetime(13, 6, 30))
+        );
+    }
+
+    #[test]
+    fn weekly_alarm_is_due_up_to_a_week_later() {
+        let alarm = alarm(7, 0, "wed");
+        assert_eq!(
+            alarm.next_occurrence(&datetime(8, 7, 1)),
+            Some(datetime(15, 7, 0))
+        );
+        assert_eq!(
+            alarm.next_occurrence(&datetime(9, 0, 0)),
+            Some(datetime(15, 7, 0))
+        );
+
+        let never = Alarm {
+            weekdays: Weekdays(0),
+            ..alarm
+        };
+        assert_eq!(never.next_occurrence(&datetime(8, 0, 0)), None);
+    }
+
+    #[test]
+    fn alarm_matches_its_minute_on_its_weekdays() {
+        let alarm = alarm(7, 15, "sat,sun");
+        assert!(alarm.matches(&datetime(11, 7, 15)));
+        assert!(!alarm.matches(&datetime(11, 7, 16)));
+        assert!(!alarm.matches(&datetime(10, 7, 15)));
+    }
+
+    #[test]
+    fn alarms_are_read_completely_even_if_invalid() {
+        let data = [
+            2, // Count
+            25, 0, 0x7F, 0, // Invalid hour
+            7, 30, 0x1F, 1, b'x', // Valid
+            0xAB, // Next field
+        ];
+        let mut reader = Reader::new(&data);
+        assert!(deserialize_alarms(&mut reader).is_none());
+        assert_eq!(reader.read_u8(), Some(0xAB));
+
+        let mut buffer = [0; 64];
+        let mut writer = Writer::new(&mut buffer);
+        let alarms = [Some(alarm(7, 30, "weekdays")); MAX_ALARMS];
+        serialize_alarms(&alarms, &mut writer);
+        writer.write_u8(0xAB);
+        let mut reader = Reader::new(&buffer);
+        let read = deserialize_alarms(&mut reader).unwrap();
+        assert!(read
+            .iter()
+            .all(|x| x.is_some_and(|x| x.time == alarms[0].unwrap().time)));
+        assert_eq!(reader.read_u8(), Some(0xAB));
+    }
+
+    #[test]
+    fn weekdays_are_parsed_and_formatted() {
+        assert!(Weekdays::parse("mon,fri").unwrap() == Weekdays(0x11));
+        assert!(Weekdays::parse("mon,sat,sun,tue,wed,thu,fri").unwrap() == Weekdays::ALL);
+        assert!(Weekdays::parse("monday").is_none());
+        assert!(Weekdays::parse("").is_none());
+
+        assert_eq!(format!("{}", Weekdays(0x11)), "mon,fri");
+        assert_eq!(format!("{}", Weekdays(0x60)), "weekend");
+    }
+}
@@ -0,0 +1,157 @@
+//! Brightness of the display, either fixed or adjusted to the ambient light measured by the
+//! light sensor.
+
+use core::fmt;
+
+pub const MAX_BRIGHTNESS: u8 = 100;
+
+/// Lowest brightness used in the automatic mode (so the display never goes dark completely).
+const MIN_AUTO_BRIGHTNESS: u8 = 5;
+
+/// Minimum change of the brightness calculated from the ambient light before it is applied
+/// (avoids flickering between two values at the edge).
+const HYSTERESIS: u8 = 8;
+
+/// Maximum light level (12-bit ADC).
+const MAX_LIGHT_LEVEL: u32 = 4095;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Brightness {
+    /// Brightness in percent (0 switches the display off).
+    Fixed(u8),
+    /// Adjusted to the ambient light.
+    Auto,
+}
+
+impl Brightness {
+    pub fn to_raw(self) -> u8 {
+        match self {
+            Brightness::Fixed(percent) => percent,
+            Brightness::Auto => 0xFF,
+        }
+    }
+
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0xFF => Some(Brightness::Auto),
+            x if x <= MAX_BRIGHTNESS => Some(Brightness::Fixed(x)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Brightness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Brightness::Fixed(percent) => write!(f, "{}%", percent),
+            Brightness::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Calculates the brightness from the (noisy) readings of the light sensor.
+pub struct AutoBrightness {
+    /// Low-pass filtered light level times 8 (or 'None' before the first reading). Scaled so the
+    /// filter reaches the limits of the light level despite the integer arithmetic.
+    filtered_level: Option<u32>,
+    brightness: u8,
+}
+
+impl AutoBrightness {
+    pub fn new() -> Self {
+        Self {
+            filtered_level: None,
+            brightness: MAX_BRIGHTNESS,
+        }
+    }
+
+    /// Feeds a new reading of the light sensor and returns the brightness to be used.
+    pub fn update(&mut self, light_level: u16) -> u8 {
+        let light_level = light_level as u32;
+        let scaled_level = match self.filtered_level {
+            Some(x) => x - x / 8 + light_level,
+            None => light_level * 8,
+        };
+        let filtered_level = scaled_level / 8;
+
+        let range = (MAX_BRIGHTNESS - MIN_AUTO_BRIGHTNESS) as u32;
+        let target = MIN_AUTO_BRIGHTNESS
+            + (filtered_level.min(MAX_LIGHT_LEVEL) * range / MAX_LIGHT_LEVEL) as u8;
+
+        // The limits are always reached (even if the change is smaller than the hysteresis)
+        if self.filtered_level.is_none()
+            || target.abs_diff(self.brightness) >= HYSTERESIS
+            || target == MIN_AUTO_BRIGHTNESS
+            || target == MAX_BRIGHTNESS
+        {
+            self.brightness = target;
+        }
+        self.filtered_level = Some(scaled_level);
+
+        self.brightness
+    }
+}
+
+impl Default for AutoBrightness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_reading_is_applied_directly() {
+        assert_eq!(AutoBrightness::new().update(0), MIN_AUTO_BRIGHTNESS);
+        assert_eq!(AutoBrightness::new().update(4095), MAX_BRIGHTNESS);
+        assert_eq!(AutoBrightness::new().update(2048), 52);
+    }
+
+    #[test]
+    fn small_changes_are_ignored() {
+        let mut auto_brightness = AutoBrightness::new();
+        auto_brightness.update(2048);
+
+        // Moves the target by less than the hysteresis
+        for _ in 0..100 {
+            assert_eq!(auto_brightness.update(2300), 52);
+        }
+    }
+
+    #[test]
+    fn large_changes_are_applied_in_steps() {
+        let mut auto_brightness = AutoBrightness::new();
+        let mut brightness = auto_brightness.update(2048);
+
+        for light_level in [4095, 0] {
+            let limit = if light_level > 0 {
+                MAX_BRIGHTNESS
+            } else {
+                MIN_AUTO_BRIGHTNESS
+            };
+            for _ in 0..100 {
+                let new_brightness = auto_brightness.update(light_level);
+                // The limits are reached in the end even if closer than the hysteresis
+                if new_brightness != brightness && new_brightness != limit {
+                    assert!(new_brightness.abs_diff(brightness) >= HYSTERESIS);
+                }
+                brightness = new_brightness;
+            }
+            assert_eq!(brightness, limit);
+        }
+    }
+
+    #[test]
+    fn brightness_is_converted_from_and_to_raw() {
+        for brightness in [
+            Brightness::Auto,
+            Brightness::Fixed(0),
+            Brightness::Fixed(100),
+        ] {
+            assert!(Brightness::from_raw(brightness.to_raw()) == Some(brightness));
+        }
+        assert!(Brightness::from_raw(MAX_BRIGHTNESS + 1).is_none());
+    }
+}
@@ -0,0 +1,67 @@
+//! Events of the buttons, queued by the button task (or the simulator) and taken by the display
+//! FSM.
+
+// Concurrency (critical sections provided by the firmware or the host)
+use core::cell::RefCell;
+use critical_section::Mutex;
+
+const EVENT_QUEUE_LEN: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Button {
+    Set,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ButtonEvent {
+    /// Released before a long press has been detected.
+    Press(Button),
+    /// Held down for 'LONG_PRESS_TIME' (not followed by 'Press' when released).
+    LongPress(Button),
+    /// Still held down after a long press (sent every 'REPEAT_PERIOD').
+    Repeat(Button),
+}
+
+/// Ring buffer of events not taken yet (new events are dropped if it is full).
+struct EventQueue {
+    events: [Option<ButtonEvent>; EVENT_QUEUE_LEN],
+    head: usize,
+    len: usize,
+}
+
+impl EventQueue {
+    fn push(&mut self, event: ButtonEvent) {
+        if self.len < EVENT_QUEUE_LEN {
+            self.events[(self.head + self.len) % EVENT_QUEUE_LEN] = Some(event);
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<ButtonEvent> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % EVENT_QUEUE_LEN;
+        self.len -= 1;
+        event
+    }
+}
+
+static EVENTS: Mutex<RefCell<EventQueue>> = Mutex::new(RefCell::new(EventQueue {
+    events: [None; EVENT_QUEUE_LEN],
+    head: 0,
+    len: 0,
+}));
+
+/// Takes the oldest event that has not been taken yet (if any).
+pub fn take_event() -> Option<ButtonEvent> {
+    critical_section::with(|cs| EVENTS.borrow(cs).borrow_mut().pop())
+}
+
+pub fn push_event(event: ButtonEvent) {
+    critical_section::with(|cs| EVENTS.borrow(cs).borrow_mut().push(event));
+}
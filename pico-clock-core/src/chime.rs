@@ -0,0 +1,82 @@
+//! Hourly chime (a short melody at every full hour except during the quiet hours).
+
+use core::fmt;
+
+/// Hours in which the chime is not played, from 'start' (inclusive) to 'end' (exclusive).
+/// Wraps around midnight if 'start' is greater than 'end'.
+#[derive(Clone, Copy)]
+pub struct QuietHours {
+    pub start: u8,
+    pub end: u8,
+}
+
+impl QuietHours {
+    /// Parses e.g. '22-7' (hours 0 to 23). Returns 'None' if invalid.
+    pub fn parse(input: &str) -> Option<Self> {
+        let (start, end) = input.split_once('-')?;
+        let quiet_hours = Self {
+            start: start.parse().ok()?,
+            end: end.parse().ok()?,
+        };
+
+        if quiet_hours.start < 24 && quiet_hours.end < 24 {
+            Some(quiet_hours)
+        } else {
+            None
+        }
+    }
+
+    /// Checks whether the given hour (0 to 23) is a quiet hour.
+    pub fn contains(&self, hour: u8) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&hour)
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:00-{:02}:00", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_hours_may_wrap_around_midnight() {
+        let quiet_hours = QuietHours::parse("22-7").unwrap();
+
+        assert!(!quiet_hours.contains(21));
+        assert!(quiet_hours.contains(22));
+        assert!(quiet_hours.contains(23));
+        assert!(quiet_hours.contains(0));
+        assert!(quiet_hours.contains(6));
+        assert!(!quiet_hours.contains(7));
+        assert!(!quiet_hours.contains(12));
+    }
+
+    #[test]
+    fn quiet_hours_may_be_within_a_day() {
+        let quiet_hours = QuietHours::parse("0-6").unwrap();
+
+        assert!(quiet_hours.contains(0));
+        assert!(quiet_hours.contains(5));
+        assert!(!quiet_hours.contains(6));
+        assert!(!quiet_hours.contains(23));
+
+        // Same start and end means no quiet hours at all
+        assert!(!QuietHours::parse("8-8").unwrap().contains(8));
+    }
+
+    #[test]
+    fn invalid_quiet_hours_are_rejected() {
+        assert!(QuietHours::parse("22").is_none());
+        assert!(QuietHours::parse("24-7").is_none());
+        assert!(QuietHours::parse("22-24").is_none());
+        assert!(QuietHours::parse("-1-7").is_none());
+    }
+}
@@ -0,0 +1,264 @@
+//! Parses date/time input of the form 'HH:MM', 'HH:MM:SS' or 'YYYY-MM-DDTHH:MM:SS', Unix
+//! timestamps in milliseconds and durations of the form 'MM:SS' or 'HH:MM:SS'.
+
+use ds323x::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+pub enum DateTimeInput {
+    /// Only the time of day has been given (the date shall be kept).
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+}
+
+#[derive(Debug)]
+pub enum DateTimeParseError {
+    InvalidFormat,
+    /// Contains the name of the field being out of range.
+    OutOfRange(&'static str),
+}
+
+/// Range of years supported by the RTC.
+const MIN_YEAR: u32 = 2000;
+const MAX_YEAR: u32 = 2099;
+
+const SECONDS_PER_DAY: u64 = 24 * 3600;
+/// Day number of 1970-01-01 (0001-01-01 being day 1).
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+pub fn parse(input: &str) -> Result<DateTimeInput, DateTimeParseError> {
+    if let Some((date, time)) = input.split_once('T') {
+        let date = parse_date(date)?;
+        let time = parse_time(time, true)?;
+        Ok(DateTimeInput::DateTime(date.and_time(time)))
+    } else {
+        let with_seconds = input.matches(':').count() == 2;
+        Ok(DateTimeInput::Time(parse_time(input, with_seconds)?))
+    }
+}
+
+fn parse_date(input: &str) -> Result<NaiveDate, DateTimeParseError> {
+    let mut iter = input.split('-');
+    let year = parse_field(iter.next(), 4, 4)?;
+    let month = parse_field(iter.next(), 2, 2)?;
+    let day = parse_field(iter.next(), 2, 2)?;
+    if iter.next().is_some() {
+        return Err(DateTimeParseError::InvalidFormat);
+    }
+
+    if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+        return Err(DateTimeParseError::OutOfRange("year"));
+    }
+    if !(1..=12).contains(&month) {
+        return Err(DateTimeParseError::OutOfRange("month"));
+    }
+
+    // Also checks the number of days of the particular month (including leap years)
+    NaiveDate::from_ymd_opt(year as i32, month, day).ok_or(DateTimeParseError::OutOfRange("day"))
+}
+
+fn parse_time(input: &str, with_seconds: bool) -> Result<NaiveTime, DateTimeParseError> {
+    let mut iter = input.split(':');
+    let hours = parse_field(iter.next(), 1, 2)?;
+    let minutes = parse_field(iter.next(), 2, 2)?;
+    let seconds = if with_seconds {
+        parse_field(iter.next(), 2, 2)?
+    } else {
+        0
+    };
+    if iter.next().is_some() {
+        return Err(DateTimeParseError::InvalidFormat);
+    }
+
+    if hours > 23 {
+        return Err(DateTimeParseError::OutOfRange("hours"));
+    }
+    if minutes > 59 {
+        return Err(DateTimeParseError::OutOfRange("minutes"));
+    }
+    if seconds > 59 {
+        return Err(DateTimeParseError::OutOfRange("seconds"));
+    }
+
+    // Cannot fail anymore after the range checks above
+    NaiveTime::from_hms_opt(hours, minutes, seconds).ok_or(DateTimeParseError::InvalidFormat)
+}
+
+/// Parses a duration and returns it in seconds (up to 99 hours).
+pub fn parse_duration(input: &str) -> Result<u32, DateTimeParseError> {
+    let mut iter = input.split(':');
+    let (hours, minutes, seconds) = match input.matches(':').count() {
+        1 => (
+            0,
+            parse_field(iter.next(), 1, 2)?,
+            parse_field(iter.next(), 2, 2)?,
+        ),
+        2 => (
+            parse_field(iter.next(), 1, 2)?,
+            parse_field(iter.next(), 2, 2)?,
+            parse_field(iter.next(), 2, 2)?,
+        ),
+        _ => return Err(DateTimeParseError::InvalidFormat),
+    };
+
+    if minutes > 59 {
+        return Err(DateTimeParseError::OutOfRange("minutes"));
+    }
+    if seconds > 59 {
+        return Err(DateTimeParseError::OutOfRange("seconds"));
+    }
+
+    Ok(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Parses a Unix timestamp in milliseconds.
+pub fn parse_unix_millis(input: &str) -> Result<u64, DateTimeParseError> {
+    // Up to the year 33658
+    if input.is_empty() || input.len() > 15 || !input.bytes().all(|x| x.is_ascii_digit()) {
+        return Err(DateTimeParseError::InvalidFormat);
+    }
+    // Cannot fail because of the number of digits
+    Ok(input.parse().unwrap())
+}
+
+/// Converts a Unix timestamp in seconds to a date and time (UTC) supported by the RTC.
+pub fn from_unix_seconds(seconds: u64) -> Result<NaiveDateTime, DateTimeParseError> {
+    let days = (seconds / SECONDS_PER_DAY) as i32;
+    let date = NaiveDate::from_num_days_from_ce_opt(UNIX_EPOCH_DAYS_FROM_CE + days)
+        .filter(|x| (MIN_YEAR as i32..=MAX_YEAR as i32).contains(&x.year()))
+        .ok_or(DateTimeParseError::OutOfRange("year"))?;
+    // Cannot fail because the seconds are within a day
+    let time = NaiveTime::from_num_seconds_from_midnight_opt((seconds % SECONDS_PER_DAY) as u32, 0)
+        .unwrap();
+
+    Ok(date.and_time(time))
+}
+
+/// Converts a date and time (UTC) to a Unix timestamp in seconds.
+pub fn to_unix_seconds(datetime: &NaiveDateTime) -> i64 {
+    let days = datetime.date().num_days_from_ce() - UNIX_EPOCH_DAYS_FROM_CE;
+    days as i64 * SECONDS_PER_DAY as i64 + datetime.num_seconds_from_midnight() as i64
+}
+
+/// Parses a decimal field consisting of 'min_digits' to 'max_digits' digits.
+fn parse_field(
+    field: Option<&str>,
+    min_digits: usize,
+    max_digits: usize,
+) -> Result<u32, DateTimeParseError> {
+    let field = field.ok_or(DateTimeParseError::InvalidFormat)?;
+    if field.len() < min_digits
+        || field.len() > max_digits
+        || !field.bytes().all(|x| x.is_ascii_digit())
+    {
+        return Err(DateTimeParseError::InvalidFormat);
+    }
+
+    field.parse().map_err(|_| DateTimeParseError::InvalidFormat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn out_of_range<T>(result: Result<T, DateTimeParseError>) -> Option<&'static str> {
+        match result {
+            Err(DateTimeParseError::OutOfRange(field)) => Some(field),
+            _ => None,
+        }
+    }
+
+    fn invalid_format<T>(result: Result<T, DateTimeParseError>) -> bool {
+        matches!(result, Err(DateTimeParseError::InvalidFormat))
+    }
+
+    #[test]
+    fn time_may_be_given_with_or_without_seconds() {
+        let Ok(DateTimeInput::Time(time)) = parse("7:05") else {
+            panic!()
+        };
+        assert_eq!(time, NaiveTime::from_hms_opt(7, 5, 0).unwrap());
+
+        let Ok(DateTimeInput::Time(time)) = parse("23:59:59") else {
+            panic!()
+        };
+        assert_eq!(time, NaiveTime::from_hms_opt(23, 59, 59).unwrap());
+    }
+
+    #[test]
+    fn date_and_time_are_separated_by_t() {
+        let Ok(DateTimeInput::DateTime(datetime)) = parse("2024-02-29T12:34:56") else {
+            panic!()
+        };
+        assert_eq!(
+            datetime,
+            NaiveDate::from_ymd_opt(2024, 2, 29)
+                .unwrap()
+                .and_hms_opt(12, 34, 56)
+                .unwrap()
+        );
+
+        // The seconds are mandatory together with a date
+        assert!(invalid_format(parse("2024-02-29T12:34")));
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(invalid_format(parse("")));
+        assert!(invalid_format(parse("123:00")));
+        assert!(invalid_format(parse("12:5")));
+        assert!(invalid_format(parse("12:34:56:00")));
+        assert!(invalid_format(parse("+1:00")));
+        assert!(invalid_format(parse("24-01-01T00:00:00")));
+        assert!(invalid_format(parse("2024-01-01-01T00:00:00")));
+    }
+
+    #[test]
+    fn fields_out_of_range_are_named() {
+        assert_eq!(out_of_range(parse("24:00")), Some("hours"));
+        assert_eq!(out_of_range(parse("12:60")), Some("minutes"));
+        assert_eq!(out_of_range(parse("12:00:60")), Some("seconds"));
+        assert_eq!(out_of_range(parse("1999-12-31T00:00:00")), Some("year"));
+        assert_eq!(out_of_range(parse("2100-01-01T00:00:00")), Some("year"));
+        assert_eq!(out_of_range(parse("2024-13-01T00:00:00")), Some("month"));
+        assert_eq!(out_of_range(parse("2023-02-29T00:00:00")), Some("day"));
+        assert_eq!(out_of_range(parse("2024-04-31T00:00:00")), Some("day"));
+    }
+
+    #[test]
+    fn duration_is_returned_in_seconds() {
+        assert_eq!(parse_duration("5:00").unwrap(), 300);
+        assert_eq!(parse_duration("1:02:03").unwrap(), 3723);
+        assert_eq!(parse_duration("99:59:59").unwrap(), 359_999);
+
+        assert!(invalid_format(parse_duration("300")));
+        assert!(invalid_format(parse_duration("100:00:00")));
+        assert!(invalid_format(parse_duration("1:2")));
+        assert_eq!(out_of_range(parse_duration("60:00")), Some("minutes"));
+        assert_eq!(out_of_range(parse_duration("1:00:60")), Some("seconds"));
+    }
+
+    #[test]
+    fn unix_seconds_are_converted_both_ways() {
+        let expected = datetime(2024, 5, 6, 12, 34);
+        assert_eq!(to_unix_seconds(&expected), 1_714_998_840);
+        assert_eq!(from_unix_seconds(1_714_998_840).unwrap(), expected);
+
+        // Limits of the RTC
+        let first = datetime(2000, 1, 1, 0, 0);
+        assert_eq!(from_unix_seconds(946_684_800).unwrap(), first);
+        assert_eq!(out_of_range(from_unix_seconds(946_684_799)), Some("year"));
+        let last = NaiveDate::from_ymd_opt(2099, 12, 31)
+            .unwrap()
+            .and_hms_opt(23, 59, 59)
+            .unwrap();
+        let seconds = to_unix_seconds(&last) as u64;
+        assert_eq!(from_unix_seconds(seconds).unwrap(), last);
+        assert_eq!(out_of_range(from_unix_seconds(seconds + 1)), Some("year"));
+    }
+}
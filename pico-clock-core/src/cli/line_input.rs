@@ -58,3 +58,47 @@ impl<'a, const MAX_LEN: usize> LineInput<MAX_LEN> {
         }
     }
 }
+
+impl<const MAX_LEN: usize> Default for LineInput<MAX_LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printable_characters_are_echoed_until_the_line_is_complete() {
+        let mut input = LineInput::<8>::new();
+
+        assert!(matches!(input.feed(b'h'), LineInputResult::Echo(b'h')));
+        assert!(matches!(input.feed(b'i'), LineInputResult::Echo(b'i')));
+        assert!(matches!(input.feed(b'\r'), LineInputResult::Complete("hi")));
+        // Starts over after a complete line
+        assert!(matches!(input.feed(b'\r'), LineInputResult::Complete("")));
+    }
+
+    #[test]
+    fn backspace_removes_the_last_character() {
+        let mut input = LineInput::<8>::new();
+
+        assert!(matches!(input.feed(0x7F), LineInputResult::None));
+        input.feed(b'a');
+        input.feed(b'b');
+        assert!(matches!(input.feed(0x7F), LineInputResult::Echo(0x7F)));
+        assert!(matches!(input.feed(b'\r'), LineInputResult::Complete("a")));
+    }
+
+    #[test]
+    fn characters_beyond_the_maximum_length_are_ignored() {
+        let mut input = LineInput::<2>::new();
+
+        input.feed(b'a');
+        input.feed(b'b');
+        assert!(matches!(input.feed(b'c'), LineInputResult::None));
+        assert!(matches!(input.feed(b'\n'), LineInputResult::None));
+        assert!(matches!(input.feed(b'\r'), LineInputResult::Complete("ab")));
+    }
+}
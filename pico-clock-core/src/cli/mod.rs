@@ -0,0 +1,4 @@
+//! Parts of the command line interface that do not depend on the serial port.
+
+pub mod datetime;
+pub mod line_input;
@@ -0,0 +1,276 @@
+#![allow(dead_code)] // Not all functionality here must be used.
+
+/// Maximum number of columns (one bit per column in a row of the raw data).
+pub const RAW_WIDTH: usize = 32;
+/// Maximum number of rows.
+pub const RAW_HEIGHT: usize = 8;
+pub type RawData = [u32; RAW_HEIGHT];
+
+pub const DOT_MATRIX_WIDTH: usize = 22;
+pub const DOT_MATRIX_HEIGHT: usize = 7;
+pub type DotMatrixData = [u32; DOT_MATRIX_HEIGHT];
+
+/// Layout of a display driven by shift registers (columns) and address lines (rows).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    /// Number of bits shifted out per row (at most RAW_WIDTH).
+    pub raw_width: usize,
+    /// Number of rows (at most RAW_HEIGHT).
+    pub raw_height: usize,
+    /// Number of columns connected to LEDs, starting with the lowest bit (the other bits are
+    /// shifted out as 0).
+    pub active_columns: usize,
+    /// Column of the leftmost dot of the dot matrix.
+    pub dot_matrix_x_offset: usize,
+    /// Row of the top dot of the dot matrix.
+    pub dot_matrix_y_offset: usize,
+}
+
+impl Geometry {
+    /// Display of the "Pico Clock Green" kit: 32 bits per row, but only the lower 24 are
+    /// connected to a column. The indicators take the top row and the two leftmost columns.
+    pub const PICO_CLOCK_GREEN: Self = Self {
+        raw_width: 32,
+        raw_height: 8,
+        active_columns: 24,
+        dot_matrix_x_offset: 2,
+        dot_matrix_y_offset: 1,
+    };
+
+    /// Mask of the bits of a row that are connected to a column.
+    pub fn active_mask(&self) -> u32 {
+        u32::MAX >> (RAW_WIDTH - self.active_columns)
+    }
+
+    /// Panics if the geometry exceeds the raw data or cannot contain the dot matrix.
+    pub fn validate(&self) {
+        assert!(self.raw_width <= RAW_WIDTH && self.raw_height <= RAW_HEIGHT);
+        assert!(self.active_columns > 0 && self.active_columns <= self.raw_width);
+        assert!(self.dot_matrix_x_offset + DOT_MATRIX_WIDTH <= self.active_columns);
+        assert!(self.dot_matrix_y_offset + DOT_MATRIX_HEIGHT <= self.raw_height);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Indicator {
+    Mon,
+    Tues,
+    Wed,
+    Thur,
+    Fri,
+    Sat,
+    Sun,
+    MoveOn,
+    AlarmOn,
+    CountDown,
+    DegreeC,
+    DegreeF,
+    AM,
+    PM,
+    CountUp,
+    Hourly,
+    AutoLight,
+}
+
+impl Indicator {
+    pub const ALL: [Indicator; 17] = [
+        Indicator::Mon,
+        Indicator::Tues,
+        Indicator::Wed,
+        Indicator::Thur,
+        Indicator::Fri,
+        Indicator::Sat,
+        Indicator::Sun,
+        Indicator::MoveOn,
+        Indicator::AlarmOn,
+        Indicator::CountDown,
+        Indicator::DegreeC,
+        Indicator::DegreeF,
+        Indicator::AM,
+        Indicator::PM,
+        Indicator::CountUp,
+        Indicator::Hourly,
+        Indicator::AutoLight,
+    ];
+
+    /// Row and mask of the bits of the raw data lighting the indicator (of the "Pico Clock
+    /// Green" kit).
+    pub fn position(self) -> (usize, u32) {
+        match self {
+            Indicator::Mon => (0, 0x00000018),
+            Indicator::Tues => (0, 0x000000C0),
+            Indicator::Wed => (0, 0x00000600),
+            Indicator::Thur => (0, 0x00003000),
+            Indicator::Fri => (0, 0x00018000),
+            Indicator::Sat => (0, 0x000C0000),
+            Indicator::Sun => (0, 0x00600000),
+            Indicator::MoveOn => (0, 0x00000003),
+            Indicator::AlarmOn => (1, 0x00000003),
+            Indicator::CountDown => (2, 0x00000003),
+            Indicator::DegreeF => (3, 0x00000001),
+            Indicator::DegreeC => (3, 0x00000002),
+            Indicator::AM => (4, 0x00000001),
+            Indicator::PM => (4, 0x00000002),
+            Indicator::CountUp => (5, 0x00000003),
+            Indicator::Hourly => (6, 0x00000003),
+            Indicator::AutoLight => (7, 0x00000003),
+        }
+    }
+}
+
+pub struct Data {
+    pub raw_data: RawData,
+    geometry: Geometry,
+}
+
+impl Data {
+    pub fn new() -> Self {
+        Self::with_geometry(Geometry::PICO_CLOCK_GREEN)
+    }
+
+    pub fn with_geometry(geometry: Geometry) -> Self {
+        geometry.validate();
+        Self {
+            raw_data: [0; RAW_HEIGHT],
+            geometry,
+        }
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    pub fn clear(&mut self) {
+        self.raw_data = [0; RAW_HEIGHT];
+    }
+
+    pub fn set_dot_matrix(&mut self, data: &DotMatrixData) {
+        const DATA_MASK: u32 = (1 << DOT_MATRIX_WIDTH) - 1;
+        let x_offset = self.geometry.dot_matrix_x_offset;
+        let y_offset = self.geometry.dot_matrix_y_offset;
+        let raw_data_mask = DATA_MASK << x_offset;
+
+        for (row, row_data) in data.iter().enumerate() {
+            // Ensure no other bits are set
+            assert!(row_data & !DATA_MASK == 0);
+
+            let raw_row_data = &mut self.raw_data[row + y_offset];
+            *raw_row_data = *raw_row_data & !raw_data_mask | (row_data << x_offset);
+        }
+    }
+
+    pub fn set_indicator(&mut self, indicator: Indicator, state: bool) {
+        let (row, mask) = indicator.position();
+
+        let raw_row_data = &mut self.raw_data[row];
+        *raw_row_data = *raw_row_data & !mask | (if state { mask } else { 0 });
+    }
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weekday_indicators_are_in_the_top_row() {
+        let masks = [
+            (Indicator::Mon, 0x0000_0018),
+            (Indicator::Tues, 0x0000_00C0),
+            (Indicator::Wed, 0x0000_0600),
+            (Indicator::Thur, 0x0000_3000),
+            (Indicator::Fri, 0x0001_8000),
+            (Indicator::Sat, 0x000C_0000),
+            (Indicator::Sun, 0x0060_0000),
+        ];
+
+        for (indicator, mask) in masks {
+            let mut data = Data::new();
+            data.set_indicator(indicator, true);
+
+            let mut expected = [0; RAW_HEIGHT];
+            expected[0] = mask;
+            assert_eq!(data.raw_data, expected);
+        }
+    }
+
+    #[test]
+    fn all_indicators_light_the_columns_outside_of_the_dot_matrix() {
+        let mut data = Data::new();
+        for indicator in Indicator::ALL {
+            data.set_indicator(indicator, true);
+        }
+
+        assert_eq!(data.raw_data, [0x006D_B6DB, 3, 3, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn indicators_sharing_a_row_are_independent() {
+        let mut data = Data::new();
+        data.set_indicator(Indicator::DegreeC, true);
+        data.set_indicator(Indicator::DegreeF, true);
+        data.set_indicator(Indicator::DegreeF, false);
+        assert_eq!(data.raw_data[3], 0b10);
+
+        data.set_indicator(Indicator::AM, true);
+        data.set_indicator(Indicator::PM, true);
+        data.set_indicator(Indicator::AM, false);
+        assert_eq!(data.raw_data[4], 0b10);
+    }
+
+    #[test]
+    fn dot_matrix_is_next_to_the_indicators() {
+        let mut data = Data::new();
+        data.set_indicator(Indicator::AlarmOn, true);
+
+        let mut frame = [0; DOT_MATRIX_HEIGHT];
+        frame[0] = 0b1;
+        frame[DOT_MATRIX_HEIGHT - 1] = (1 << DOT_MATRIX_WIDTH) - 1;
+        data.set_dot_matrix(&frame);
+
+        assert_eq!(data.raw_data[0], 0);
+        assert_eq!(data.raw_data[1], 0b111);
+        assert_eq!(data.raw_data[RAW_HEIGHT - 1], 0x00FF_FFFC);
+
+        // The frame is replaced, the indicators are kept
+        data.set_dot_matrix(&[0; DOT_MATRIX_HEIGHT]);
+        assert_eq!(data.raw_data[1], 0b11);
+        assert_eq!(data.raw_data[RAW_HEIGHT - 1], 0);
+    }
+
+    #[test]
+    fn dot_matrix_follows_the_offsets_of_the_geometry() {
+        let mut data = Data::with_geometry(Geometry {
+            dot_matrix_x_offset: 0,
+            dot_matrix_y_offset: 0,
+            ..Geometry::PICO_CLOCK_GREEN
+        });
+
+        let mut frame = [0; DOT_MATRIX_HEIGHT];
+        frame[0] = 0b1;
+        data.set_dot_matrix(&frame);
+        assert_eq!(data.raw_data[0], 0b1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn geometry_without_room_for_the_dot_matrix_is_rejected() {
+        Data::with_geometry(Geometry {
+            active_columns: DOT_MATRIX_WIDTH,
+            ..Geometry::PICO_CLOCK_GREEN
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn dot_matrix_wider_than_the_display_is_rejected() {
+        let mut frame = [0; DOT_MATRIX_HEIGHT];
+        frame[0] = 1 << DOT_MATRIX_WIDTH;
+        Data::new().set_dot_matrix(&frame);
+    }
+}
@@ -0,0 +1,286 @@
+//! Driver for dot matrix displays with the columns driven by shift registers and the rows
+//! selected by address lines (e.g. the display of the "Pico Clock Green" kit).
+//!
+//! The output enable of the shift registers is not handled here (the firmware drives it by PWM
+//! to control the brightness).
+
+use super::data::{Geometry, RawData};
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{OutputPin, PinState};
+
+/// Output pins of the display. 'ADDRESS_LINES' address lines select up to
+/// 2^ADDRESS_LINES rows.
+pub struct Pins<P, const ADDRESS_LINES: usize> {
+    // SDI
+    pub serial_data: P,
+    // CLK
+    pub clock: P,
+    // LE
+    pub latch: P,
+    // A0..
+    pub address: [P; ADDRESS_LINES],
+}
+
+pub struct ShiftRegisterMatrix<P, D, const ADDRESS_LINES: usize> {
+    pins: Pins<P, ADDRESS_LINES>,
+    delay: D,
+    geometry: Geometry,
+    pulse_width_us: u32,
+}
+
+impl<P, D, const ADDRESS_LINES: usize> ShiftRegisterMatrix<P, D, ADDRESS_LINES>
+where
+    P: OutputPin,
+    D: DelayUs<u32>,
+{
+    /// The pins must already be configured as outputs. The clock and latch pulses are as short as
+    /// the pins can be toggled (see 'set_pulse_width').
+    pub fn new(pins: Pins<P, ADDRESS_LINES>, delay: D, geometry: Geometry) -> Self {
+        geometry.validate();
+        assert!(geometry.raw_height <= 1 << ADDRESS_LINES);
+
+        Self {
+            pins,
+            delay,
+            geometry,
+            pulse_width_us: 0,
+        }
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Sets the minimum width of the clock and latch pulses (for slow shift registers or long
+    /// wires).
+    pub fn set_pulse_width(&mut self, pulse_width_us: u32) {
+        self.pulse_width_us = pulse_width_us;
+    }
+
+    /// Busy-waits with the delay of the driver (e.g. while the output is disabled before
+    /// switching rows).
+    pub fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+
+    /// Selects the row and shifts its data into the shift registers, the lowest bit first. The
+    /// columns that are not active are shifted out as 0.
+    pub fn write_row(&mut self, row: usize, raw_data: &RawData) -> Result<(), P::Error> {
+        assert!(row < self.geometry.raw_height);
+
+        self.select_row(row)?;
+
+        let row_data = raw_data[row] & self.geometry.active_mask();
+        for column in 0..self.geometry.raw_width {
+            self.pins.clock.set_low()?;
+            self.pins
+                .serial_data
+                .set_state(PinState::from((row_data >> column) & 1 != 0))?;
+            self.pulse_delay();
+            self.pins.clock.set_high()?;
+            self.pulse_delay();
+        }
+
+        self.pins.latch.set_high()?;
+        self.pulse_delay();
+        self.pins.latch.set_low()
+    }
+
+    fn select_row(&mut self, row: usize) -> Result<(), P::Error> {
+        for (line, pin) in self.pins.address.iter_mut().enumerate() {
+            pin.set_state(PinState::from((row >> line) & 1 != 0))?;
+        }
+        Ok(())
+    }
+
+    fn pulse_delay(&mut self) {
+        if self.pulse_width_us > 0 {
+            self.delay.delay_us(self.pulse_width_us);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::data::RAW_HEIGHT;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Line {
+        SerialData,
+        Clock,
+        Latch,
+        Address(usize),
+    }
+
+    type Log = Rc<RefCell<Vec<(Line, bool)>>>;
+
+    #[derive(Debug, PartialEq)]
+    struct PinError;
+
+    struct MockPin {
+        line: Line,
+        log: Log,
+        broken: bool,
+    }
+
+    impl OutputPin for MockPin {
+        type Error = PinError;
+
+        fn set_low(&mut self) -> Result<(), PinError> {
+            self.set(false)
+        }
+
+        fn set_high(&mut self) -> Result<(), PinError> {
+            self.set(true)
+        }
+    }
+
+    impl MockPin {
+        fn set(&mut self, state: bool) -> Result<(), PinError> {
+            if self.broken {
+                return Err(PinError);
+            }
+            self.log.borrow_mut().push((self.line, state));
+            Ok(())
+        }
+    }
+
+    struct MockDelay {
+        total_us: u32,
+    }
+
+    impl DelayUs<u32> for MockDelay {
+        fn delay_us(&mut self, us: u32) {
+            self.total_us += us;
+        }
+    }
+
+    fn matrix(
+        geometry: Geometry,
+        broken: Option<Line>,
+    ) -> (ShiftRegisterMatrix<MockPin, MockDelay, 3>, Log) {
+        let log = Log::default();
+        let pin = |line| MockPin {
+            line,
+            log: log.clone(),
+            broken: broken == Some(line),
+        };
+        let pins = Pins {
+            serial_data: pin(Line::SerialData),
+            clock: pin(Line::Clock),
+            latch: pin(Line::Latch),
+            address: [
+                pin(Line::Address(0)),
+                pin(Line::Address(1)),
+                pin(Line::Address(2)),
+            ],
+        };
+        let matrix = ShiftRegisterMatrix::new(pins, MockDelay { total_us: 0 }, geometry);
+        (matrix, log)
+    }
+
+    /// Bits sampled by the shift registers (at the rising clock edges) in the order shifted in.
+    fn shifted_bits(log: &[(Line, bool)]) -> Vec<bool> {
+        let mut serial_data = false;
+        let mut bits = Vec::new();
+        for &(line, state) in log {
+            match line {
+                Line::SerialData => serial_data = state,
+                Line::Clock if state => bits.push(serial_data),
+                _ => {}
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn row_is_selected_shifted_lowest_bit_first_and_latched() {
+        let (mut matrix, log) = matrix(Geometry::PICO_CLOCK_GREEN, None);
+        let mut raw_data = [0; RAW_HEIGHT];
+        raw_data[5] = 0xFF00_0005;
+
+        matrix.write_row(5, &raw_data).unwrap();
+
+        let log = log.borrow();
+        assert_eq!(
+            log[..3],
+            [
+                (Line::Address(0), true),
+                (Line::Address(1), false),
+                (Line::Address(2), true),
+            ]
+        );
+
+        // The upper 8 bits are not connected to a column
+        let mut expected = [false; 32];
+        expected[0] = true;
+        expected[2] = true;
+        assert_eq!(shifted_bits(&log), expected);
+
+        assert_eq!(
+            log[log.len() - 2..],
+            [(Line::Latch, true), (Line::Latch, false)]
+        );
+    }
+
+    #[test]
+    fn raw_width_sets_the_number_of_bits_shifted() {
+        let (mut matrix, log) = matrix(
+            Geometry {
+                raw_width: 24,
+                ..Geometry::PICO_CLOCK_GREEN
+            },
+            None,
+        );
+
+        matrix.write_row(0, &[0x00FF_FFFF; RAW_HEIGHT]).unwrap();
+
+        assert_eq!(shifted_bits(&log.borrow()), [true; 24]);
+    }
+
+    #[test]
+    fn pulses_are_stretched_by_the_delay() {
+        let (mut matrix, _log) = matrix(Geometry::PICO_CLOCK_GREEN, None);
+        matrix.write_row(0, &[0; RAW_HEIGHT]).unwrap();
+        assert_eq!(matrix.delay.total_us, 0);
+
+        matrix.set_pulse_width(2);
+        matrix.write_row(0, &[0; RAW_HEIGHT]).unwrap();
+        // Clock low and high for each bit and the latch pulse
+        assert_eq!(matrix.delay.total_us, (32 * 2 + 1) * 2);
+    }
+
+    #[test]
+    fn pin_errors_are_propagated() {
+        for line in [Line::SerialData, Line::Clock, Line::Latch, Line::Address(2)] {
+            let (mut matrix, _log) = matrix(Geometry::PICO_CLOCK_GREEN, Some(line));
+            assert_eq!(matrix.write_row(0, &[0; RAW_HEIGHT]), Err(PinError));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rows_that_cannot_be_addressed_are_rejected() {
+        let log = Log::default();
+        let pin = |line| MockPin {
+            line,
+            log: log.clone(),
+            broken: false,
+        };
+        let pins = Pins {
+            serial_data: pin(Line::SerialData),
+            clock: pin(Line::Clock),
+            latch: pin(Line::Latch),
+            address: [pin(Line::Address(0)), pin(Line::Address(1))],
+        };
+        // 8 rows, but only 2 address lines
+        ShiftRegisterMatrix::new(pins, MockDelay { total_us: 0 }, Geometry::PICO_CLOCK_GREEN);
+    }
+}
@@ -0,0 +1,24 @@
+//! Content of the dot matrix LED display of the "Pico Clock Green" kit.
+
+pub mod data;
+pub mod matrix;
+pub mod swap;
+
+use data::Data;
+
+/// Display showing the content (refreshed by its driver independently of the updates).
+///
+/// The frame is composed by 'modify_data' and 'set_brightness' and shown as a whole by
+/// 'present'.
+pub trait DisplayOutput {
+    fn modify_data<F>(&mut self, func: F)
+    where
+        F: FnOnce(&mut Data);
+
+    /// Sets the brightness in percent (0 switches the display off).
+    fn set_brightness(&mut self, brightness: u8);
+
+    /// Shows the frame composed so far. May block until the refresh has switched to it, which
+    /// paces the caller to the refresh.
+    fn present(&mut self);
+}
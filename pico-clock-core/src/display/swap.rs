@@ -0,0 +1,139 @@
+//! Swapping of the front and back frame buffers between refresh cycles.
+//!
+//! The refresh (e.g. by DMA) starts each cycle at the start address of the front buffer, read
+//! from memory. To never switch buffers within a cycle, that address is only changed right after
+//! the start of a cycle (e.g. by the interrupt handler of the restart). So a swap takes two cycle
+//! starts: The first one schedules the back buffer for the next cycle, the second one confirms
+//! that it is shown.
+
+/// State of the swap, shared by the producer of the frames and the refresh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Swap {
+    front: usize,
+    requested: bool,
+    scheduled: bool,
+}
+
+/// Action to be taken at the start of a refresh cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CycleStart {
+    Nothing,
+    /// The buffer with the given index shall be used from the next cycle on.
+    Schedule(usize),
+    /// The former back buffer is shown from now on (the producer may be notified).
+    Swapped,
+}
+
+impl Swap {
+    /// Buffer 0 is in front.
+    pub const fn new() -> Self {
+        Self {
+            front: 0,
+            requested: false,
+            scheduled: false,
+        }
+    }
+
+    /// Index of the buffer being refreshed.
+    pub fn front(&self) -> usize {
+        self.front
+    }
+
+    /// Index of the buffer to compose the next frame in. Must not be written while a swap is
+    /// pending.
+    pub fn back(&self) -> usize {
+        1 - self.front
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.requested || self.scheduled
+    }
+
+    /// Requests the back buffer to be shown (after composing the frame in it).
+    pub fn request(&mut self) {
+        assert!(!self.is_pending());
+        self.requested = true;
+    }
+
+    /// Cancels a requested swap that has not been scheduled yet (e.g. if the refresh has
+    /// stopped). Returns false if it is too late.
+    pub fn cancel(&mut self) -> bool {
+        if self.scheduled {
+            return false;
+        }
+        self.requested = false;
+        true
+    }
+
+    /// To be called at the start of each refresh cycle (after the start address for that cycle
+    /// has been read).
+    pub fn cycle_start(&mut self) -> CycleStart {
+        if self.scheduled {
+            self.scheduled = false;
+            self.front = self.back();
+            CycleStart::Swapped
+        } else if self.requested {
+            self.requested = false;
+            self.scheduled = true;
+            CycleStart::Schedule(self.back())
+        } else {
+            CycleStart::Nothing
+        }
+    }
+}
+
+impl Default for Swap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_takes_effect_one_cycle_after_being_scheduled() {
+        let mut swap = Swap::new();
+        assert_eq!(swap.cycle_start(), CycleStart::Nothing);
+
+        swap.request();
+        assert!(swap.is_pending());
+        assert_eq!(swap.cycle_start(), CycleStart::Schedule(1));
+        // Still refreshing the old front buffer during this cycle
+        assert_eq!(swap.front(), 0);
+
+        assert_eq!(swap.cycle_start(), CycleStart::Swapped);
+        assert_eq!(swap.front(), 1);
+        assert_eq!(swap.back(), 0);
+        assert!(!swap.is_pending());
+
+        assert_eq!(swap.cycle_start(), CycleStart::Nothing);
+        swap.request();
+        assert_eq!(swap.cycle_start(), CycleStart::Schedule(0));
+        assert_eq!(swap.cycle_start(), CycleStart::Swapped);
+        assert_eq!(swap.front(), 0);
+    }
+
+    #[test]
+    fn only_unscheduled_swaps_can_be_cancelled() {
+        let mut swap = Swap::new();
+        swap.request();
+        assert!(swap.cancel());
+        assert!(!swap.is_pending());
+        assert_eq!(swap.cycle_start(), CycleStart::Nothing);
+
+        swap.request();
+        swap.cycle_start();
+        assert!(!swap.cancel());
+        assert_eq!(swap.cycle_start(), CycleStart::Swapped);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_cannot_be_requested_while_pending() {
+        let mut swap = Swap::new();
+        swap.request();
+        swap.request();
+    }
+}
@@ -0,0 +1,1394 @@
+//! State machine animating the content of the display (stepped periodically by the animation
+//! task).
+
+use crate::alarm::{self, AlarmLabel, AlarmRequest};
+use crate::brightness::{AutoBrightness, Brightness};
+use crate::buttons::{Button, ButtonEvent};
+use crate::display::data::{Data, DotMatrixData, Indicator, DOT_MATRIX_HEIGHT, DOT_MATRIX_WIDTH};
+use crate::display::DisplayOutput;
+use crate::hardware::Hardware;
+use crate::melody::Melody;
+use crate::menu::{Menu, MenuAction, MenuContext};
+use crate::night::{NightMode, NIGHT_BRIGHTNESS};
+use crate::playlist::{PlaylistEntry, Screen, MAX_PLAYLIST_LEN};
+use crate::rtc::{self, AlarmSlot, TimeSource};
+use crate::settings::{DateFormat, Settings};
+use crate::temperature::{self, TemperatureUnit};
+use crate::text::{TextBitmap, COMPACT_TEXT_HEIGHT};
+use crate::timer::{self, Timer, TimerMode, TimerRequest};
+
+use ds323x::{Datelike, Hours, NaiveDateTime, NaiveTime, Timelike};
+use embedded_time::duration::Milliseconds;
+
+/// Period in which 'next_step' is expected to be called.
+pub const STEP_PERIOD: Milliseconds = Milliseconds(120);
+
+/// Number of steps the time is shown when flashing while an alarm is ringing.
+const ALARM_FLASH_STEPS: u64 = 24;
+/// Number of steps of the alert when a countdown has reached zero.
+const TIMER_ALERT_STEPS: u64 = 48;
+/// Number of steps without button event after which the menu is left.
+const MENU_TIMEOUT_STEPS: u64 = 125;
+/// Number of steps the display is woken up from the night mode by a button.
+const NIGHT_WAKE_STEPS: u64 = 84;
+/// Half the period of the blinking indicator while the external RTC cannot be accessed.
+const ERROR_BLINK_PERIOD: Milliseconds = Milliseconds(500);
+
+enum DisplayFsmState {
+    /// Entry of the playlist with the given index.
+    Playlist(usize),
+    /// Flashing time and scrolling label of the alarm (until snoozed or dismissed).
+    Alarm(AlarmLabel),
+    /// Value of the countdown timer or stopwatch (instead of the playlist if it does not contain
+    /// a countdown screen).
+    Timer,
+    /// Flashing zero and playing a melody when the countdown has reached zero.
+    TimerAlert,
+    /// Menu operated by the buttons.
+    Menu(Menu),
+}
+
+/// Effect when the minute of the time screen changes.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Transition {
+    None,
+    /// The new time slides in from below.
+    Slide,
+    /// The rows of the new time replace the old ones from top to bottom.
+    FadeByRow,
+}
+
+impl Transition {
+    pub fn to_raw(self) -> u8 {
+        match self {
+            Transition::None => 0,
+            Transition::Slide => 1,
+            Transition::FadeByRow => 2,
+        }
+    }
+
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Transition::None),
+            1 => Some(Transition::Slide),
+            2 => Some(Transition::FadeByRow),
+            _ => None,
+        }
+    }
+}
+
+/// Time screen shown last (to detect a change of the minute).
+struct LastTime {
+    minutes: u8,
+    frame: DotMatrixData,
+}
+
+/// Running transition of the time screen.
+struct RunningTransition {
+    old_frame: DotMatrixData,
+    step: usize,
+}
+
+#[derive(PartialEq)]
+enum DisplayFsmStateResult {
+    Continue,
+    Done,
+}
+
+pub struct DisplayFsm<H: Hardware> {
+    hardware: H,
+    /// Rendered scrolling text (only updated when starting to scroll to avoid tearing).
+    /// Contains the label of the alarm while an alarm is ringing.
+    text_bitmap: TextBitmap,
+    auto_brightness: AutoBrightness,
+    /// Label and due time (UTC) of the snoozed alarm (if any).
+    snoozed_alarm: Option<(AlarmLabel, NaiveDateTime)>,
+    /// Minute (local time) the alarms have last been checked without the alarms of the RTC.
+    fallback_alarm_minute: Option<NaiveDateTime>,
+    /// Countdown timer or stopwatch (if switched on).
+    timer: Option<Timer>,
+    /// Set when a countdown has expired and the alert has not been given yet.
+    timer_alert_pending: bool,
+    /// Hour (0 to 23) of the last full hour the chime has been handled for.
+    chimed_hour: Option<u8>,
+    /// Night mode according to the schedule ('Off' during the day).
+    night: NightMode,
+    /// Remaining steps the display is woken up from the night mode.
+    night_wake_steps: u64,
+    last_time: Option<LastTime>,
+    transition: Option<RunningTransition>,
+    state: DisplayFsmState,
+    step: u64,
+}
+
+impl<H: Hardware> DisplayFsm<H> {
+    pub fn new(hardware: H) -> Self {
+        let display_fsm = Self {
+            hardware,
+            text_bitmap: TextBitmap::new(),
+            auto_brightness: AutoBrightness::new(),
+            snoozed_alarm: None,
+            fallback_alarm_minute: None,
+            timer: None,
+            timer_alert_pending: false,
+            chimed_hour: None,
+            night: NightMode::Off,
+            night_wake_steps: 0,
+            last_time: None,
+            transition: None,
+            state: DisplayFsmState::Playlist(0),
+            step: 0,
+        };
+
+        // Ensure the RTC matches the settings
+        display_fsm.program_alarm();
+        display_fsm
+    }
+
+    pub fn next_step(&mut self, display: &mut impl DisplayOutput) {
+        self.handle_timer(display);
+        self.handle_alarms(display);
+
+        let time = self.get_local_datetime().map(|x| x.time());
+        self.handle_chime(display, time);
+        self.handle_night(time);
+        self.handle_brightness(display);
+        self.handle_rtc_fallback(display);
+        self.handle_buttons();
+
+        // The unit is only shown together with the temperature
+        if self.current_screen() != Some(Screen::Temperature) {
+            self.clear_temperature_unit(display);
+        }
+
+        match self.state {
+            DisplayFsmState::Playlist(_) if self.active_night_mode() == NightMode::TimeOnly => {
+                // The playlist is paused during the night
+                self.show_time(display);
+            }
+            DisplayFsmState::Playlist(index) => {
+                // The playlist may have been changed in the meantime
+                let entry = self
+                    .hardware
+                    .settings()
+                    .playlist
+                    .get(index)
+                    .copied()
+                    .flatten();
+                let result = match entry {
+                    Some(entry) => self.update_screen(display, entry, self.step),
+                    None => DisplayFsmStateResult::Done,
+                };
+
+                if result == DisplayFsmStateResult::Continue {
+                    self.step += 1;
+                } else {
+                    self.enter_playlist_entry(index + 1);
+                }
+            }
+            DisplayFsmState::Alarm(_) => {
+                self.update_alarm(display, self.step);
+                self.step += 1;
+            }
+            DisplayFsmState::Timer => {
+                self.show_timer(display);
+            }
+            DisplayFsmState::TimerAlert => {
+                if self.update_timer_alert(display, self.step) == DisplayFsmStateResult::Continue {
+                    self.step += 1;
+                } else {
+                    self.hardware.stop_melody();
+                    self.enter_idle_state();
+                }
+            }
+            DisplayFsmState::Menu(menu) => {
+                if self.step < MENU_TIMEOUT_STEPS {
+                    // The value being edited blinks
+                    let frame = menu.render((self.step / 4).is_multiple_of(2));
+                    display.modify_data(|x| x.set_dot_matrix(&frame));
+                    self.step += 1;
+                } else {
+                    self.enter_idle_state();
+                }
+            }
+        }
+
+        display.present();
+    }
+
+    /// Returns to the timer (if switched on and not part of the playlist) or the playlist.
+    fn enter_idle_state(&mut self) {
+        let playlist = self.hardware.settings().playlist;
+        let timer_in_playlist = playlist
+            .iter()
+            .flatten()
+            .any(|x| x.screen == Screen::Countdown);
+
+        if self.timer.is_some() && !timer_in_playlist {
+            self.state = DisplayFsmState::Timer;
+            self.step = 0;
+        } else {
+            self.enter_playlist_entry(0);
+        }
+    }
+
+    /// Continues the playlist with the first entry starting at the given index that can be
+    /// shown (skipping empty texts and the countdown screen if the timer is off).
+    fn enter_playlist_entry(&mut self, start_index: usize) {
+        let (playlist, texts) = {
+            let settings = self.hardware.settings();
+            (settings.playlist, settings.texts)
+        };
+
+        let len = playlist.iter().flatten().count().max(1);
+        let available = |entry: &PlaylistEntry| match entry.screen {
+            Screen::Text(index) => !texts[index as usize].as_str().is_empty(),
+            Screen::Countdown => self.timer.is_some(),
+            _ => true,
+        };
+
+        // Fall back to the start index if nothing can be shown at all
+        let index = (0..MAX_PLAYLIST_LEN)
+            .map(|x| (start_index + x) % len)
+            .find(|x| playlist[*x].as_ref().is_some_and(&available))
+            .unwrap_or(start_index % len);
+
+        if let Some(PlaylistEntry {
+            screen: Screen::Text(text_index),
+            ..
+        }) = playlist[index]
+        {
+            self.render_text(text_index as usize);
+        }
+
+        self.state = DisplayFsmState::Playlist(index);
+        self.step = 0;
+
+        // No transition from a time shown earlier
+        self.last_time = None;
+        self.transition = None;
+    }
+
+    fn current_screen(&self) -> Option<Screen> {
+        if let DisplayFsmState::Playlist(index) = self.state {
+            self.hardware.settings().playlist[index].map(|x| x.screen)
+        } else {
+            None
+        }
+    }
+
+    /// Processes timer requests and starts the alert when a countdown has reached zero.
+    fn handle_timer(&mut self, display: &mut impl DisplayOutput) {
+        let now = self.hardware.tick_count();
+
+        if let Some(request) = timer::take_request() {
+            let was_on = self.timer.is_some();
+            self.process_timer_request(request, now);
+
+            // A request also stops a running alert (but not a ringing alarm). Otherwise the state
+            // only changes if the timer is switched on or off.
+            if let DisplayFsmState::TimerAlert = self.state {
+                self.hardware.stop_melody();
+                self.enter_idle_state();
+            } else if was_on != self.timer.is_some()
+                && !matches!(self.state, DisplayFsmState::Alarm(_))
+            {
+                self.enter_idle_state();
+            }
+        }
+
+        let mode = self.timer.as_ref().map(|x| x.mode());
+        display.modify_data(|x| {
+            x.set_indicator(
+                Indicator::CountDown,
+                matches!(mode, Some(TimerMode::CountDown(_))),
+            );
+            x.set_indicator(Indicator::CountUp, mode == Some(TimerMode::CountUp));
+        });
+
+        if let Some(timer) = &mut self.timer {
+            if timer.is_running() && timer.is_expired(now) {
+                timer.stop(now);
+                self.timer_alert_pending = true;
+            }
+        }
+
+        // A ringing alarm has precedence (the alert is given afterwards)
+        if self.timer_alert_pending && !matches!(self.state, DisplayFsmState::Alarm(_)) {
+            self.timer_alert_pending = false;
+            self.state = DisplayFsmState::TimerAlert;
+            self.step = 0;
+            self.hardware
+                .play_melody(Melody::builtin("timer").unwrap(), true);
+        }
+    }
+
+    fn process_timer_request(&mut self, request: TimerRequest, now: Milliseconds) {
+        match request {
+            TimerRequest::CountDown(duration) => {
+                let mut timer = Timer::new(TimerMode::CountDown(duration));
+                timer.start(now);
+                self.timer = Some(timer);
+            }
+            TimerRequest::CountUp => {
+                let mut timer = Timer::new(TimerMode::CountUp);
+                timer.start(now);
+                self.timer = Some(timer);
+            }
+            TimerRequest::Off => {
+                self.timer = None;
+            }
+            // The remaining requests are ignored if the timer is off
+            TimerRequest::Start => {
+                if let Some(timer) = &mut self.timer {
+                    timer.start(now);
+                }
+            }
+            TimerRequest::Stop => {
+                if let Some(timer) = &mut self.timer {
+                    timer.stop(now);
+                }
+            }
+            TimerRequest::StartStop => {
+                if let Some(timer) = &mut self.timer {
+                    if timer.is_running() {
+                        timer.stop(now);
+                    } else {
+                        timer.start(now);
+                    }
+                }
+            }
+            TimerRequest::Reset => {
+                if let Some(timer) = &mut self.timer {
+                    timer.reset();
+                }
+            }
+        }
+
+        self.timer_alert_pending = false;
+    }
+
+    /// Starts ringing if an alarm went off and stops it on request.
+    fn handle_alarms(&mut self, display: &mut impl DisplayOutput) {
+        let alarms = self.hardware.settings().alarms;
+        let any_alarm = alarms.iter().any(|x| x.is_some());
+        display.modify_data(|x| x.set_indicator(Indicator::AlarmOn, any_alarm));
+
+        if let DisplayFsmState::Alarm(label) = self.state {
+            if let Some(request) = alarm::take_request() {
+                if let AlarmRequest::Snooze = request {
+                    if let Ok(due) = alarm::program_snooze(&mut *self.hardware.rtc()) {
+                        self.snoozed_alarm = Some((label, due));
+                    }
+                }
+
+                self.hardware.stop_melody();
+                self.enter_idle_state();
+            }
+        } else {
+            // Requests are only valid while ringing
+            alarm::take_request();
+
+            if let Some(label) = self.poll_alarms(&alarms) {
+                self.text_bitmap =
+                    TextBitmap::from_str(label.as_str()).unwrap_or_else(|_| TextBitmap::new());
+                self.state = DisplayFsmState::Alarm(label);
+                self.step = 0;
+                self.hardware
+                    .play_melody(Melody::builtin("alarm").unwrap(), true);
+            }
+        }
+    }
+
+    /// Processes the events of the buttons depending on the state.
+    fn handle_buttons(&mut self) {
+        while let Some(event) = self.hardware.take_button_event() {
+            match self.state {
+                // Handled like the CLI commands (in the next step)
+                DisplayFsmState::Alarm(_) => match event {
+                    ButtonEvent::Press(Button::Set) => alarm::request(AlarmRequest::Dismiss),
+                    ButtonEvent::Press(_) => alarm::request(AlarmRequest::Snooze),
+                    _ => {}
+                },
+                DisplayFsmState::TimerAlert => {
+                    if let ButtonEvent::Press(_) = event {
+                        timer::request(TimerRequest::Stop);
+                    }
+                }
+                DisplayFsmState::Menu(_) => self.handle_menu_event(event),
+                DisplayFsmState::Playlist(_) | DisplayFsmState::Timer => {
+                    if self.night != NightMode::Off {
+                        let asleep = self.night_wake_steps == 0;
+                        self.night_wake_steps = NIGHT_WAKE_STEPS;
+
+                        // The first event only wakes up the display
+                        if asleep {
+                            continue;
+                        }
+                    }
+
+                    self.handle_idle_event(event);
+                }
+            }
+        }
+    }
+
+    /// Processes a button event while showing the playlist or the timer.
+    fn handle_idle_event(&mut self, event: ButtonEvent) {
+        match event {
+            ButtonEvent::Press(Button::Set) => {
+                self.state = DisplayFsmState::Menu(Menu::new());
+                self.step = 0;
+            }
+            // Up and down control the timer if it is on
+            ButtonEvent::Press(Button::Up) if self.timer.is_some() => {
+                timer::request(TimerRequest::StartStop)
+            }
+            ButtonEvent::Press(Button::Down) if self.timer.is_some() => {
+                timer::request(TimerRequest::Reset)
+            }
+            ButtonEvent::LongPress(Button::Down) if self.timer.is_some() => {
+                timer::request(TimerRequest::Off)
+            }
+            // Otherwise, up skips to the next screen of the playlist
+            ButtonEvent::Press(Button::Up) => {
+                if let DisplayFsmState::Playlist(index) = self.state {
+                    self.enter_playlist_entry(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_menu_event(&mut self, event: ButtonEvent) {
+        // Copy to avoid holding both mutexes at the same time
+        let (alarms, brightness) = {
+            let settings = self.hardware.settings();
+            (settings.alarms, settings.brightness)
+        };
+        let now = self.get_local_datetime();
+        let time = now.map(|x| x.time()).unwrap_or_default();
+        let context = MenuContext {
+            time,
+            alarms: &alarms,
+            brightness,
+        };
+
+        let action = match &mut self.state {
+            DisplayFsmState::Menu(menu) => menu.handle_event(event, &context),
+            _ => return,
+        };
+        // The blinking restarts with the value being visible
+        self.step = 0;
+
+        match action {
+            MenuAction::None => {}
+            MenuAction::Exit => self.enter_idle_state(),
+            MenuAction::SetTime(time) => {
+                // The date is kept (the time cannot be set if the RTC cannot be read). The next
+                // due alarm depends on the time.
+                if let Some(now) = now {
+                    let time_zone = self.hardware.settings().time_zone;
+                    let local = now.date().and_time(time);
+                    rtc::set_local_datetime(&mut *self.hardware.rtc(), &time_zone, &local)
+                        .unwrap_or(());
+                    self.program_alarm();
+                    // The deviation accumulated until now is unknown
+                    self.modify_settings(|x| x.drift_log.discard_interval());
+                }
+            }
+            MenuAction::SetAlarm(index, alarm) => {
+                self.modify_settings(|x| x.alarms[index] = alarm);
+                self.program_alarm();
+            }
+            MenuAction::SetBrightness(brightness) => {
+                self.modify_settings(|x| x.brightness = brightness);
+            }
+        }
+    }
+
+    /// Changes the settings and stores them persistently.
+    fn modify_settings<F: FnOnce(&mut Settings)>(&mut self, func: F) {
+        let settings = {
+            let mut settings = self.hardware.settings();
+            func(&mut settings);
+            *settings
+        };
+        self.hardware.save_settings(&settings);
+    }
+
+    /// Applies the brightness (adjusting it to the ambient light in the automatic mode).
+    /// Blinks the 'MoveOn' indicator while the time is kept by the internal RTC.
+    fn handle_rtc_fallback(&mut self, display: &mut impl DisplayOutput) {
+        let blink_on = (self.hardware.tick_count().0 / ERROR_BLINK_PERIOD.0).is_multiple_of(2);
+        let error = self.hardware.is_rtc_fallback_active() && blink_on;
+        display.modify_data(|x| x.set_indicator(Indicator::MoveOn, error));
+    }
+
+    fn handle_brightness(&mut self, display: &mut impl DisplayOutput) {
+        let brightness = self.hardware.settings().brightness;
+
+        match (self.active_night_mode(), brightness) {
+            (NightMode::Blank, _) => display.set_brightness(0),
+            (NightMode::Dim | NightMode::TimeOnly, _) => display.set_brightness(NIGHT_BRIGHTNESS),
+            (NightMode::Off, Brightness::Fixed(percent)) => display.set_brightness(percent),
+            (NightMode::Off, Brightness::Auto) => {
+                // Keep the previous brightness if the light sensor cannot be read
+                if let Some(light_level) = self.hardware.read_light_level() {
+                    display.set_brightness(self.auto_brightness.update(light_level));
+                }
+            }
+        }
+
+        display
+            .modify_data(|x| x.set_indicator(Indicator::AutoLight, brightness == Brightness::Auto));
+    }
+
+    /// Determines whether it is night according to the schedule.
+    fn handle_night(&mut self, time: Option<NaiveTime>) {
+        let (night_mode, night_schedule) = {
+            let settings = self.hardware.settings();
+            (settings.night_mode, settings.night_schedule)
+        };
+
+        // Keep the previous state if the RTC cannot be read
+        if let Some(time) = time {
+            self.night = if night_schedule.contains(&time) {
+                night_mode
+            } else {
+                NightMode::Off
+            };
+        }
+
+        self.night_wake_steps = self.night_wake_steps.saturating_sub(1);
+    }
+
+    /// Gets the night mode to be applied now: Alarms, timer alerts and the menu are never
+    /// affected, as well as the display woken up by a button.
+    fn active_night_mode(&self) -> NightMode {
+        match self.state {
+            DisplayFsmState::Playlist(_) | DisplayFsmState::Timer if self.night_wake_steps == 0 => {
+                self.night
+            }
+            _ => NightMode::Off,
+        }
+    }
+
+    /// Plays the chime at every full hour (if enabled and not within the quiet hours).
+    fn handle_chime(&mut self, display: &mut impl DisplayOutput, time: Option<NaiveTime>) {
+        let (hourly_chime, quiet_hours) = {
+            let settings = self.hardware.settings();
+            (settings.hourly_chime, settings.quiet_hours)
+        };
+        display.modify_data(|x| x.set_indicator(Indicator::Hourly, hourly_chime));
+
+        if !hourly_chime {
+            return;
+        }
+
+        let time = match time {
+            Some(x) => x,
+            None => return,
+        };
+        let hour = time.hour() as u8;
+
+        if time.minute() == 0 && self.chimed_hour != Some(hour) {
+            self.chimed_hour = Some(hour);
+
+            let quiet = quiet_hours.is_some_and(|x| x.contains(hour));
+            // An alarm or a timer alert is not interrupted
+            let busy = matches!(
+                self.state,
+                DisplayFsmState::Alarm(_) | DisplayFsmState::TimerAlert
+            );
+            if !quiet && !busy {
+                self.hardware
+                    .play_melody(Melody::builtin("chime").unwrap(), false);
+            }
+        }
+    }
+
+    /// Checks the alarm flags of the RTC and returns the label of an alarm that went off.
+    fn poll_alarms(&mut self, alarms: &alarm::Alarms) -> Option<AlarmLabel> {
+        let time_zone = self.hardware.settings().time_zone;
+        let mut rtc = self.hardware.rtc();
+
+        let label = match rtc.has_alarm_matched(AlarmSlot::Next) {
+            Ok(true) => {
+                let now = rtc::get_local_datetime(&mut *rtc, &time_zone).ok()?;
+                // Also clears the flag
+                alarm::program_next(&mut *rtc, alarms, &time_zone).unwrap_or(());
+
+                // The alarm may have been deleted in the meantime
+                alarms
+                    .iter()
+                    .flatten()
+                    .find(|x| x.matches(&now))
+                    .map(|x| x.label)
+            }
+            Ok(false) => None,
+            Err(_) => {
+                // The alarms of the RTC are not supported or cannot be used: Checked once per
+                // minute by software instead
+                let now = rtc::get_local_datetime(&mut *rtc, &time_zone).ok()?;
+                let minute = now.with_second(0)?;
+                if self.fallback_alarm_minute == Some(minute) {
+                    None
+                } else {
+                    self.fallback_alarm_minute = Some(minute);
+                    alarms
+                        .iter()
+                        .flatten()
+                        .find(|x| x.matches(&now))
+                        .map(|x| x.label)
+                }
+            }
+        };
+        if label.is_some() {
+            return label;
+        }
+
+        let (label, due) = self.snoozed_alarm?;
+        let matched = match rtc.has_alarm_matched(AlarmSlot::Snooze) {
+            Ok(x) => x,
+            Err(_) => rtc.get_datetime().ok()? >= due,
+        };
+        if matched {
+            rtc.clear_alarm(AlarmSlot::Snooze).unwrap_or(());
+            self.snoozed_alarm = None;
+            return Some(label);
+        }
+
+        None
+    }
+
+    fn update_screen(
+        &mut self,
+        display: &mut impl DisplayOutput,
+        entry: PlaylistEntry,
+        step: u64,
+    ) -> DisplayFsmStateResult {
+        let steps = match entry.screen {
+            Screen::Time => {
+                self.show_time(display);
+                entry.duration as u64 * 1000 / STEP_PERIOD.0 as u64
+            }
+            Screen::Date => {
+                self.show_date(display);
+                entry.duration as u64 * 1000 / STEP_PERIOD.0 as u64
+            }
+            Screen::Temperature => {
+                self.show_temperature(display);
+                entry.duration as u64 * 1000 / STEP_PERIOD.0 as u64
+            }
+            Screen::Countdown => {
+                self.show_timer(display);
+                entry.duration as u64 * 1000 / STEP_PERIOD.0 as u64
+            }
+            Screen::Text(_) => {
+                let scroll_steps = (DOT_MATRIX_WIDTH + self.text_bitmap.width) as u64 + 1;
+                self.show_text_segment(display, step % scroll_steps);
+                scroll_steps * entry.duration as u64
+            }
+        };
+
+        if step + 1 < steps {
+            DisplayFsmStateResult::Continue
+        } else {
+            DisplayFsmStateResult::Done
+        }
+    }
+
+    fn show_time(&mut self, display: &mut impl DisplayOutput) {
+        let (colon_blink, seconds_bar, transition) = {
+            let settings = self.hardware.settings();
+            (
+                settings.colon_blink,
+                settings.seconds_bar,
+                settings.transition,
+            )
+        };
+
+        if let Some((hours, minutes, seconds, weekday)) = self.get_time_fields() {
+            let (hour, am, pm) = match hours {
+                Hours::AM(x) => (x, true, false),
+                Hours::PM(x) => (x, false, true),
+                Hours::H24(x) => (x, false, false),
+            };
+
+            let colon_visible = !colon_blink || self.hardware.colon_visible();
+            // The bar takes the bottom row, so the time is shown with the compact characters
+            let mut frame = time_frame(hour, minutes, colon_visible, seconds_bar);
+            if seconds_bar {
+                frame[COMPACT_TEXT_HEIGHT] = seconds_bar_row(seconds);
+            }
+
+            if let Some(last_time) = &self.last_time {
+                if last_time.minutes != minutes && transition != Transition::None {
+                    self.transition = Some(RunningTransition {
+                        old_frame: last_time.frame,
+                        step: 1,
+                    });
+                }
+            }
+            self.last_time = Some(LastTime { minutes, frame });
+
+            let shown_frame = match &mut self.transition {
+                Some(running) if running.step < DOT_MATRIX_HEIGHT => {
+                    let shown_frame =
+                        transition_frame(transition, &running.old_frame, &frame, running.step);
+                    running.step += 1;
+                    shown_frame
+                }
+                _ => {
+                    self.transition = None;
+                    frame
+                }
+            };
+
+            display.modify_data(|x| {
+                x.set_dot_matrix(&shown_frame);
+                x.set_indicator(Indicator::AM, am);
+                x.set_indicator(Indicator::PM, pm);
+                set_weekday_indicators(x, weekday);
+            });
+        } else {
+            // Show nothing of communication with RTC fails.
+            display.modify_data(|x| x.clear());
+        }
+    }
+
+    fn show_date(&mut self, display: &mut impl DisplayOutput) {
+        let date_format = self.hardware.settings().date_format;
+        let date = self.get_local_datetime().map(|x| x.date());
+
+        if let Some(date) = date {
+            let (day, month) = (date.day() as u8, date.month() as u8);
+            match date_format {
+                DateFormat::DayMonth => show_two_numbers(display, day, month, true, '.'),
+                DateFormat::MonthDay => show_two_numbers(display, month, day, true, '/'),
+            }
+        } else {
+            // Show nothing of communication with RTC fails.
+            display.modify_data(|x| x.set_dot_matrix(&[0; DOT_MATRIX_HEIGHT]));
+        }
+    }
+
+    fn show_temperature(&mut self, display: &mut impl DisplayOutput) {
+        let unit = self.hardware.settings().temperature_unit;
+        let celsius = self.hardware.rtc().get_temperature();
+
+        if let Ok(celsius) = celsius {
+            let tenths = temperature::to_tenths(celsius, unit);
+
+            let mut bitmap = TextBitmap::new();
+            if tenths < 0 {
+                bitmap.append_char('-').unwrap();
+            }
+            append_number(&mut bitmap, tenths.unsigned_abs() / 10);
+            bitmap.append_char('.').unwrap();
+            append_number(&mut bitmap, tenths.unsigned_abs() % 10);
+
+            // Centered
+            let offset = -((DOT_MATRIX_WIDTH as isize - bitmap.width as isize) / 2);
+            let bitmap_segment = bitmap.segment(offset, DOT_MATRIX_WIDTH);
+            let bitmap_data_u32 = bitmap_segment.data.map(|x| x as u32);
+            display.modify_data(|x| {
+                x.set_dot_matrix(&bitmap_data_u32);
+                x.set_indicator(Indicator::DegreeC, unit == TemperatureUnit::Celsius);
+                x.set_indicator(Indicator::DegreeF, unit == TemperatureUnit::Fahrenheit);
+            });
+        } else {
+            // Show nothing of communication with RTC fails.
+            display.modify_data(|x| x.set_dot_matrix(&[0; DOT_MATRIX_HEIGHT]));
+            self.clear_temperature_unit(display);
+        }
+    }
+
+    fn clear_temperature_unit(&mut self, display: &mut impl DisplayOutput) {
+        display.modify_data(|x| {
+            x.set_indicator(Indicator::DegreeC, false);
+            x.set_indicator(Indicator::DegreeF, false);
+        });
+    }
+
+    fn show_timer(&mut self, display: &mut impl DisplayOutput) {
+        if let Some(timer) = &self.timer {
+            let seconds = timer.value(self.hardware.tick_count()).0 / 1000;
+
+            // MM:SS below one hour, HH:MM otherwise
+            if seconds < 3600 {
+                show_two_numbers(
+                    display,
+                    (seconds / 60) as u8,
+                    (seconds % 60) as u8,
+                    true,
+                    ':',
+                );
+            } else {
+                show_two_numbers(
+                    display,
+                    (seconds / 3600) as u8,
+                    (seconds / 60 % 60) as u8,
+                    true,
+                    ':',
+                );
+            }
+        } else {
+            // The timer has been switched off while being shown
+            self.show_time(display);
+        }
+    }
+
+    /// Flashes the expired countdown.
+    fn update_timer_alert(
+        &mut self,
+        display: &mut impl DisplayOutput,
+        step: u64,
+    ) -> DisplayFsmStateResult {
+        if (step / 4).is_multiple_of(2) {
+            self.show_timer(display);
+        } else {
+            display.modify_data(|x| x.set_dot_matrix(&[0; DOT_MATRIX_HEIGHT]));
+        }
+
+        if step < TIMER_ALERT_STEPS {
+            DisplayFsmStateResult::Continue
+        } else {
+            DisplayFsmStateResult::Done
+        }
+    }
+
+    /// Shows the scrolling text at the given step.
+    fn show_text_segment(&mut self, display: &mut impl DisplayOutput, step: u64) {
+        let bitmap_offset_min: isize = -(DOT_MATRIX_WIDTH as isize);
+        let bitmap_offset = bitmap_offset_min + (step as isize);
+        let bitmap_segment = self.text_bitmap.segment(bitmap_offset, DOT_MATRIX_WIDTH);
+        let bitmap_data_u32 = bitmap_segment.data.map(|x| x as u32);
+        display.modify_data(|x| x.set_dot_matrix(&bitmap_data_u32));
+    }
+
+    /// Alternates between flashing the time and scrolling the alarm label.
+    fn update_alarm(&mut self, display: &mut impl DisplayOutput, step: u64) {
+        let scroll_steps = (DOT_MATRIX_WIDTH + self.text_bitmap.width) as u64 + 1;
+        let cycle_step = step % (ALARM_FLASH_STEPS + scroll_steps);
+
+        if cycle_step < ALARM_FLASH_STEPS {
+            if (cycle_step / 4).is_multiple_of(2) {
+                self.show_time(display);
+            } else {
+                display.modify_data(|x| x.set_dot_matrix(&[0; DOT_MATRIX_HEIGHT]));
+            }
+        } else {
+            self.show_text_segment(display, cycle_step - ALARM_FLASH_STEPS);
+        }
+    }
+
+    /// Renders the scrolling text with the given index of the settings.
+    fn render_text(&mut self, index: usize) {
+        let text = self.hardware.settings().texts[index];
+
+        // The text has already been checked when it was set, so this should not fail.
+        self.text_bitmap =
+            TextBitmap::from_str(text.as_str()).unwrap_or_else(|_| TextBitmap::new());
+    }
+
+    /// Gets the hours (in the configured hour mode), the minutes, the seconds and the weekday
+    /// (1 is Sunday) of the local time.
+    fn get_time_fields(&mut self) -> Option<(Hours, u8, u8, u8)> {
+        let hour_mode = self.hardware.settings().hour_mode;
+        let now = self.get_local_datetime()?;
+
+        Some((
+            rtc::to_hours(now.hour(), hour_mode),
+            now.minute() as u8,
+            now.second() as u8,
+            now.weekday().number_from_sunday() as u8,
+        ))
+    }
+
+    /// Reads the local date and time from the RTC.
+    fn get_local_datetime(&mut self) -> Option<NaiveDateTime> {
+        // Copy to avoid holding both mutexes at the same time
+        let time_zone = self.hardware.settings().time_zone;
+        rtc::get_local_datetime(&mut *self.hardware.rtc(), &time_zone).ok()
+    }
+
+    /// Programs the RTC to the next due alarm.
+    fn program_alarm(&self) {
+        // Copy to avoid holding both mutexes at the same time
+        let (alarms, time_zone) = {
+            let settings = self.hardware.settings();
+            (settings.alarms, settings.time_zone)
+        };
+
+        alarm::program_next(&mut *self.hardware.rtc(), &alarms, &time_zone).unwrap_or(());
+    }
+}
+
+/// Shows two numbers (0..=99) with a separator in between, e.g. hours and minutes.
+fn show_two_numbers(
+    display: &mut impl DisplayOutput,
+    left: u8,
+    right: u8,
+    leading_zero: bool,
+    separator: char,
+) {
+    let frame = two_numbers_frame(left, right, leading_zero, separator, false).0;
+    display.modify_data(|x| x.set_dot_matrix(&frame));
+}
+
+/// Renders two numbers (0..=99) with a separator in between, optionally with the compact
+/// characters. Additionally returns the column of the separator.
+fn two_numbers_frame(
+    left: u8,
+    right: u8,
+    leading_zero: bool,
+    separator: char,
+    compact: bool,
+) -> (DotMatrixData, usize) {
+    let mut bitmap = TextBitmap::new();
+    let mut append = |c: u8| {
+        let c = c as char;
+        if compact {
+            bitmap.append_compact_char(c).unwrap();
+        } else {
+            bitmap.append_char(c).unwrap();
+        }
+        bitmap.width
+    };
+
+    if left >= 10 || leading_zero {
+        append(0x30 + left / 10);
+    } else {
+        append(b' ');
+    }
+    // Including the gap between characters
+    let separator_column = append(0x30 + left % 10) + 1;
+
+    append(separator as u8);
+
+    append(0x30 + right / 10);
+    append(0x30 + right % 10);
+
+    let bitmap_segment = bitmap.segment(0, DOT_MATRIX_WIDTH);
+    (bitmap_segment.data.map(|x| x as u32), separator_column)
+}
+
+/// Renders the time with an optional colon. The compact variant leaves the bottom row empty.
+fn time_frame(hours: u8, minutes: u8, colon_visible: bool, compact: bool) -> DotMatrixData {
+    let (mut frame, colon_column) = two_numbers_frame(hours, minutes, false, ':', compact);
+
+    if !colon_visible {
+        // The colon is 2 dots wide
+        let colon_mask = 0b11 << colon_column;
+        frame.iter_mut().for_each(|x| *x &= !colon_mask);
+    }
+
+    frame
+}
+
+/// Renders the seconds as a bar growing from left to right (full width at 59 seconds).
+fn seconds_bar_row(seconds: u8) -> u32 {
+    let width = (seconds as u32 + 1) * DOT_MATRIX_WIDTH as u32 / 60;
+    (1 << width) - 1
+}
+
+/// Combines the old and the new frame at the given step (1 to 'DOT_MATRIX_HEIGHT' - 1) of a
+/// transition.
+fn transition_frame(
+    transition: Transition,
+    old_frame: &DotMatrixData,
+    new_frame: &DotMatrixData,
+    step: usize,
+) -> DotMatrixData {
+    let mut frame = *new_frame;
+
+    for (row, x) in frame.iter_mut().enumerate() {
+        *x = match transition {
+            Transition::None => new_frame[row],
+            Transition::Slide if row + step < DOT_MATRIX_HEIGHT => old_frame[row + step],
+            Transition::Slide => new_frame[row + step - DOT_MATRIX_HEIGHT],
+            Transition::FadeByRow if row < step => new_frame[row],
+            Transition::FadeByRow => old_frame[row],
+        };
+    }
+
+    frame
+}
+
+/// Appends the decimal digits of a number.
+fn append_number(bitmap: &mut TextBitmap, value: u32) {
+    if value >= 10 {
+        append_number(bitmap, value / 10);
+    }
+    bitmap
+        .append_char((0x30 + (value % 10) as u8) as char)
+        .unwrap();
+}
+
+/// Lights the indicator of the given weekday (1 is Sunday as used by the RTC) only.
+fn set_weekday_indicators(data: &mut Data, weekday: u8) {
+    const INDICATORS: [Indicator; 7] = [
+        Indicator::Sun,
+        Indicator::Mon,
+        Indicator::Tues,
+        Indicator::Wed,
+        Indicator::Thur,
+        Indicator::Fri,
+        Indicator::Sat,
+    ];
+
+    for (index, indicator) in INDICATORS.into_iter().enumerate() {
+        data.set_indicator(indicator, weekday == index as u8 + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarm::{Alarm, Weekdays};
+    use crate::rtc::RtcError;
+
+    use ds323x::NaiveDate;
+
+    use core::cell::RefCell;
+    use core::ops::DerefMut;
+    use std::collections::VecDeque;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// The requests of the alarms and the timer are global, so the tests stepping an FSM must not
+    /// run in parallel.
+    static REQUESTS: Mutex<()> = Mutex::new(());
+
+    fn lock_requests() -> MutexGuard<'static, ()> {
+        REQUESTS.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// RTC without alarms (they are checked by software then) and temperature sensor.
+    struct TestRtc {
+        datetime: NaiveDateTime,
+    }
+
+    impl TimeSource for TestRtc {
+        fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError> {
+            Ok(self.datetime)
+        }
+
+        fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+            self.datetime = *datetime;
+            Ok(())
+        }
+    }
+
+    struct TestHardware {
+        settings: RefCell<Settings>,
+        rtc: RefCell<TestRtc>,
+        tick_count: Milliseconds,
+        button_events: VecDeque<ButtonEvent>,
+        /// Whether the melody being played is repeated (if any is played).
+        melody: Option<bool>,
+    }
+
+    impl Hardware for TestHardware {
+        type Rtc = TestRtc;
+
+        fn settings(&self) -> impl DerefMut<Target = Settings> + '_ {
+            self.settings.borrow_mut()
+        }
+
+        fn save_settings(&mut self, _settings: &Settings) {}
+
+        fn rtc(&self) -> impl DerefMut<Target = TestRtc> + '_ {
+            self.rtc.borrow_mut()
+        }
+
+        fn is_rtc_fallback_active(&self) -> bool {
+            false
+        }
+
+        fn colon_visible(&self) -> bool {
+            true
+        }
+
+        fn tick_count(&self) -> Milliseconds {
+            self.tick_count
+        }
+
+        fn take_button_event(&mut self) -> Option<ButtonEvent> {
+            self.button_events.pop_front()
+        }
+
+        fn read_light_level(&mut self) -> Option<u16> {
+            None
+        }
+
+        fn play_melody(&mut self, _melody: Melody, repeat: bool) {
+            self.melody = Some(repeat);
+        }
+
+        fn stop_melody(&mut self) {
+            self.melody = None;
+        }
+    }
+
+    struct TestDisplay {
+        data: Data,
+        presented: usize,
+    }
+
+    impl TestDisplay {
+        fn new() -> Self {
+            Self {
+                data: Data::new(),
+                presented: 0,
+            }
+        }
+    }
+
+    impl DisplayOutput for TestDisplay {
+        fn modify_data<F>(&mut self, func: F)
+        where
+            F: FnOnce(&mut Data),
+        {
+            func(&mut self.data);
+        }
+
+        fn set_brightness(&mut self, _brightness: u8) {}
+
+        fn present(&mut self) {
+            self.presented += 1;
+        }
+    }
+
+    fn datetime(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 5)
+            .and_then(|x| x.and_hms_opt(hour, minute, second))
+            .unwrap()
+    }
+
+    /// Creates an FSM with the time set to the given time (UTC).
+    fn display_fsm(settings: Settings, time: NaiveDateTime) -> DisplayFsm<TestHardware> {
+        DisplayFsm::new(TestHardware {
+            settings: RefCell::new(settings),
+            rtc: RefCell::new(TestRtc { datetime: time }),
+            tick_count: Milliseconds(0),
+            button_events: VecDeque::new(),
+            melody: None,
+        })
+    }
+
+    /// Steps the FSM like the animation task (the time of the RTC is kept).
+    fn step(display_fsm: &mut DisplayFsm<TestHardware>, display: &mut TestDisplay) {
+        display_fsm.next_step(display);
+        display_fsm.hardware.tick_count.0 += STEP_PERIOD.0;
+    }
+
+    fn press(display_fsm: &mut DisplayFsm<TestHardware>, button: Button) {
+        let event = ButtonEvent::Press(button);
+        display_fsm.hardware.button_events.push_back(event);
+    }
+
+    fn settings_with_alarm(time: NaiveTime) -> Settings {
+        let mut settings = Settings::new();
+        settings.alarms[0] = Some(Alarm {
+            time,
+            weekdays: Weekdays::ALL,
+            label: AlarmLabel::new("Wake up").unwrap(),
+        });
+        settings
+    }
+
+    #[test]
+    fn playlist_continues_with_the_next_screen() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let mut display_fsm = display_fsm(Settings::new(), datetime(12, 0, 0));
+
+        // The time screen of the default playlist is shown for 5 s
+        let steps = 5000 / STEP_PERIOD.0;
+        for _ in 1..steps {
+            step(&mut display_fsm, &mut display);
+        }
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(1)));
+
+        // Up skips to the next screen
+        press(&mut display_fsm, Button::Up);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(2)));
+    }
+
+    #[test]
+    fn time_screen_shows_hours_and_minutes() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let mut display_fsm = display_fsm(Settings::new(), datetime(12, 34, 56));
+
+        step(&mut display_fsm, &mut display);
+
+        let mut expected = Data::new();
+        expected.set_dot_matrix(&time_frame(12, 34, true, false));
+        expected.set_indicator(Indicator::Tues, true);
+        assert_eq!(display.data.raw_data, expected.raw_data);
+        // The whole frame is presented once per step
+        assert_eq!(display.presented, 1);
+    }
+
+    #[test]
+    fn seconds_bar_does_not_overwrite_the_digits() {
+        let _requests = lock_requests();
+        let mut settings = Settings::new();
+        settings.seconds_bar = true;
+        let digits = time_frame(18, 8, true, true);
+        // The bottom row is left empty for the bar
+        assert_eq!(
+            digits[COMPACT_TEXT_HEIGHT..],
+            [0; DOT_MATRIX_HEIGHT - COMPACT_TEXT_HEIGHT]
+        );
+
+        for seconds in [0, 29, 59] {
+            let mut display = TestDisplay::new();
+            let mut display_fsm = display_fsm(settings, datetime(18, 8, seconds));
+            step(&mut display_fsm, &mut display);
+
+            let mut expected = digits;
+            expected[COMPACT_TEXT_HEIGHT] = seconds_bar_row(seconds as u8);
+            let mut expected_data = Data::new();
+            expected_data.set_dot_matrix(&expected);
+            expected_data.set_indicator(Indicator::Tues, true);
+            assert_eq!(display.data.raw_data, expected_data.raw_data);
+        }
+    }
+
+    #[test]
+    fn menu_is_left_after_the_timeout() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let mut display_fsm = display_fsm(Settings::new(), datetime(12, 0, 0));
+
+        step(&mut display_fsm, &mut display);
+        press(&mut display_fsm, Button::Set);
+        for _ in 0..MENU_TIMEOUT_STEPS {
+            step(&mut display_fsm, &mut display);
+            assert!(matches!(display_fsm.state, DisplayFsmState::Menu(_)));
+        }
+
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+    }
+
+    #[test]
+    fn alarm_rings_until_dismissed() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let settings = settings_with_alarm(NaiveTime::from_hms_opt(7, 0, 0).unwrap());
+        let mut display_fsm = display_fsm(settings, datetime(7, 0, 10));
+
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Alarm(x) if x.as_str() == "Wake up"));
+        assert_eq!(display_fsm.hardware.melody, Some(true));
+
+        // The request of the button is processed in the next step
+        press(&mut display_fsm, Button::Set);
+        step(&mut display_fsm, &mut display);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+        assert_eq!(display_fsm.hardware.melody, None);
+
+        // Not ringing again within the same minute
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+    }
+
+    #[test]
+    fn snoozed_alarm_rings_again() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let settings = settings_with_alarm(NaiveTime::from_hms_opt(7, 0, 0).unwrap());
+        let mut display_fsm = display_fsm(settings, datetime(7, 0, 10));
+
+        step(&mut display_fsm, &mut display);
+        press(&mut display_fsm, Button::Up);
+        step(&mut display_fsm, &mut display);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+        assert_eq!(display_fsm.hardware.melody, None);
+
+        display_fsm.hardware.rtc.borrow_mut().datetime = datetime(7, 4, 59);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+
+        display_fsm.hardware.rtc.borrow_mut().datetime = datetime(7, 5, 0);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Alarm(_)));
+        assert_eq!(display_fsm.hardware.melody, Some(true));
+    }
+
+    #[test]
+    fn expired_countdown_alerts_until_a_button_is_pressed() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let mut display_fsm = display_fsm(Settings::new(), datetime(12, 0, 0));
+
+        timer::request(TimerRequest::CountDown(Milliseconds(1000)));
+        step(&mut display_fsm, &mut display);
+        // Shown instead of the playlist (which has no countdown screen)
+        assert!(matches!(display_fsm.state, DisplayFsmState::Timer));
+        assert!(display.data.raw_data[2] & 0b11 != 0);
+
+        // Until the first step after the expiry
+        while display_fsm.hardware.tick_count.0 < 1000 + STEP_PERIOD.0 {
+            step(&mut display_fsm, &mut display);
+        }
+        assert!(matches!(display_fsm.state, DisplayFsmState::TimerAlert));
+        assert_eq!(display_fsm.hardware.melody, Some(true));
+
+        // The request of the button is processed in the next step
+        press(&mut display_fsm, Button::Down);
+        step(&mut display_fsm, &mut display);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Timer));
+        assert_eq!(display_fsm.hardware.melody, None);
+    }
+
+    #[test]
+    fn timer_requests_keep_the_screen_unless_switching_the_timer() {
+        let _requests = lock_requests();
+        let mut display = TestDisplay::new();
+        let mut settings = Settings::new();
+        settings.playlist = [None; MAX_PLAYLIST_LEN];
+        settings.playlist[0] = Some(PlaylistEntry::new(Screen::Time, Some(1)));
+        settings.playlist[1] = Some(PlaylistEntry::new(Screen::Countdown, Some(60)));
+        let mut display_fsm = display_fsm(settings, datetime(12, 0, 0));
+
+        timer::request(TimerRequest::CountUp);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+        while !matches!(display_fsm.state, DisplayFsmState::Playlist(1)) {
+            step(&mut display_fsm, &mut display);
+        }
+
+        // Pausing and resetting doesn't restart the playlist
+        for request in [TimerRequest::Stop, TimerRequest::Reset, TimerRequest::Start] {
+            timer::request(request);
+            step(&mut display_fsm, &mut display);
+            assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(1)));
+        }
+
+        // Neither does it leave the menu
+        press(&mut display_fsm, Button::Set);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Menu(_)));
+        timer::request(TimerRequest::StartStop);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Menu(_)));
+
+        // Switching the timer off does
+        timer::request(TimerRequest::Off);
+        step(&mut display_fsm, &mut display);
+        assert!(matches!(display_fsm.state, DisplayFsmState::Playlist(0)));
+    }
+}
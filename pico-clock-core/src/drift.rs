@@ -0,0 +1,240 @@
+//! Drift of the RTC measured by the host synchronizations, used to recommend the aging offset
+//! of the DS3231.
+//!
+//! Each synchronization measures the deviation accumulated since the previous one, unless the
+//! time or the aging offset has been changed in between.
+
+use crate::settings::store::{Reader, Writer};
+
+use core::fmt;
+
+/// Number of measurements kept (the oldest one is dropped).
+pub const MAX_MEASUREMENTS: usize = 4;
+
+/// Total time of the measurements required for a drift to be reported (shorter intervals are
+/// dominated by the inaccuracy of the synchronization).
+const MIN_MEASUREMENT_TIME: u32 = 6 * 3600;
+
+/// Largest deviation in milliseconds that is recorded as a measurement. Larger ones come from a
+/// wrong time rather than from the drift.
+pub const MAX_DEVIATION: i32 = 60_000;
+
+#[derive(Clone, Copy)]
+struct Measurement {
+    /// Time since the previous synchronization in seconds.
+    interval: u32,
+    /// Deviation of the RTC at the synchronization in milliseconds (positive if ahead).
+    deviation: i32,
+    /// Aging offset in effect during the interval.
+    aging_offset: i8,
+}
+
+#[derive(Clone, Copy)]
+pub struct DriftLog {
+    /// Unix time in seconds of the last synchronization, if the RTC has not been changed since.
+    last_sync: Option<u32>,
+    /// Oldest first.
+    measurements: [Option<Measurement>; MAX_MEASUREMENTS],
+}
+
+/// Drift in tenths of ppm (positive if the RTC is fast), formatted in ppm like '+1.5'.
+#[derive(Clone, Copy)]
+pub struct Drift(pub i32);
+
+impl DriftLog {
+    pub fn new() -> Self {
+        Self {
+            last_sync: None,
+            measurements: [None; MAX_MEASUREMENTS],
+        }
+    }
+
+    /// Records a synchronization at the given Unix time, with the deviation (in milliseconds)
+    /// the RTC had before being set.
+    pub fn record_sync(&mut self, time: u32, deviation: i32, aging_offset: i8) {
+        if let Some(last_sync) = self.last_sync.filter(|x| *x < time) {
+            self.measurements.rotate_left(1);
+            self.measurements[MAX_MEASUREMENTS - 1] = Some(Measurement {
+                interval: time - last_sync,
+                deviation,
+                aging_offset,
+            });
+        }
+        self.last_sync = Some(time);
+    }
+
+    /// Discards the running interval because the time or the aging offset has been changed
+    /// (the next synchronization starts a new one).
+    pub fn discard_interval(&mut self) {
+        self.last_sync = None;
+    }
+
+    /// Number of measurements taken with the given aging offset.
+    pub fn count(&self, aging_offset: i8) -> usize {
+        self.with_offset(aging_offset).count()
+    }
+
+    /// Gets the drift with the given aging offset, averaged over the measurements taken with it
+    /// ('None' if not measured long enough).
+    pub fn drift(&self, aging_offset: i8) -> Option<Drift> {
+        let (interval, deviation) =
+            self.with_offset(aging_offset)
+                .fold((0i64, 0i64), |(interval, deviation), x| {
+                    (interval + x.interval as i64, deviation + x.deviation as i64)
+                });
+
+        if interval < MIN_MEASUREMENT_TIME as i64 {
+            return None;
+        }
+        // Milliseconds per second are 1000 ppm
+        Some(Drift((deviation * 10_000 / interval) as i32))
+    }
+
+    /// Recommends the aging offset compensating the drift measured with the given one.
+    ///
+    /// One step of the aging offset changes the frequency by about 0.1 ppm (at 25 °C), a
+    /// positive offset slows the oscillator down.
+    pub fn recommended_aging_offset(&self, aging_offset: i8) -> Option<i8> {
+        let drift = self.drift(aging_offset)?;
+        Some((aging_offset as i32 + drift.0).clamp(i8::MIN as i32, i8::MAX as i32) as i8)
+    }
+
+    fn with_offset(&self, aging_offset: i8) -> impl Iterator<Item = &Measurement> {
+        self.measurements
+            .iter()
+            .flatten()
+            .filter(move |x| x.aging_offset == aging_offset)
+    }
+
+    pub fn serialize(&self, writer: &mut Writer) {
+        writer.write_u32(self.last_sync.unwrap_or(u32::MAX));
+        for measurement in self.measurements {
+            match measurement {
+                Some(x) => {
+                    writer.write_u32(x.interval);
+                    writer.write_u32(x.deviation as u32);
+                    writer.write_u8(x.aging_offset as u8);
+                }
+                // An interval of zero is never recorded
+                None => {
+                    writer.write_u32(0);
+                    writer.write_u32(0);
+                    writer.write_u8(0);
+                }
+            }
+        }
+    }
+
+    pub fn deserialize(reader: &mut Reader) -> Option<Self> {
+        let mut log = Self::new();
+        log.last_sync = Some(reader.read_u32()?).filter(|x| *x != u32::MAX);
+
+        for measurement in log.measurements.iter_mut() {
+            let interval = reader.read_u32()?;
+            let deviation = reader.read_u32()? as i32;
+            let aging_offset = reader.read_u8()? as i8;
+            if interval > 0 {
+                *measurement = Some(Measurement {
+                    interval,
+                    deviation,
+                    aging_offset,
+                });
+            }
+        }
+
+        Some(log)
+    }
+}
+
+impl Default for DriftLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let tenths = self.0.unsigned_abs();
+        write!(f, "{}{}.{}", sign, tenths / 10, tenths % 10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u32 = 86_400;
+
+    #[test]
+    fn drift_requires_a_minimum_measurement_time() {
+        let mut log = DriftLog::new();
+        log.record_sync(1_000_000, 0, 0);
+        log.record_sync(1_000_000 + 3600, 50, 0);
+
+        assert_eq!(log.count(0), 1);
+        assert!(log.drift(0).is_none());
+        assert!(log.recommended_aging_offset(0).is_none());
+    }
+
+    #[test]
+    fn drift_is_averaged_over_the_measurements_with_the_aging_offset() {
+        let mut log = DriftLog::new();
+        log.record_sync(0, 0, 0);
+        // 10 ppm fast
+        log.record_sync(DAY, 864, 0);
+        assert_eq!(log.drift(0).unwrap().0, 100);
+        assert_eq!(log.recommended_aging_offset(0), Some(100));
+
+        // Exact on the second day
+        log.record_sync(2 * DAY, 0, 0);
+        assert_eq!(log.drift(0).unwrap().0, 50);
+
+        // Measured with another aging offset
+        log.record_sync(3 * DAY, -432, 50);
+        assert_eq!(log.count(0), 2);
+        assert_eq!(log.drift(0).unwrap().0, 50);
+        assert_eq!(log.drift(50).unwrap().0, -50);
+        assert_eq!(log.recommended_aging_offset(50), Some(0));
+    }
+
+    #[test]
+    fn recommended_aging_offset_is_clamped() {
+        let mut log = DriftLog::new();
+        log.record_sync(0, 0, 100);
+        log.record_sync(DAY, 864, 100);
+        assert_eq!(log.recommended_aging_offset(100), Some(i8::MAX));
+    }
+
+    #[test]
+    fn discarded_interval_is_not_measured() {
+        let mut log = DriftLog::new();
+        log.record_sync(0, 0, 0);
+        log.discard_interval();
+        log.record_sync(DAY, 5000, 0);
+        assert_eq!(log.count(0), 0);
+
+        // A new interval starts with that synchronization
+        log.record_sync(2 * DAY, 864, 0);
+        assert_eq!(log.drift(0).unwrap().0, 100);
+    }
+
+    #[test]
+    fn oldest_measurement_is_dropped() {
+        let mut log = DriftLog::new();
+        log.record_sync(0, 0, 0);
+        log.record_sync(DAY, 8640, 0);
+        for day in 2..=MAX_MEASUREMENTS as u32 + 1 {
+            log.record_sync(day * DAY, 0, 0);
+        }
+        assert_eq!(log.count(0), MAX_MEASUREMENTS);
+        assert_eq!(log.drift(0).unwrap().0, 0);
+    }
+
+    #[test]
+    fn drift_is_formatted_in_ppm() {
+        assert_eq!(format!("{}", Drift(15)), "+1.5");
+        assert_eq!(format!("{}", Drift(-7)), "-0.7");
+        assert_eq!(format!("{}", Drift(0)), "+0.0");
+    }
+}
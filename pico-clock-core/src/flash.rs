@@ -0,0 +1,17 @@
+//! Access to the flash memory holding the settings store.
+
+pub const FLASH_SIZE: u32 = 2048 * 1024;
+pub const SECTOR_SIZE: u32 = 4096;
+pub const PAGE_SIZE: u32 = 256;
+
+/// Flash of 'FLASH_SIZE' bytes (the offsets are relative to its start).
+pub trait Flash {
+    /// Gets flash content.
+    fn read(&self, offset: u32, len: u32) -> &[u8];
+
+    /// Erases whole sectors.
+    fn erase(&mut self, offset: u32, len: u32);
+
+    /// Programs whole pages (that must have been erased before).
+    fn program(&mut self, offset: u32, data: &[u8]);
+}
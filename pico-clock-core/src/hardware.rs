@@ -0,0 +1,46 @@
+//! Hardware used by the display FSM (besides the display itself).
+
+use crate::buttons::ButtonEvent;
+use crate::melody::Melody;
+use crate::rtc::TimeSource;
+use crate::settings::Settings;
+
+use embedded_time::duration::Milliseconds;
+
+use core::ops::DerefMut;
+
+pub trait Hardware {
+    type Rtc: TimeSource;
+
+    /// Locks the settings, which are shared with other tasks (e.g. the CLI).
+    fn settings(&self) -> impl DerefMut<Target = Settings> + '_;
+
+    /// Stores the settings persistently.
+    fn save_settings(&mut self, settings: &Settings);
+
+    /// Locks the RTC, which is shared with other tasks (e.g. the CLI).
+    fn rtc(&self) -> impl DerefMut<Target = Self::Rtc> + '_;
+
+    /// Checks whether the time is kept by a fallback because the RTC cannot be accessed.
+    fn is_rtc_fallback_active(&self) -> bool;
+
+    /// Checks whether the blinking colon is visible (synchronized with the seconds of the RTC
+    /// if possible).
+    fn colon_visible(&self) -> bool;
+
+    /// Gets the tick count of the scheduler in milliseconds (wrapping around).
+    fn tick_count(&self) -> Milliseconds;
+
+    /// Takes the oldest event of the buttons that has not been taken yet (if any).
+    fn take_button_event(&mut self) -> Option<ButtonEvent>;
+
+    /// Reads the ambient light level (0 to 4095, increasing with the light).
+    fn read_light_level(&mut self) -> Option<u16>;
+
+    /// Starts playing the melody on the buzzer (replacing the one being played). If 'repeat' is
+    /// set, it is played until 'stop_melody' is called.
+    fn play_melody(&mut self, melody: Melody, repeat: bool);
+
+    /// Stops the melody being played (if any).
+    fn stop_melody(&mut self);
+}
@@ -0,0 +1,29 @@
+//! Logic of the "Pico Clock Green" firmware that does not depend on the hardware: The content of
+//! the display, the menu, the settings and their storage, alarms, timers and time zones.
+//!
+//! The hardware is accessed through traits ('hardware::Hardware', 'display::DisplayOutput',
+//! 'rtc::TimeSource', 'flash::Flash') implemented by the firmware (pico-clock-hello) and by the
+//! simulator (pico-clock-sim), so the library also runs on the host (e.g. for unit tests).
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod alarm;
+pub mod brightness;
+pub mod buttons;
+pub mod chime;
+pub mod cli;
+pub mod display;
+pub mod display_fsm;
+pub mod drift;
+pub mod flash;
+pub mod hardware;
+pub mod melody;
+pub mod menu;
+pub mod night;
+pub mod playlist;
+pub mod rtc;
+pub mod settings;
+pub mod temperature;
+pub mod text;
+pub mod timer;
+pub mod timezone;
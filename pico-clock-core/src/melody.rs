@@ -0,0 +1,219 @@
+//! Melodies as sequences of tones, written as text like 'C5:150 E5:150 G5:300 -:500'.
+//!
+//! Each tone consists of the note ('C' to 'B', optionally followed by '#' for sharp) and the
+//! octave (3 to 7), or '-' for a rest, followed by the duration in milliseconds.
+
+use core::fmt;
+
+/// Maximum number of tones (including rests) of a melody.
+pub const MAX_TONES: usize = 32;
+
+/// Maximum duration of a single tone.
+const MAX_TONE_DURATION: u16 = 5000;
+
+const MIN_OCTAVE: u8 = 3;
+const MAX_OCTAVE: u8 = 7;
+
+/// Frequencies of the notes C to B of the 4th octave in Hz (other octaves are derived by
+/// doubling or halving).
+const OCTAVE_4_FREQUENCIES: [u16; 12] =
+    [262, 277, 294, 311, 330, 349, 370, 392, 415, 440, 466, 494];
+
+/// Played while an alarm is ringing (repeated).
+pub const ALARM: &str = "C6:100 -:100 C6:100 -:100 C6:100 -:100 C6:100 -:600";
+/// Played when a countdown has reached zero (repeated during the alert).
+pub const TIMER: &str = "G5:120 -:60 G5:120 -:60 G5:120 -:600";
+/// Played at the full hour.
+pub const CHIME: &str = "G5:200 E5:200 C5:400";
+
+#[derive(Clone, Copy)]
+pub struct Tone {
+    /// Frequency in Hz (zero for a rest).
+    pub frequency: u16,
+    /// Duration in milliseconds.
+    pub duration: u16,
+}
+
+#[derive(Clone, Copy)]
+pub struct Melody {
+    tones: [Tone; MAX_TONES],
+    len: usize,
+}
+
+#[derive(Debug)]
+pub enum MelodyParseError {
+    Empty,
+    TooManyTones,
+    /// Contains the position of the invalid tone (starting at 1).
+    InvalidTone(usize),
+}
+
+impl fmt::Display for MelodyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MelodyParseError::Empty => write!(f, "Melody is empty"),
+            MelodyParseError::TooManyTones => {
+                write!(f, "Melody has more than {} tones", MAX_TONES)
+            }
+            MelodyParseError::InvalidTone(position) => write!(f, "Invalid tone #{}", position),
+        }
+    }
+}
+
+impl Melody {
+    /// Parses the tones separated by whitespace.
+    pub fn parse(input: &str) -> Result<Self, MelodyParseError> {
+        let mut melody = Self {
+            tones: [Tone {
+                frequency: 0,
+                duration: 0,
+            }; MAX_TONES],
+            len: 0,
+        };
+
+        for (index, tone) in input.split_whitespace().enumerate() {
+            if index >= MAX_TONES {
+                return Err(MelodyParseError::TooManyTones);
+            }
+            melody.tones[index] =
+                parse_tone(tone).ok_or(MelodyParseError::InvalidTone(index + 1))?;
+            melody.len = index + 1;
+        }
+
+        if melody.len == 0 {
+            return Err(MelodyParseError::Empty);
+        }
+
+        Ok(melody)
+    }
+
+    /// Gets one of the built-in melodies by its name ('alarm', 'timer' or 'chime').
+    pub fn builtin(name: &str) -> Option<Self> {
+        let text = match name {
+            "alarm" => ALARM,
+            "timer" => TIMER,
+            "chime" => CHIME,
+            _ => return None,
+        };
+
+        // The built-in melodies are valid, so this cannot fail.
+        Some(Self::parse(text).unwrap())
+    }
+
+    pub fn tones(&self) -> &[Tone] {
+        &self.tones[0..self.len]
+    }
+}
+
+fn parse_tone(input: &str) -> Option<Tone> {
+    let (note, duration) = input.split_once(':')?;
+
+    let duration: u16 = duration.parse().ok()?;
+    if !(1..=MAX_TONE_DURATION).contains(&duration) {
+        return None;
+    }
+
+    let frequency = if note == "-" { 0 } else { parse_note(note)? };
+
+    Some(Tone {
+        frequency,
+        duration,
+    })
+}
+
+/// Converts a note like 'A4' or 'C#5' to its frequency.
+fn parse_note(input: &str) -> Option<u16> {
+    let mut chars = input.chars();
+
+    let mut semitone = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+
+    let mut rest = chars.as_str();
+    if let Some(x) = rest.strip_prefix('#') {
+        // B# is not supported (it would belong to the next octave)
+        if semitone == 11 {
+            return None;
+        }
+        semitone += 1;
+        rest = x;
+    }
+
+    let octave: u8 = rest.parse().ok()?;
+    if !(MIN_OCTAVE..=MAX_OCTAVE).contains(&octave) {
+        return None;
+    }
+
+    let frequency = OCTAVE_4_FREQUENCIES[semitone];
+    if octave >= 4 {
+        Some(frequency << (octave - 4))
+    } else {
+        Some(frequency >> (4 - octave))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_tone(input: &str) -> Option<usize> {
+        match Melody::parse(input) {
+            Err(MelodyParseError::InvalidTone(position)) => Some(position),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn tones_are_parsed_with_their_frequency() {
+        let melody = Melody::parse("A4:100  c#5:200\t-:300 C3:1 B7:5000").unwrap();
+        let tones: Vec<_> = melody
+            .tones()
+            .iter()
+            .map(|x| (x.frequency, x.duration))
+            .collect();
+
+        assert_eq!(
+            tones,
+            [(440, 100), (554, 200), (0, 300), (131, 1), (3952, 5000)]
+        );
+    }
+
+    #[test]
+    fn invalid_tone_is_reported_with_its_position() {
+        assert_eq!(invalid_tone("H4:100"), Some(1));
+        assert_eq!(invalid_tone("C4:100 C4"), Some(2));
+        assert_eq!(invalid_tone("C4:100 C4:100 C2:100"), Some(3));
+        assert_eq!(invalid_tone("C4:100 C8:100"), Some(2));
+        assert_eq!(invalid_tone("C4:100 B#4:100"), Some(2));
+        assert_eq!(invalid_tone("C4:0"), Some(1));
+        assert_eq!(invalid_tone("C4:100 -:5001"), Some(2));
+        assert_eq!(invalid_tone("C4:100 -:-1"), Some(2));
+    }
+
+    #[test]
+    fn melody_must_have_one_to_max_tones() {
+        assert!(matches!(Melody::parse(" "), Err(MelodyParseError::Empty)));
+
+        let tones = "-:1 ".repeat(MAX_TONES);
+        assert_eq!(Melody::parse(&tones).unwrap().tones().len(), MAX_TONES);
+        assert!(matches!(
+            Melody::parse(&(tones + "-:1")),
+            Err(MelodyParseError::TooManyTones)
+        ));
+    }
+
+    #[test]
+    fn builtin_melodies_are_valid() {
+        for name in ["alarm", "timer", "chime"] {
+            assert!(Melody::builtin(name).is_some());
+        }
+        assert!(Melody::builtin("other").is_none());
+    }
+}
@@ -0,0 +1,538 @@
+//! On-device menu operated by the buttons, for setting the time, the alarms and the brightness
+//! without a serial cable.
+//!
+//! 'Up' and 'Down' change the selected item or value (repeating while held down), 'Set' enters
+//! an item or confirms a value and a long press of 'Set' leaves the menu.
+
+use crate::alarm::{Alarm, AlarmLabel, Alarms, Weekdays, MAX_ALARMS};
+use crate::brightness::{Brightness, MAX_BRIGHTNESS};
+use crate::buttons::{Button, ButtonEvent};
+use crate::display::data::{DotMatrixData, DOT_MATRIX_WIDTH};
+use crate::text::TextBitmap;
+
+use ds323x::{NaiveTime, Timelike};
+
+/// Change of the brightness per step.
+const BRIGHTNESS_STEP: u8 = 10;
+
+/// Choices of the days of an alarm ('None' deletes the alarm).
+const DAYS_CHOICES: [Option<Weekdays>; 4] = [
+    Some(Weekdays::ALL),
+    Some(Weekdays::WORKDAYS),
+    Some(Weekdays::WEEKEND),
+    None,
+];
+
+#[derive(Clone, Copy)]
+pub enum MenuItem {
+    Time,
+    Alarm,
+    Brightness,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Hours,
+    Minutes,
+    /// Days of an alarm.
+    Days,
+}
+
+#[derive(Clone, Copy)]
+pub enum Menu {
+    /// Selection of the item to be set.
+    Select(MenuItem),
+    Time {
+        hours: u8,
+        minutes: u8,
+        field: Field,
+    },
+    /// Selection of the alarm slot (as numbered by the CLI).
+    AlarmSlot {
+        index: usize,
+        /// Whether the slot contains an alarm.
+        used: bool,
+    },
+    Alarm {
+        index: usize,
+        hours: u8,
+        minutes: u8,
+        /// Days of the alarm ('None' deletes it).
+        days: Option<Weekdays>,
+        label: AlarmLabel,
+        field: Field,
+    },
+    Brightness(Brightness),
+}
+
+/// Change to be applied when a value has been confirmed.
+pub enum MenuAction {
+    None,
+    Exit,
+    SetTime(NaiveTime),
+    SetAlarm(usize, Option<Alarm>),
+    SetBrightness(Brightness),
+}
+
+/// Current values the editing starts with.
+pub struct MenuContext<'a> {
+    pub time: NaiveTime,
+    pub alarms: &'a Alarms,
+    pub brightness: Brightness,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Menu::Select(MenuItem::Time)
+    }
+
+    pub fn handle_event(&mut self, event: ButtonEvent, context: &MenuContext) -> MenuAction {
+        let up = match event {
+            ButtonEvent::LongPress(Button::Set) => return MenuAction::Exit,
+            ButtonEvent::Press(Button::Set) => return self.confirm(context),
+            ButtonEvent::Repeat(Button::Set) => return MenuAction::None,
+            ButtonEvent::Press(button)
+            | ButtonEvent::LongPress(button)
+            | ButtonEvent::Repeat(button) => button == Button::Up,
+        };
+
+        match self {
+            Menu::Select(item) => {
+                *item = match (*item, up) {
+                    (MenuItem::Time, true) | (MenuItem::Brightness, false) => MenuItem::Alarm,
+                    (MenuItem::Alarm, true) | (MenuItem::Time, false) => MenuItem::Brightness,
+                    (MenuItem::Brightness, true) | (MenuItem::Alarm, false) => MenuItem::Time,
+                };
+            }
+            Menu::Time {
+                hours,
+                minutes,
+                field,
+            }
+            | Menu::Alarm {
+                hours,
+                minutes,
+                field,
+                ..
+            } if *field != Field::Days => {
+                if *field == Field::Hours {
+                    *hours = step(*hours, 24, up);
+                } else {
+                    *minutes = step(*minutes, 60, up);
+                }
+            }
+            Menu::Alarm { days, .. } => {
+                // Custom days (set by the CLI) are replaced by the first choice
+                let position = DAYS_CHOICES.iter().position(|x| x == days).unwrap_or(0);
+                *days = DAYS_CHOICES[step(position as u8, DAYS_CHOICES.len() as u8, up) as usize];
+            }
+            Menu::Time { .. } => {}
+            Menu::AlarmSlot { index, .. } => {
+                let index = step(*index as u8, MAX_ALARMS as u8, up) as usize;
+                *self = alarm_slot(index, context);
+            }
+            Menu::Brightness(brightness) => {
+                *brightness = step_brightness(*brightness, up);
+            }
+        }
+
+        MenuAction::None
+    }
+
+    /// Renders the menu. The value being edited is only shown if 'blink_on' is set.
+    pub fn render(&self, blink_on: bool) -> DotMatrixData {
+        let mut bitmap = TextBitmap::new();
+
+        // The font only contains printable ASCII characters, so rendering cannot fail.
+        match self {
+            Menu::Select(item) => {
+                let text = match item {
+                    MenuItem::Time => "Time",
+                    MenuItem::Alarm => "Alrm",
+                    MenuItem::Brightness => "Brt",
+                };
+                bitmap.append_text(text).unwrap();
+            }
+            Menu::Time {
+                hours,
+                minutes,
+                field,
+            } => {
+                append_time(&mut bitmap, *hours, *minutes, *field, blink_on);
+            }
+            Menu::AlarmSlot { index, used } => {
+                // E.g. 'A1 +' if the slot contains an alarm, 'A1 -' otherwise
+                bitmap.append_char('A').unwrap();
+                append_digits(&mut bitmap, *index as u8 + 1, 1, true);
+                bitmap.append_text(if *used { " +" } else { " -" }).unwrap();
+            }
+            Menu::Alarm {
+                hours,
+                minutes,
+                days,
+                field,
+                ..
+            } => {
+                if *field == Field::Days {
+                    if blink_on {
+                        let text = match days {
+                            Some(Weekdays::ALL) => "Dly",
+                            Some(Weekdays::WORKDAYS) => "M-F",
+                            Some(Weekdays::WEEKEND) => "S+S",
+                            Some(_) => "Cus",
+                            None => "Off",
+                        };
+                        bitmap.append_text(text).unwrap();
+                    }
+                } else {
+                    append_time(&mut bitmap, *hours, *minutes, *field, blink_on);
+                }
+            }
+            Menu::Brightness(brightness) => {
+                if blink_on {
+                    match brightness {
+                        Brightness::Fixed(percent) => {
+                            let digits = if *percent >= 100 {
+                                3
+                            } else if *percent >= 10 {
+                                2
+                            } else {
+                                1
+                            };
+                            append_digits(&mut bitmap, *percent, digits, true);
+                            bitmap.append_char('%').unwrap();
+                        }
+                        Brightness::Auto => bitmap.append_text("Auto").unwrap(),
+                    }
+                }
+            }
+        }
+
+        // Centered (or left aligned if too wide)
+        let offset = -((DOT_MATRIX_WIDTH as isize - bitmap.width as isize) / 2).max(0);
+        let bitmap_segment = bitmap.segment(offset, DOT_MATRIX_WIDTH);
+        bitmap_segment.data.map(|x| x as u32)
+    }
+
+    /// Enters the selected item or confirms the value being edited.
+    fn confirm(&mut self, context: &MenuContext) -> MenuAction {
+        match *self {
+            Menu::Select(MenuItem::Time) => {
+                *self = Menu::Time {
+                    hours: context.time.hour() as u8,
+                    minutes: context.time.minute() as u8,
+                    field: Field::Hours,
+                };
+            }
+            Menu::Select(MenuItem::Alarm) => {
+                *self = alarm_slot(0, context);
+            }
+            Menu::Select(MenuItem::Brightness) => {
+                *self = Menu::Brightness(context.brightness);
+            }
+            Menu::Time {
+                hours,
+                minutes,
+                field,
+            } => {
+                if field == Field::Hours {
+                    *self = Menu::Time {
+                        hours,
+                        minutes,
+                        field: Field::Minutes,
+                    };
+                } else {
+                    *self = Menu::Select(MenuItem::Time);
+                    // Cannot fail because the fields are within their range
+                    let time = NaiveTime::from_hms_opt(hours as u32, minutes as u32, 0).unwrap();
+                    return MenuAction::SetTime(time);
+                }
+            }
+            Menu::AlarmSlot { index, .. } => {
+                // A new alarm starts at 07:00 on all days
+                let alarm = context.alarms[index];
+                *self = Menu::Alarm {
+                    index,
+                    hours: alarm.map_or(7, |x| x.time.hour() as u8),
+                    minutes: alarm.map_or(0, |x| x.time.minute() as u8),
+                    days: Some(alarm.map_or(Weekdays::ALL, |x| x.weekdays)),
+                    label: alarm.map_or(AlarmLabel::new("Alarm").unwrap(), |x| x.label),
+                    field: Field::Hours,
+                };
+            }
+            Menu::Alarm {
+                index,
+                hours,
+                minutes,
+                days,
+                label,
+                field,
+            } => {
+                let next_field = match field {
+                    Field::Hours => Field::Minutes,
+                    Field::Minutes => Field::Days,
+                    Field::Days => {
+                        *self = Menu::AlarmSlot {
+                            index,
+                            used: days.is_some(),
+                        };
+                        // Cannot fail because the fields are within their range
+                        let time =
+                            NaiveTime::from_hms_opt(hours as u32, minutes as u32, 0).unwrap();
+                        let alarm = days.map(|weekdays| Alarm {
+                            time,
+                            weekdays,
+                            label,
+                        });
+                        return MenuAction::SetAlarm(index, alarm);
+                    }
+                };
+                *self = Menu::Alarm {
+                    index,
+                    hours,
+                    minutes,
+                    days,
+                    label,
+                    field: next_field,
+                };
+            }
+            Menu::Brightness(brightness) => {
+                *self = Menu::Select(MenuItem::Brightness);
+                return MenuAction::SetBrightness(brightness);
+            }
+        }
+
+        MenuAction::None
+    }
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn alarm_slot(index: usize, context: &MenuContext) -> Menu {
+    Menu::AlarmSlot {
+        index,
+        used: context.alarms[index].is_some(),
+    }
+}
+
+/// Increments or decrements a value in the range 0 to 'count' - 1 (wrapping around).
+fn step(value: u8, count: u8, up: bool) -> u8 {
+    if up {
+        (value + 1) % count
+    } else {
+        (value + count - 1) % count
+    }
+}
+
+/// Steps through the multiples of 'BRIGHTNESS_STEP' followed by the automatic mode.
+fn step_brightness(brightness: Brightness, up: bool) -> Brightness {
+    match (brightness, up) {
+        (Brightness::Auto, true) => Brightness::Fixed(0),
+        (Brightness::Auto, false) => Brightness::Fixed(MAX_BRIGHTNESS),
+        (Brightness::Fixed(MAX_BRIGHTNESS), true) | (Brightness::Fixed(0), false) => {
+            Brightness::Auto
+        }
+        (Brightness::Fixed(percent), true) => {
+            Brightness::Fixed((percent / BRIGHTNESS_STEP + 1) * BRIGHTNESS_STEP)
+        }
+        (Brightness::Fixed(percent), false) => {
+            Brightness::Fixed((percent - 1) / BRIGHTNESS_STEP * BRIGHTNESS_STEP)
+        }
+    }
+}
+
+/// Appends 'HH:MM' with the field being edited only shown if 'blink_on' is set.
+fn append_time(bitmap: &mut TextBitmap, hours: u8, minutes: u8, field: Field, blink_on: bool) {
+    append_digits(bitmap, hours, 2, field != Field::Hours || blink_on);
+    bitmap.append_char(':').unwrap();
+    append_digits(bitmap, minutes, 2, field != Field::Minutes || blink_on);
+}
+
+/// Appends the given number of digits (with leading zeros), or spaces of the same width if not
+/// visible.
+fn append_digits(bitmap: &mut TextBitmap, value: u8, digits: u32, visible: bool) {
+    for position in (0..digits).rev() {
+        let digit = value / 10u8.pow(position) % 10;
+        let c = if visible { (0x30 + digit) as char } else { ' ' };
+        bitmap.append_char(c).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(alarms: &Alarms) -> MenuContext<'_> {
+        MenuContext {
+            time: NaiveTime::from_hms_opt(12, 34, 0).unwrap(),
+            alarms,
+            brightness: Brightness::Fixed(50),
+        }
+    }
+
+    fn press(menu: &mut Menu, button: Button, context: &MenuContext) -> MenuAction {
+        menu.handle_event(ButtonEvent::Press(button), context)
+    }
+
+    #[test]
+    fn items_are_selected_in_a_cycle() {
+        let alarms = [None; MAX_ALARMS];
+        let context = context(&alarms);
+        let mut menu = Menu::new();
+
+        press(&mut menu, Button::Down, &context);
+        assert!(matches!(menu, Menu::Select(MenuItem::Brightness)));
+        press(&mut menu, Button::Up, &context);
+        press(&mut menu, Button::Up, &context);
+        assert!(matches!(menu, Menu::Select(MenuItem::Alarm)));
+
+        // Leaving the menu is up to the caller
+        let action = menu.handle_event(ButtonEvent::LongPress(Button::Set), &context);
+        assert!(matches!(action, MenuAction::Exit));
+    }
+
+    #[test]
+    fn time_is_confirmed_after_the_minutes() {
+        let alarms = [None; MAX_ALARMS];
+        let context = context(&alarms);
+        let mut menu = Menu::new();
+
+        // Starts with the current time, the hours wrap around
+        assert!(matches!(
+            press(&mut menu, Button::Set, &context),
+            MenuAction::None
+        ));
+        for _ in 0..12 {
+            press(&mut menu, Button::Up, &context);
+        }
+        assert!(matches!(
+            menu,
+            Menu::Time {
+                hours: 0,
+                minutes: 34,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            press(&mut menu, Button::Set, &context),
+            MenuAction::None
+        ));
+        menu.handle_event(ButtonEvent::Repeat(Button::Down), &context);
+        let MenuAction::SetTime(time) = press(&mut menu, Button::Set, &context) else {
+            panic!()
+        };
+        assert_eq!(time, NaiveTime::from_hms_opt(0, 33, 0).unwrap());
+        assert!(matches!(menu, Menu::Select(MenuItem::Time)));
+    }
+
+    #[test]
+    fn new_alarm_is_confirmed_after_the_days() {
+        let alarms = [None; MAX_ALARMS];
+        let context = context(&alarms);
+        let mut menu = Menu::Select(MenuItem::Alarm);
+
+        press(&mut menu, Button::Set, &context);
+        press(&mut menu, Button::Down, &context);
+        assert!(matches!(
+            menu,
+            Menu::AlarmSlot {
+                index: 7,
+                used: false
+            }
+        ));
+
+        // Defaults to 07:00 on all days
+        press(&mut menu, Button::Set, &context);
+        press(&mut menu, Button::Set, &context);
+        press(&mut menu, Button::Set, &context);
+        press(&mut menu, Button::Up, &context);
+        let MenuAction::SetAlarm(7, Some(alarm)) = press(&mut menu, Button::Set, &context) else {
+            panic!()
+        };
+        assert_eq!(alarm.time, NaiveTime::from_hms_opt(7, 0, 0).unwrap());
+        assert!(alarm.weekdays == Weekdays::WORKDAYS);
+        assert_eq!(alarm.label.as_str(), "Alarm");
+        assert!(matches!(
+            menu,
+            Menu::AlarmSlot {
+                index: 7,
+                used: true
+            }
+        ));
+    }
+
+    #[test]
+    fn alarm_is_deleted_by_the_last_days_choice() {
+        let mut alarms = [None; MAX_ALARMS];
+        alarms[0] = Some(Alarm {
+            time: NaiveTime::from_hms_opt(6, 30, 0).unwrap(),
+            weekdays: Weekdays::WEEKEND,
+            label: AlarmLabel::new("Gym").unwrap(),
+        });
+        let context = context(&alarms);
+        let mut menu = Menu::Select(MenuItem::Alarm);
+
+        press(&mut menu, Button::Set, &context);
+        assert!(matches!(
+            menu,
+            Menu::AlarmSlot {
+                index: 0,
+                used: true
+            }
+        ));
+
+        // Starts with the values of the alarm
+        press(&mut menu, Button::Set, &context);
+        assert!(matches!(
+            menu,
+            Menu::Alarm {
+                hours: 6,
+                minutes: 30,
+                ..
+            }
+        ));
+        press(&mut menu, Button::Set, &context);
+        press(&mut menu, Button::Set, &context);
+        press(&mut menu, Button::Up, &context);
+        let action = press(&mut menu, Button::Set, &context);
+        assert!(matches!(action, MenuAction::SetAlarm(0, None)));
+        assert!(matches!(
+            menu,
+            Menu::AlarmSlot {
+                index: 0,
+                used: false
+            }
+        ));
+    }
+
+    #[test]
+    fn brightness_steps_through_auto() {
+        let alarms = [None; MAX_ALARMS];
+        let context = context(&alarms);
+        let mut menu = Menu::Select(MenuItem::Brightness);
+
+        press(&mut menu, Button::Set, &context);
+        for _ in 0..5 {
+            press(&mut menu, Button::Up, &context);
+        }
+        assert!(matches!(menu, Menu::Brightness(Brightness::Fixed(100))));
+        press(&mut menu, Button::Up, &context);
+        assert!(matches!(menu, Menu::Brightness(Brightness::Auto)));
+        press(&mut menu, Button::Up, &context);
+        assert!(matches!(menu, Menu::Brightness(Brightness::Fixed(0))));
+        press(&mut menu, Button::Down, &context);
+        press(&mut menu, Button::Down, &context);
+
+        let action = press(&mut menu, Button::Set, &context);
+        assert!(matches!(
+            action,
+            MenuAction::SetBrightness(Brightness::Fixed(100))
+        ));
+        assert!(matches!(menu, Menu::Select(MenuItem::Brightness)));
+    }
+}
@@ -0,0 +1,148 @@
+//! Night mode: Dimmed or blank display according to a daily schedule.
+
+use crate::settings::store::{Reader, Writer};
+
+use ds323x::{NaiveTime, Timelike};
+
+use core::fmt;
+
+/// Brightness in percent used by the night mode (if not blank).
+pub const NIGHT_BRIGHTNESS: u8 = 1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NightMode {
+    Off,
+    /// The playlist keeps running at the night brightness.
+    Dim,
+    /// The display is switched off.
+    Blank,
+    /// Only the time is shown at the night brightness.
+    TimeOnly,
+}
+
+impl NightMode {
+    pub fn to_raw(self) -> u8 {
+        match self {
+            NightMode::Off => 0,
+            NightMode::Dim => 1,
+            NightMode::Blank => 2,
+            NightMode::TimeOnly => 3,
+        }
+    }
+
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(NightMode::Off),
+            1 => Some(NightMode::Dim),
+            2 => Some(NightMode::Blank),
+            3 => Some(NightMode::TimeOnly),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NightMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NightMode::Off => write!(f, "off"),
+            NightMode::Dim => write!(f, "dim"),
+            NightMode::Blank => write!(f, "blank"),
+            NightMode::TimeOnly => write!(f, "time"),
+        }
+    }
+}
+
+/// Time of day the night starts (inclusive) and ends (exclusive). Wraps around midnight if the
+/// start is later than the end.
+#[derive(Clone, Copy)]
+pub struct NightSchedule {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl NightSchedule {
+    pub fn contains(&self, time: &NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= *time && *time < self.end
+        } else {
+            *time >= self.start || *time < self.end
+        }
+    }
+
+    pub fn serialize(&self, writer: &mut Writer) {
+        for time in [self.start, self.end] {
+            writer.write_u8(time.hour() as u8);
+            writer.write_u8(time.minute() as u8);
+        }
+    }
+
+    pub fn deserialize(reader: &mut Reader) -> Option<Self> {
+        let mut read_time = || {
+            let hour = reader.read_u8()?;
+            let minute = reader.read_u8()?;
+            NaiveTime::from_hms_opt(hour as u32, minute as u32, 0)
+        };
+
+        Some(Self {
+            start: read_time()?,
+            end: read_time()?,
+        })
+    }
+}
+
+impl fmt::Display for NightSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start.hour(),
+            self.start.minute(),
+            self.end.hour(),
+            self.end.minute()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn schedule(start: NaiveTime, end: NaiveTime) -> NightSchedule {
+        NightSchedule { start, end }
+    }
+
+    #[test]
+    fn night_may_wrap_around_midnight() {
+        let schedule = schedule(time(22, 30), time(6, 15));
+
+        assert!(!schedule.contains(&time(22, 29)));
+        assert!(schedule.contains(&time(22, 30)));
+        assert!(schedule.contains(&time(0, 0)));
+        assert!(schedule.contains(&time(6, 14)));
+        assert!(!schedule.contains(&time(6, 15)));
+        assert!(!schedule.contains(&time(12, 0)));
+    }
+
+    #[test]
+    fn night_may_be_within_a_day() {
+        let schedule = schedule(time(0, 30), time(5, 0));
+
+        assert!(!schedule.contains(&time(0, 29)));
+        assert!(schedule.contains(&time(0, 30)));
+        assert!(schedule.contains(&time(4, 59)));
+        assert!(!schedule.contains(&time(5, 0)));
+        assert!(!schedule.contains(&time(23, 0)));
+    }
+
+    #[test]
+    fn night_with_the_same_start_and_end_is_empty() {
+        let schedule = schedule(time(22, 0), time(22, 0));
+
+        assert!(!schedule.contains(&time(22, 0)));
+        assert!(!schedule.contains(&time(3, 0)));
+    }
+}
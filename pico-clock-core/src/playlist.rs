@@ -0,0 +1,169 @@
+//! Ordered list of screens shown in rotation by the display FSM.
+
+use crate::settings::store::{Reader, Writer};
+use crate::settings::MAX_TEXTS;
+
+use core::fmt;
+
+pub const MAX_PLAYLIST_LEN: usize = 8;
+
+pub type Playlist = [Option<PlaylistEntry>; MAX_PLAYLIST_LEN];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Screen {
+    Time,
+    Date,
+    Temperature,
+    /// Scrolling text with the given index.
+    Text(u8),
+    /// Countdown timer or stopwatch (skipped if switched off).
+    Countdown,
+}
+
+impl Screen {
+    /// Parses 'time', 'date', 'temp', 'text1' to 'text4' (or 'text' for the first text) or
+    /// 'countdown'.
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "time" => Some(Screen::Time),
+            "date" => Some(Screen::Date),
+            "temp" => Some(Screen::Temperature),
+            "text" => Some(Screen::Text(0)),
+            "countdown" => Some(Screen::Countdown),
+            _ => {
+                let number: usize = input.strip_prefix("text")?.parse().ok()?;
+                if (1..=MAX_TEXTS).contains(&number) {
+                    Some(Screen::Text((number - 1) as u8))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn to_raw(self) -> u8 {
+        match self {
+            Screen::Time => 0,
+            Screen::Date => 1,
+            Screen::Temperature => 2,
+            Screen::Countdown => 3,
+            Screen::Text(index) => 0x10 + index,
+        }
+    }
+
+    fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Screen::Time),
+            1 => Some(Screen::Date),
+            2 => Some(Screen::Temperature),
+            3 => Some(Screen::Countdown),
+            x if x >= 0x10 && ((x - 0x10) as usize) < MAX_TEXTS => Some(Screen::Text(x - 0x10)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Screen::Time => write!(f, "time"),
+            Screen::Date => write!(f, "date"),
+            Screen::Temperature => write!(f, "temp"),
+            Screen::Text(index) => write!(f, "text{}", index + 1),
+            Screen::Countdown => write!(f, "countdown"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct PlaylistEntry {
+    pub screen: Screen,
+    /// Duration in seconds, or the number of times the text is scrolled for a text screen.
+    pub duration: u8,
+}
+
+impl PlaylistEntry {
+    /// Uses a default duration (or scroll count) if none is given.
+    pub fn new(screen: Screen, duration: Option<u8>) -> Self {
+        let default = match screen {
+            Screen::Time | Screen::Countdown => 5,
+            Screen::Date | Screen::Temperature => 3,
+            Screen::Text(_) => 1,
+        };
+
+        Self {
+            screen,
+            duration: duration.unwrap_or(default),
+        }
+    }
+}
+
+impl fmt::Display for PlaylistEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Screen::Text(_) = self.screen {
+            write!(f, "{} x{}", self.screen, self.duration)
+        } else {
+            write!(f, "{} {}s", self.screen, self.duration)
+        }
+    }
+}
+
+/// Playlist used if the user has not defined one.
+pub fn default_playlist() -> Playlist {
+    let mut playlist = [None; MAX_PLAYLIST_LEN];
+    playlist[0] = Some(PlaylistEntry::new(Screen::Time, None));
+    playlist[1] = Some(PlaylistEntry::new(Screen::Date, None));
+    playlist[2] = Some(PlaylistEntry::new(Screen::Temperature, None));
+    playlist[3] = Some(PlaylistEntry::new(Screen::Text(0), None));
+    playlist
+}
+
+pub fn serialize_playlist(playlist: &Playlist, writer: &mut Writer) {
+    writer.write_u8(playlist.iter().flatten().count() as u8);
+    for entry in playlist.iter().flatten() {
+        writer.write_u8(entry.screen.to_raw());
+        writer.write_u8(entry.duration);
+    }
+}
+
+/// Returns 'None' if the playlist is invalid or empty. All entries are read anyway, so the
+/// fields following them are read from the right position.
+pub fn deserialize_playlist(reader: &mut Reader) -> Option<Playlist> {
+    let mut playlist = [None; MAX_PLAYLIST_LEN];
+
+    let count = reader.read_u8()? as usize;
+    let mut valid = (1..=MAX_PLAYLIST_LEN).contains(&count);
+    for index in 0..count {
+        let screen = Screen::from_raw(reader.read_u8()?);
+        let duration = reader.read_u8()?;
+        match (screen, playlist.get_mut(index)) {
+            (Some(screen), Some(x)) => *x = Some(PlaylistEntry { screen, duration }),
+            _ => valid = false,
+        }
+    }
+
+    valid.then_some(playlist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playlist_is_read_completely_even_if_invalid() {
+        let data = [
+            3, // Count
+            0, 10, // Time
+            0x20, 1, // Invalid text index
+            1, 5,    // Date
+            0xAB, // Next field
+        ];
+        let mut reader = Reader::new(&data);
+        assert!(deserialize_playlist(&mut reader).is_none());
+        assert_eq!(reader.read_u8(), Some(0xAB));
+
+        let mut reader = Reader::new(&[0, 0xAB]);
+        assert!(deserialize_playlist(&mut reader).is_none());
+        assert_eq!(reader.read_u8(), Some(0xAB));
+    }
+}
@@ -0,0 +1,125 @@
+//! Interface of the real time clocks (implemented by the firmware for the supported RTCs).
+//!
+//! The RTC keeps UTC in 24-hour mode. Local time is derived using the configured time zone.
+
+use crate::timezone::TimeZone;
+
+use ds323x::{Hours, NaiveDateTime};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RtcError {
+    /// Communication with the RTC failed.
+    Bus,
+    /// The RTC returned invalid data or rejected the input (e.g. the time has not been set
+    /// after a power loss or the year is out of range).
+    InvalidData,
+    /// The function is not supported by the RTC.
+    Unsupported,
+}
+
+/// Alarms of the RTC used by the alarm clock.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlarmSlot {
+    /// Next due alarm of the user (the day, hour and minute are compared).
+    Next,
+    /// Snoozed alarm (the hour and minute are compared).
+    Snooze,
+}
+
+/// Source of the date and time (UTC). Functions not available on all RTCs return
+/// 'RtcError::Unsupported' by default.
+pub trait TimeSource {
+    fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError>;
+
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError>;
+
+    /// Gets the temperature in degrees Celsius.
+    fn get_temperature(&mut self) -> Result<f32, RtcError> {
+        Err(RtcError::Unsupported)
+    }
+
+    /// Programs an alarm to go off at the given time (UTC) and clears its flag.
+    fn set_alarm(&mut self, _slot: AlarmSlot, _datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        Err(RtcError::Unsupported)
+    }
+
+    /// Checks the flag of an alarm (set when the alarm went off until it is cleared).
+    fn has_alarm_matched(&mut self, _slot: AlarmSlot) -> Result<bool, RtcError> {
+        Err(RtcError::Unsupported)
+    }
+
+    fn clear_alarm(&mut self, _slot: AlarmSlot) -> Result<(), RtcError> {
+        Err(RtcError::Unsupported)
+    }
+
+    /// Gets the offset trimming the frequency of the oscillator.
+    fn get_aging_offset(&mut self) -> Result<i8, RtcError> {
+        Err(RtcError::Unsupported)
+    }
+
+    /// Sets the offset trimming the frequency of the oscillator (effective immediately).
+    fn set_aging_offset(&mut self, _offset: i8) -> Result<(), RtcError> {
+        Err(RtcError::Unsupported)
+    }
+
+    /// Outputs a 1 Hz square wave (falling edge when the seconds are incremented) as used by
+    /// the 'sqw' module.
+    fn enable_square_wave(&mut self) -> Result<(), RtcError> {
+        Err(RtcError::Unsupported)
+    }
+}
+
+/// Hour format used for displaying the time.
+#[derive(Clone, Copy, PartialEq)]
+pub enum HourMode {
+    H24,
+    H12,
+}
+
+impl HourMode {
+    pub fn to_raw(self) -> u8 {
+        match self {
+            HourMode::H24 => 0,
+            HourMode::H12 => 1,
+        }
+    }
+
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HourMode::H24),
+            1 => Some(HourMode::H12),
+            _ => None,
+        }
+    }
+}
+
+/// Converts an hour (0-23) to the representation of the given hour mode.
+pub fn to_hours(hour: u32, mode: HourMode) -> Hours {
+    let hour = hour as u8;
+    match mode {
+        HourMode::H24 => Hours::H24(hour),
+        HourMode::H12 => match hour {
+            0 => Hours::AM(12),
+            1..=11 => Hours::AM(hour),
+            12 => Hours::PM(12),
+            _ => Hours::PM(hour - 12),
+        },
+    }
+}
+
+/// Reads the date and time and converts it to local time.
+pub fn get_local_datetime<R: TimeSource>(
+    rtc: &mut R,
+    time_zone: &TimeZone,
+) -> Result<NaiveDateTime, RtcError> {
+    Ok(time_zone.utc_to_local(&rtc.get_datetime()?))
+}
+
+/// Sets the date and time given as local time.
+pub fn set_local_datetime<R: TimeSource>(
+    rtc: &mut R,
+    time_zone: &TimeZone,
+    local: &NaiveDateTime,
+) -> Result<(), RtcError> {
+    rtc.set_datetime(&time_zone.local_to_utc(local))
+}
@@ -0,0 +1,248 @@
+//! Settings that can be changed by the user at runtime.
+
+pub mod store;
+
+use crate::alarm::{self, Alarms, MAX_ALARMS};
+use crate::brightness::Brightness;
+use crate::chime::QuietHours;
+use crate::display_fsm::Transition;
+use crate::drift::DriftLog;
+use crate::night::{NightMode, NightSchedule};
+use crate::playlist::{self, Playlist};
+use crate::rtc::HourMode;
+use crate::temperature::TemperatureUnit;
+use crate::timezone::TimeZone;
+
+use store::{Reader, Writer};
+
+use ds323x::NaiveTime;
+
+/// Number of scrolling texts that can be used in the playlist.
+pub const MAX_TEXTS: usize = 4;
+
+/// Maximum length of the scrolling text in bytes.
+/// Note: The text is additionally limited by the width of a 'TextBitmap'.
+pub const MAX_TEXT_LEN: usize = 64;
+
+/// Text stored without heap (only printable ASCII characters are accepted).
+#[derive(Clone, Copy)]
+pub struct FixedText<const MAX_LEN: usize> {
+    data: [u8; MAX_LEN],
+    len: usize,
+}
+
+pub type ScrollText = FixedText<MAX_TEXT_LEN>;
+
+impl<const MAX_LEN: usize> FixedText<MAX_LEN> {
+    /// Returns 'None' if the text is too long or contains non-printable/non-ASCII characters.
+    pub fn new(text: &str) -> Option<Self> {
+        if text.len() > MAX_LEN || !text.bytes().all(|x| (0x20..=0x7E).contains(&x)) {
+            return None;
+        }
+
+        let mut data = [0; MAX_LEN];
+        data[0..text.len()].clone_from_slice(text.as_bytes());
+
+        Some(Self {
+            data,
+            len: text.len(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Cannot fail because only ASCII characters have been accepted.
+        core::str::from_utf8(&self.data[0..self.len]).unwrap()
+    }
+
+    pub fn serialize(&self, writer: &mut Writer) {
+        writer.write_u8(self.len as u8);
+        writer.write_bytes(&self.data[0..self.len]);
+    }
+
+    pub fn deserialize(reader: &mut Reader) -> Option<Self> {
+        let len = reader.read_u8()? as usize;
+        let text = core::str::from_utf8(reader.read_bytes(len)?).ok()?;
+        Self::new(text)
+    }
+}
+
+/// Order of day and month when showing the date.
+#[derive(Clone, Copy)]
+pub enum DateFormat {
+    /// DD.MM
+    DayMonth,
+    /// MM/DD
+    MonthDay,
+}
+
+impl DateFormat {
+    fn to_raw(self) -> u8 {
+        match self {
+            DateFormat::DayMonth => 0,
+            DateFormat::MonthDay => 1,
+        }
+    }
+
+    fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DateFormat::DayMonth),
+            1 => Some(DateFormat::MonthDay),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Settings {
+    /// Scrolling texts (empty ones are skipped in the playlist).
+    pub texts: [ScrollText; MAX_TEXTS],
+    pub alarms: Alarms,
+    pub temperature_unit: TemperatureUnit,
+    pub hour_mode: HourMode,
+    pub date_format: DateFormat,
+    pub playlist: Playlist,
+    /// Blink the colon of the time screen (synchronized to the seconds of the RTC).
+    pub colon_blink: bool,
+    /// Show the seconds as a bar in the bottom row of the time screen.
+    pub seconds_bar: bool,
+    pub transition: Transition,
+    /// Play a melody at every full hour.
+    pub hourly_chime: bool,
+    /// Hours without chime (if any).
+    pub quiet_hours: Option<QuietHours>,
+    pub brightness: Brightness,
+    pub night_mode: NightMode,
+    pub night_schedule: NightSchedule,
+    pub time_zone: TimeZone,
+    pub drift_log: DriftLog,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            texts: [
+                ScrollText::new("Hello world!").unwrap(),
+                ScrollText::new("").unwrap(),
+                ScrollText::new("").unwrap(),
+                ScrollText::new("").unwrap(),
+            ],
+            alarms: [None; MAX_ALARMS],
+            temperature_unit: TemperatureUnit::Celsius,
+            hour_mode: HourMode::H24,
+            date_format: DateFormat::DayMonth,
+            playlist: playlist::default_playlist(),
+            colon_blink: true,
+            seconds_bar: false,
+            transition: Transition::None,
+            hourly_chime: false,
+            quiet_hours: Some(QuietHours { start: 22, end: 7 }),
+            brightness: Brightness::Fixed(100),
+            night_mode: NightMode::Off,
+            night_schedule: NightSchedule {
+                start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(6, 30, 0).unwrap(),
+            },
+            time_zone: TimeZone::utc(),
+            drift_log: DriftLog::new(),
+        }
+    }
+
+    /// Field order must not be changed. New fields must be appended (and 'store::VERSION'
+    /// incremented), so records of older versions remain readable.
+    fn serialize(&self, writer: &mut Writer) {
+        self.texts[0].serialize(writer);
+        alarm::serialize_alarms(&self.alarms, writer);
+        writer.write_u8(self.temperature_unit.to_raw());
+        writer.write_u8(self.hour_mode.to_raw());
+        writer.write_u8(self.date_format.to_raw());
+        playlist::serialize_playlist(&self.playlist, writer);
+        for text in &self.texts[1..] {
+            text.serialize(writer);
+        }
+        writer.write_u8(self.colon_blink as u8);
+        writer.write_u8(self.seconds_bar as u8);
+        writer.write_u8(self.transition.to_raw());
+        writer.write_u8(self.hourly_chime as u8);
+        // Start hour of 0xFF if there are no quiet hours
+        let (start, end) = self.quiet_hours.map_or((0xFF, 0), |x| (x.start, x.end));
+        writer.write_u8(start);
+        writer.write_u8(end);
+        writer.write_u8(self.brightness.to_raw());
+        writer.write_u8(self.night_mode.to_raw());
+        self.night_schedule.serialize(writer);
+        self.time_zone.serialize(writer);
+        self.drift_log.serialize(writer);
+    }
+
+    /// Fields missing in the record (because it was written by an older version) or being
+    /// invalid keep their default value.
+    fn deserialize(reader: &mut Reader) -> Self {
+        let mut settings = Self::new();
+
+        if let Some(text) = ScrollText::deserialize(reader) {
+            settings.texts[0] = text;
+        }
+        if let Some(alarms) = alarm::deserialize_alarms(reader) {
+            settings.alarms = alarms;
+        }
+        if let Some(unit) = reader.read_u8().and_then(TemperatureUnit::from_raw) {
+            settings.temperature_unit = unit;
+        }
+        if let Some(hour_mode) = reader.read_u8().and_then(HourMode::from_raw) {
+            settings.hour_mode = hour_mode;
+        }
+        if let Some(date_format) = reader.read_u8().and_then(DateFormat::from_raw) {
+            settings.date_format = date_format;
+        }
+        if let Some(playlist) = playlist::deserialize_playlist(reader) {
+            settings.playlist = playlist;
+        }
+        for text in settings.texts[1..].iter_mut() {
+            if let Some(x) = ScrollText::deserialize(reader) {
+                *text = x;
+            }
+        }
+        if let Some(colon_blink) = reader.read_u8() {
+            settings.colon_blink = colon_blink != 0;
+        }
+        if let Some(seconds_bar) = reader.read_u8() {
+            settings.seconds_bar = seconds_bar != 0;
+        }
+        if let Some(transition) = reader.read_u8().and_then(Transition::from_raw) {
+            settings.transition = transition;
+        }
+        if let Some(hourly_chime) = reader.read_u8() {
+            settings.hourly_chime = hourly_chime != 0;
+        }
+        if let (Some(start), Some(end)) = (reader.read_u8(), reader.read_u8()) {
+            settings.quiet_hours = match start {
+                0xFF => None,
+                _ if start < 24 && end < 24 => Some(QuietHours { start, end }),
+                _ => settings.quiet_hours,
+            };
+        }
+        if let Some(brightness) = reader.read_u8().and_then(Brightness::from_raw) {
+            settings.brightness = brightness;
+        }
+        if let Some(night_mode) = reader.read_u8().and_then(NightMode::from_raw) {
+            settings.night_mode = night_mode;
+        }
+        if let Some(night_schedule) = NightSchedule::deserialize(reader) {
+            settings.night_schedule = night_schedule;
+        }
+        if let Some(time_zone) = TimeZone::deserialize(reader) {
+            settings.time_zone = time_zone;
+        }
+        if let Some(drift_log) = DriftLog::deserialize(reader) {
+            settings.drift_log = drift_log;
+        }
+
+        settings
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,387 @@
+//! Persistent storage of the settings in the last two sectors of the flash.
+//!
+//! The settings are written as records into fixed-size slots. Each update goes to the slot
+//! following the latest record (wear levelling), continuing in the other sector when a sector
+//! is full. A sector is only erased when switching to it, so the latest record of the other
+//! sector always survives a power loss during an update.
+//!
+//! Record layout (little endian):
+//! - magic (u32)
+//! - version (u16): layout version of the payload (newer versions only append fields)
+//! - payload length (u16)
+//! - sequence number (u32): incremented with each record, the highest one is the latest
+//! - CRC-32 (u32) over version, payload length, sequence number and payload
+//! - payload
+
+use super::Settings;
+use crate::flash::{Flash, FLASH_SIZE, PAGE_SIZE, SECTOR_SIZE};
+
+/// Layout version of the payload.
+pub const VERSION: u16 = 1;
+
+const MAGIC: u32 = 0x5345_5454; // "SETT"
+
+/// Offset of the store in the flash (must match the reserved space in 'memory.x' of the firmware).
+const STORE_OFFSET: u32 = FLASH_SIZE - NUMBER_OF_SECTORS * SECTOR_SIZE;
+const NUMBER_OF_SECTORS: u32 = 2;
+
+const SLOT_SIZE: u32 = 4 * PAGE_SIZE;
+const SLOTS_PER_SECTOR: u32 = SECTOR_SIZE / SLOT_SIZE;
+const NUMBER_OF_SLOTS: u32 = NUMBER_OF_SECTORS * SLOTS_PER_SECTOR;
+
+const HEADER_SIZE: usize = 16;
+const MAX_PAYLOAD_SIZE: usize = SLOT_SIZE as usize - HEADER_SIZE;
+
+/// Serialization of the payload.
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub(crate) fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        // Assumption: The settings always fit into a slot (otherwise panic), see the test
+        // 'largest_settings_fit_into_a_slot'
+        self.buffer[self.len..self.len + data.len()].clone_from_slice(data);
+        self.len += data.len();
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+}
+
+/// Deserialization of the payload. All functions return 'None' at the end of the payload.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Option<u8> {
+        Some(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Option<u16> {
+        let bytes = self.read_bytes(2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn read_u32(&mut self) -> Option<u32> {
+        let bytes = self.read_bytes(4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Valid record found in a slot.
+struct Record<'a> {
+    slot: u32,
+    sequence: u32,
+    payload: &'a [u8],
+}
+
+/// Loads the latest valid settings (or 'None' if no valid record exists).
+pub fn load<F: Flash>(flash: &F) -> Option<Settings> {
+    // Records of newer versions are read as well (only the known leading fields are used).
+    let record = find_latest_record(flash)?;
+    let mut reader = Reader::new(record.payload);
+    Some(Settings::deserialize(&mut reader))
+}
+
+/// Writes the settings as a new record.
+///
+/// On the RP2040, all other tasks are blocked for the duration of the flash operations (up to
+/// some 100 ms when a sector has to be erased).
+pub fn save<F: Flash>(flash: &mut F, settings: &Settings) {
+    let mut slot_data = [0xFFu8; SLOT_SIZE as usize];
+
+    let payload_len = {
+        let mut writer = Writer::new(&mut slot_data[HEADER_SIZE..]);
+        settings.serialize(&mut writer);
+        writer.len
+    };
+    assert!(payload_len <= MAX_PAYLOAD_SIZE);
+
+    let (mut slot, sequence) = match find_latest_record(flash) {
+        Some(x) => ((x.slot + 1) % NUMBER_OF_SLOTS, x.sequence.wrapping_add(1)),
+        None => (0, 0),
+    };
+
+    let (header, payload) = slot_data.split_at_mut(HEADER_SIZE);
+    let payload = &payload[0..payload_len];
+    let mut writer = Writer::new(header);
+    writer.write_u32(MAGIC);
+    writer.write_u16(VERSION);
+    writer.write_u16(payload_len as u16);
+    writer.write_u32(sequence);
+    let crc = record_crc(&header[4..12], payload);
+    Writer::new(&mut header[12..16]).write_u32(crc);
+
+    // A sector is erased when entering it. If the slot is unexpectedly not erased (e.g. after a
+    // power loss during a previous update), continue with the other sector.
+    if slot % SLOTS_PER_SECTOR != 0 && !is_erased(slot_content(flash, slot)) {
+        slot = (slot / SLOTS_PER_SECTOR + 1) * SLOTS_PER_SECTOR % NUMBER_OF_SLOTS;
+    }
+    if slot % SLOTS_PER_SECTOR == 0 {
+        flash.erase(slot_offset(slot), SECTOR_SIZE);
+    }
+
+    flash.program(slot_offset(slot), &slot_data);
+}
+
+fn find_latest_record<F: Flash>(flash: &F) -> Option<Record<'_>> {
+    (0..NUMBER_OF_SLOTS)
+        .filter_map(|x| parse_slot(flash, x))
+        .max_by_key(|x| x.sequence)
+}
+
+fn parse_slot<F: Flash>(flash: &F, slot: u32) -> Option<Record<'_>> {
+    let data = slot_content(flash, slot);
+    let (header, payload) = data.split_at(HEADER_SIZE);
+
+    let mut reader = Reader::new(header);
+    let magic = reader.read_u32()?;
+    let _version = reader.read_u16()?;
+    let payload_len = reader.read_u16()? as usize;
+    let sequence = reader.read_u32()?;
+    let crc = reader.read_u32()?;
+
+    if magic != MAGIC || payload_len > MAX_PAYLOAD_SIZE {
+        return None;
+    }
+    let payload = &payload[0..payload_len];
+    if crc != record_crc(&header[4..12], payload) {
+        return None;
+    }
+
+    Some(Record {
+        slot,
+        sequence,
+        payload,
+    })
+}
+
+fn slot_offset(slot: u32) -> u32 {
+    STORE_OFFSET + slot * SLOT_SIZE
+}
+
+fn slot_content<F: Flash>(flash: &F, slot: u32) -> &[u8] {
+    flash.read(slot_offset(slot), SLOT_SIZE)
+}
+
+fn is_erased(data: &[u8]) -> bool {
+    data.iter().all(|x| *x == 0xFF)
+}
+
+fn record_crc(header: &[u8], payload: &[u8]) -> u32 {
+    !crc32_update(crc32_update(0xFFFF_FFFF, header), payload)
+}
+
+/// CRC-32 (IEEE 802.3), bitwise implementation (the amount of data is small).
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for x in data {
+        crc ^= *x as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::{ScrollText, MAX_TEXTS, MAX_TEXT_LEN};
+    use crate::alarm::{Alarm, AlarmLabel, Weekdays, MAX_ALARMS, MAX_LABEL_LEN};
+    use crate::drift::MAX_MEASUREMENTS as MAX_DRIFT_MEASUREMENTS;
+    use crate::playlist::{PlaylistEntry, Screen, MAX_PLAYLIST_LEN};
+    use crate::timezone::{TimeZone, MAX_TZ_LEN};
+
+    use ds323x::NaiveTime;
+
+    use std::format;
+    use std::string::String;
+    use std::vec::Vec;
+
+    /// Flash of which only the store is backed by memory.
+    struct MockFlash {
+        store: Vec<u8>,
+    }
+
+    impl MockFlash {
+        fn new() -> Self {
+            Self {
+                store: vec![0xFF; (NUMBER_OF_SECTORS * SECTOR_SIZE) as usize],
+            }
+        }
+
+        fn range(offset: u32, len: u32) -> core::ops::Range<usize> {
+            assert!(offset >= STORE_OFFSET);
+            let start = (offset - STORE_OFFSET) as usize;
+            start..start + len as usize
+        }
+    }
+
+    impl Flash for MockFlash {
+        fn read(&self, offset: u32, len: u32) -> &[u8] {
+            &self.store[Self::range(offset, len)]
+        }
+
+        fn erase(&mut self, offset: u32, len: u32) {
+            assert_eq!(offset % SECTOR_SIZE, 0);
+            assert_eq!(len % SECTOR_SIZE, 0);
+            self.store[Self::range(offset, len)].fill(0xFF);
+        }
+
+        fn program(&mut self, offset: u32, data: &[u8]) {
+            assert_eq!(offset % PAGE_SIZE, 0);
+            assert_eq!(data.len() as u32 % PAGE_SIZE, 0);
+            let range = Self::range(offset, data.len() as u32);
+            assert!(is_erased(&self.store[range.clone()]));
+            self.store[range].clone_from_slice(data);
+        }
+    }
+
+    fn settings_with_text(text: &str) -> Settings {
+        let mut settings = Settings::new();
+        settings.texts[0] = ScrollText::new(text).unwrap();
+        settings
+    }
+
+    fn loaded_text(flash: &MockFlash) -> String {
+        load(flash).unwrap().texts[0].as_str().into()
+    }
+
+    #[test]
+    fn saved_settings_are_loaded() {
+        let mut flash = MockFlash::new();
+        assert!(load(&flash).is_none());
+
+        save(&mut flash, &settings_with_text("first"));
+        assert_eq!(loaded_text(&flash), "first");
+        save(&mut flash, &settings_with_text("second"));
+        assert_eq!(loaded_text(&flash), "second");
+    }
+
+    #[test]
+    fn record_with_wrong_crc_is_ignored() {
+        let mut flash = MockFlash::new();
+        save(&mut flash, &settings_with_text("first"));
+        save(&mut flash, &settings_with_text("second"));
+
+        // Corrupt the text of the latest record
+        let offset = SLOT_SIZE as usize + HEADER_SIZE + 1;
+        flash.store[offset] ^= 0x01;
+        assert_eq!(loaded_text(&flash), "first");
+
+        // The slot following the latest valid record is not erased, so the other sector is used
+        save(&mut flash, &settings_with_text("third"));
+        assert_eq!(find_latest_record(&flash).unwrap().slot, SLOTS_PER_SECTOR);
+        assert_eq!(loaded_text(&flash), "third");
+    }
+
+    #[test]
+    fn records_wrap_around_into_the_other_sector() {
+        let mut flash = MockFlash::new();
+        for i in 0..SLOTS_PER_SECTOR {
+            save(&mut flash, &settings_with_text(&format!("{}", i)));
+        }
+        assert_eq!(
+            find_latest_record(&flash).unwrap().slot,
+            SLOTS_PER_SECTOR - 1
+        );
+
+        // Entering the second sector keeps the records of the first one
+        save(&mut flash, &settings_with_text("second sector"));
+        assert_eq!(find_latest_record(&flash).unwrap().slot, SLOTS_PER_SECTOR);
+        assert!(parse_slot(&flash, 0).is_some());
+
+        // Back to the first sector after filling the second one
+        for i in 1..=SLOTS_PER_SECTOR {
+            save(&mut flash, &settings_with_text(&format!("{}", i)));
+        }
+        let record = find_latest_record(&flash).unwrap();
+        assert_eq!(record.slot, 0);
+        assert_eq!(record.sequence, 2 * SLOTS_PER_SECTOR);
+        assert!(parse_slot(&flash, 1).is_none());
+        assert_eq!(loaded_text(&flash), format!("{}", SLOTS_PER_SECTOR));
+    }
+
+    #[test]
+    fn non_erased_slot_is_skipped_for_the_other_sector() {
+        let mut flash = MockFlash::new();
+        save(&mut flash, &settings_with_text("first"));
+        // Remains of an interrupted update
+        let offset = SLOT_SIZE as usize + 100;
+        flash.store[offset] = 0x00;
+
+        save(&mut flash, &settings_with_text("second"));
+        assert_eq!(find_latest_record(&flash).unwrap().slot, SLOTS_PER_SECTOR);
+        assert_eq!(loaded_text(&flash), "second");
+    }
+
+    #[test]
+    fn largest_settings_fit_into_a_slot() {
+        let mut settings = Settings::new();
+        let text = "x".repeat(MAX_TEXT_LEN);
+        settings.texts = [ScrollText::new(&text).unwrap(); MAX_TEXTS];
+        let alarm = Alarm {
+            time: NaiveTime::from_hms_opt(23, 59, 0).unwrap(),
+            weekdays: Weekdays::ALL,
+            label: AlarmLabel::new(&"y".repeat(MAX_LABEL_LEN)).unwrap(),
+        };
+        settings.alarms = [Some(alarm); MAX_ALARMS];
+        let entry = PlaylistEntry {
+            screen: Screen::Text(3),
+            duration: 255,
+        };
+        settings.playlist = [Some(entry); MAX_PLAYLIST_LEN];
+        let time_zone = "<+0330>-3:30<+0430>-4:30,M12.5.6/167,M1.1.0/-100";
+        assert_eq!(time_zone.len(), MAX_TZ_LEN);
+        settings.time_zone = TimeZone::parse(time_zone).unwrap();
+        for i in 0..=MAX_DRIFT_MEASUREMENTS as u32 {
+            settings.drift_log.record_sync(i * 86_400, -1_000_000, -128);
+        }
+
+        // Panics if the buffer is too small
+        let mut buffer = [0; MAX_PAYLOAD_SIZE];
+        settings.serialize(&mut Writer::new(&mut buffer));
+
+        let mut flash = MockFlash::new();
+        save(&mut flash, &settings);
+        let loaded = load(&flash).unwrap();
+        assert_eq!(loaded.texts[3].as_str(), text);
+        assert!(loaded.alarms[MAX_ALARMS - 1].is_some());
+        assert_eq!(loaded.time_zone.as_str(), time_zone);
+    }
+}
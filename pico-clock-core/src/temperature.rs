@@ -0,0 +1,43 @@
+//! Temperature measured by the sensor of the DS3231 (used for its temperature compensation).
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn to_raw(self) -> u8 {
+        match self {
+            TemperatureUnit::Celsius => 0,
+            TemperatureUnit::Fahrenheit => 1,
+        }
+    }
+
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TemperatureUnit::Celsius),
+            1 => Some(TemperatureUnit::Fahrenheit),
+            _ => None,
+        }
+    }
+}
+
+/// Converts the temperature read from the DS3231 to tenths of a degree in the given unit
+/// (rounded half away from zero).
+///
+/// The DS3231 has a resolution of 0.25 °C, so it is calculated exactly in hundredths.
+pub fn to_tenths(celsius: f32, unit: TemperatureUnit) -> i32 {
+    let quarters = (celsius * 4.0) as i32;
+    let hundredths = match unit {
+        TemperatureUnit::Celsius => quarters * 25,
+        // F = C * 9 / 5 + 32
+        TemperatureUnit::Fahrenheit => quarters * 45 + 3200,
+    };
+
+    if hundredths >= 0 {
+        (hundredths + 5) / 10
+    } else {
+        (hundredths - 5) / 10
+    }
+}
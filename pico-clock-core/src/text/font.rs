@@ -1,6 +1,7 @@
 //! Definition of dot patterns for all printable ASCII characters, having a height of 7 dots
 //! and most of them have a width of 4 dots.
 //! The numeric digits all have a constant width of 4 dots to simplify displaying time.
+//! A compact variant of the digits and the colon has a height of 6 dots.
 
 pub const FONT_HEIGHT: usize = 7;
 
@@ -1272,6 +1273,167 @@ pub const DEFAULT_FONT: Font = Font {
     ],
 };
 
+/// Height of the compact characters, which leave the bottom row empty (e.g. for the seconds bar
+/// below the time).
+pub const COMPACT_FONT_HEIGHT: usize = 6;
+
+/// Gets the compact dot pattern for the given character. Only the space, the numeric digits and
+/// the colon are available (with the same widths as in the default font).
+pub fn get_compact_character(c: char) -> Option<&'static FontCharacter> {
+    match c {
+        ' ' => DEFAULT_FONT.get_character(' '),
+        '0'..='9' => Some(&COMPACT_DIGITS[(c as usize) - ('0' as usize)]),
+        ':' => Some(&COMPACT_COLON),
+        _ => None,
+    }
+}
+
+const COMPACT_DIGITS: [FontCharacter; 10] = [
+    // Character '0'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '1'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0010, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0010, 4),
+            reverse_bits(0b0010, 4),
+            reverse_bits(0b0010, 4),
+            reverse_bits(0b0111, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '2'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0010, 4),
+            reverse_bits(0b0100, 4),
+            reverse_bits(0b1000, 4),
+            reverse_bits(0b1111, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '3'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b1110, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b1110, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '4'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0011, 4),
+            reverse_bits(0b0101, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1111, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '5'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b1111, 4),
+            reverse_bits(0b1000, 4),
+            reverse_bits(0b1110, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '6'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b1000, 4),
+            reverse_bits(0b1110, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '7'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b1111, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b0010, 4),
+            reverse_bits(0b0100, 4),
+            reverse_bits(0b0100, 4),
+            reverse_bits(0b0100, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '8'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+    // Character '9'
+    FontCharacter {
+        width: 4u8,
+        bit_pattern: [
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b1001, 4),
+            reverse_bits(0b0111, 4),
+            reverse_bits(0b0001, 4),
+            reverse_bits(0b0110, 4),
+            reverse_bits(0b0000, 4),
+        ],
+    },
+];
+
+const COMPACT_COLON: FontCharacter = FontCharacter {
+    width: 2u8,
+    bit_pattern: [
+        reverse_bits(0b00, 2),
+        reverse_bits(0b11, 2),
+        reverse_bits(0b00, 2),
+        reverse_bits(0b00, 2),
+        reverse_bits(0b11, 2),
+        reverse_bits(0b00, 2),
+        reverse_bits(0b00, 2),
+    ],
+};
+
 /// Helper function that allows defining the bit pattern in a visually correct bit order above and
 /// to reverse it afterwards (LSB is leftmost dot).
 /// It is a const function that is evaluated at compile time and doesn't add any overhead at
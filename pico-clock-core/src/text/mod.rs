@@ -0,0 +1,204 @@
+mod font;
+
+use font::{DEFAULT_FONT, FONT_HEIGHT};
+
+pub const TEXT_BITMAP_HEIGHT: usize = FONT_HEIGHT;
+/// Number of rows used by compact characters (the rows below are empty).
+pub const COMPACT_TEXT_HEIGHT: usize = font::COMPACT_FONT_HEIGHT;
+const CHARACTER_GAP: usize = 1;
+
+#[derive(Debug)]
+pub enum TextRenderError {
+    TextTooLong,
+    UnsupportedCharacter,
+}
+
+/// Error that occurred while rendering a particular character of a text.
+#[derive(Debug)]
+pub struct TextError {
+    /// Index of the offending character (counting characters, not bytes).
+    pub position: usize,
+    pub error: TextRenderError,
+}
+
+pub struct TextBitmap {
+    pub width: usize,
+    pub data: [u128; TEXT_BITMAP_HEIGHT],
+}
+
+impl TextBitmap {
+    pub fn new() -> Self {
+        Self {
+            width: 0,
+            data: [0; TEXT_BITMAP_HEIGHT],
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)] // Rendering is not parsing
+    pub fn from_str(text: &str) -> Result<Self, TextError> {
+        let mut text_bitmap = Self::new();
+        text_bitmap.append_text(text)?;
+
+        Ok(text_bitmap)
+    }
+
+    pub fn append_text(&mut self, text: &str) -> Result<(), TextError> {
+        for (position, c) in text.chars().enumerate() {
+            self.append_char(c)
+                .map_err(|error| TextError { position, error })?;
+        }
+        Ok(())
+    }
+
+    pub fn append_char(&mut self, c: char) -> Result<(), TextRenderError> {
+        if let Some(x) = DEFAULT_FONT.get_character(c) {
+            self.append_font_character(x)?;
+            Ok(())
+        } else {
+            Err(TextRenderError::UnsupportedCharacter)
+        }
+    }
+
+    /// Appends a character of the compact variant (only ' ', '0' to '9' and ':').
+    pub fn append_compact_char(&mut self, c: char) -> Result<(), TextRenderError> {
+        if let Some(x) = font::get_compact_character(c) {
+            self.append_font_character(x)?;
+            Ok(())
+        } else {
+            Err(TextRenderError::UnsupportedCharacter)
+        }
+    }
+
+    /// Extracts a horizontal segment from an instance.
+    ///
+    /// The resulting segment always has the requested width. Those parts that are out of the
+    /// instance bounds are filled with cleared bits (useful to implement moving section for
+    /// scrolling).
+    pub fn segment(&self, offset: isize, width: usize) -> Self {
+        assert!(width <= 128);
+
+        let mut result = Self {
+            width,
+            data: [0; TEXT_BITMAP_HEIGHT],
+        };
+
+        let mask = if width < 128 {
+            (1 << width) - 1
+        } else {
+            u128::MAX
+        };
+        for row in 0..TEXT_BITMAP_HEIGHT {
+            result.data[row] = match offset {
+                -127..=-1 => (self.data[row] << -offset) & mask,
+                0 => self.data[row] & mask,
+                1..=127 => (self.data[row] >> offset) & mask,
+                _ => 0,
+            };
+        }
+
+        result
+    }
+
+    fn append_font_character(
+        &mut self,
+        character: &font::FontCharacter,
+    ) -> Result<(), TextRenderError> {
+        let gap = if self.width > 0 { CHARACTER_GAP } else { 0 };
+
+        if self.width + gap + (character.width as usize) > 128 {
+            return Err(TextRenderError::TextTooLong);
+        }
+
+        for row in 0..TEXT_BITMAP_HEIGHT {
+            self.data[row] |= (character.bit_pattern[row] as u128) << (self.width + gap);
+        }
+        self.width += gap + (character.width as usize);
+
+        Ok(())
+    }
+}
+
+impl Default for TextBitmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_are_appended_with_a_gap() {
+        // '!' is 1 dot wide, '1' is 4 dots wide (LSB is the leftmost dot)
+        let bitmap = TextBitmap::from_str("!1").unwrap();
+
+        assert_eq!(bitmap.width, 6);
+        assert_eq!(bitmap.data[0], 0b0100 << 2 | 0b1);
+        assert_eq!(bitmap.data[5], 0b0100 << 2);
+        assert_eq!(bitmap.data[6], 0b1110 << 2 | 0b1);
+    }
+
+    #[test]
+    fn unsupported_character_is_reported_with_its_position() {
+        let error = TextBitmap::from_str("ab\u{e4}c").err().unwrap();
+
+        assert_eq!(error.position, 2);
+        assert!(matches!(error.error, TextRenderError::UnsupportedCharacter));
+    }
+
+    #[test]
+    fn text_must_fit_into_the_bitmap() {
+        // 127 dots including the gaps
+        let text = "!".repeat(64);
+        assert_eq!(TextBitmap::from_str(&text).unwrap().width, 127);
+
+        let error = TextBitmap::from_str(&(text + "!")).err().unwrap();
+        assert_eq!(error.position, 64);
+        assert!(matches!(error.error, TextRenderError::TextTooLong));
+    }
+
+    #[test]
+    fn segment_is_cut_to_its_width() {
+        let bitmap = TextBitmap::from_str("88").unwrap();
+        let segment = bitmap.segment(0, 4);
+
+        assert_eq!(segment.width, 4);
+        assert_eq!(segment.data, TextBitmap::from_str("8").unwrap().data);
+    }
+
+    #[test]
+    fn segment_with_positive_offset_skips_leading_columns() {
+        let bitmap = TextBitmap::from_str("!1").unwrap();
+        let segment = bitmap.segment(2, 4);
+
+        assert_eq!(segment.data, TextBitmap::from_str("1").unwrap().data);
+    }
+
+    #[test]
+    fn segment_with_negative_offset_starts_with_cleared_columns() {
+        let bitmap = TextBitmap::from_str("1").unwrap();
+        let segment = bitmap.segment(-3, 22);
+
+        assert_eq!(segment.width, 22);
+        for (segment_row, row) in segment.data.iter().zip(bitmap.data) {
+            assert_eq!(*segment_row, row << 3);
+        }
+    }
+
+    #[test]
+    fn segment_outside_of_the_bitmap_is_cleared() {
+        let bitmap = TextBitmap::from_str("88").unwrap();
+
+        assert_eq!(bitmap.segment(-22, 22).data, [0; TEXT_BITMAP_HEIGHT]);
+        assert_eq!(bitmap.segment(9, 22).data, [0; TEXT_BITMAP_HEIGHT]);
+        assert_eq!(bitmap.segment(128, 22).data, [0; TEXT_BITMAP_HEIGHT]);
+    }
+
+    #[test]
+    fn segment_may_have_the_full_width() {
+        let bitmap = TextBitmap::from_str(&"!".repeat(64)).unwrap();
+
+        assert_eq!(bitmap.segment(0, 128).data, bitmap.data);
+    }
+}
@@ -0,0 +1,123 @@
+//! Countdown timer and stopwatch (count-up timer) based on the tick count of FreeRTOS.
+//!
+//! The timer is owned by the display FSM. Other tasks control it by requests.
+
+use embedded_time::duration::Milliseconds;
+
+// Concurrency (critical sections provided by the firmware or the host)
+use core::cell::Cell;
+use critical_section::Mutex;
+
+/// Maximum duration of a countdown (and maximum value shown by the stopwatch).
+pub const MAX_DURATION: Milliseconds = Milliseconds((99 * 3600 + 59 * 60 + 59) * 1000);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TimerMode {
+    CountDown(Milliseconds),
+    CountUp,
+}
+
+pub struct Timer {
+    mode: TimerMode,
+    /// Time counted before the current run.
+    elapsed: u32,
+    /// Tick count when the timer has been started (if it is running).
+    started_at: Option<Milliseconds>,
+}
+
+impl Timer {
+    /// Creates a stopped timer.
+    pub fn new(mode: TimerMode) -> Self {
+        Self {
+            mode,
+            elapsed: 0,
+            started_at: None,
+        }
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    pub fn start(&mut self, now: Milliseconds) {
+        if self.started_at.is_none() && !self.is_expired(now) {
+            self.started_at = Some(now);
+        }
+    }
+
+    pub fn stop(&mut self, now: Milliseconds) {
+        self.elapsed = self.elapsed(now);
+        self.started_at = None;
+    }
+
+    /// Stops the timer and sets it back to its initial value.
+    pub fn reset(&mut self) {
+        self.elapsed = 0;
+        self.started_at = None;
+    }
+
+    /// Checks whether a countdown has reached zero (a stopwatch never expires).
+    pub fn is_expired(&self, now: Milliseconds) -> bool {
+        match self.mode {
+            TimerMode::CountDown(duration) => self.elapsed(now) >= duration.0,
+            TimerMode::CountUp => false,
+        }
+    }
+
+    /// Gets the value to be shown: The remaining time of a countdown (rounded up to full
+    /// seconds, so zero is only shown when expired) or the elapsed time of a stopwatch.
+    pub fn value(&self, now: Milliseconds) -> Milliseconds {
+        match self.mode {
+            TimerMode::CountDown(duration) => {
+                let remaining = duration.0.saturating_sub(self.elapsed(now));
+                Milliseconds(remaining.div_ceil(1000) * 1000)
+            }
+            TimerMode::CountUp => Milliseconds(self.elapsed(now)),
+        }
+    }
+
+    fn elapsed(&self, now: Milliseconds) -> u32 {
+        let running = match self.started_at {
+            // The tick count wraps around
+            Some(started_at) => now.0.wrapping_sub(started_at.0),
+            None => 0,
+        };
+
+        let elapsed = self.elapsed.saturating_add(running);
+        match self.mode {
+            TimerMode::CountDown(duration) => elapsed.min(duration.0),
+            TimerMode::CountUp => elapsed.min(MAX_DURATION.0),
+        }
+    }
+}
+
+/// Action requested by the user for the timer.
+#[derive(Clone, Copy)]
+pub enum TimerRequest {
+    /// Switches to the countdown mode with the given duration and starts it.
+    CountDown(Milliseconds),
+    /// Switches to the stopwatch mode and starts it.
+    CountUp,
+    Start,
+    Stop,
+    /// Starts the timer if stopped and stops it if running (e.g. by a button).
+    StartStop,
+    Reset,
+    /// Removes the timer and returns to the clock.
+    Off,
+}
+
+static TIMER_REQUEST: Mutex<Cell<Option<TimerRequest>>> = Mutex::new(Cell::new(None));
+
+pub fn request(request: TimerRequest) {
+    critical_section::with(|cs| TIMER_REQUEST.borrow(cs).set(Some(request)));
+}
+
+/// Takes the request that has been made since the last call (if any).
+pub fn take_request() -> Option<TimerRequest> {
+    critical_section::with(|cs| TIMER_REQUEST.borrow(cs).take())
+}
@@ -0,0 +1,447 @@
+//! Time zone given as POSIX TZ string (e.g. 'CET-1CEST,M3.5.0,M10.5.0/3'), used to convert
+//! between UTC (kept by the RTC) and local time including daylight saving time.
+//!
+//! Supported format: 'std offset [dst [offset],start[/time],end[/time]]' with the rules in the
+//! form 'Mm.w.d' (day 'd' of week 'w' of month 'm', 0 being Sunday and week 5 the last one).
+//! As defined by POSIX, the offsets are positive west of UTC.
+
+use crate::settings::store::{Reader, Writer};
+use crate::settings::FixedText;
+
+use ds323x::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// Maximum length of the TZ string.
+pub const MAX_TZ_LEN: usize = 48;
+
+const SECONDS_PER_DAY: i64 = 24 * 3600;
+
+/// Start or end of the daylight saving time.
+#[derive(Clone, Copy)]
+struct TransitionRule {
+    month: u32,
+    /// 1 to 5 (5 being the last week of the month).
+    week: u32,
+    /// 0 (Sunday) to 6.
+    weekday: u32,
+    /// Local time of the transition in seconds after midnight (may be negative or exceed a day).
+    time: i32,
+}
+
+impl TransitionRule {
+    /// Gets the time of the transition in UTC for the given year, using the offset in effect
+    /// before the transition.
+    fn to_utc(self, year: i32, offset: i32) -> Option<NaiveDateTime> {
+        let first = NaiveDate::from_ymd_opt(year, self.month, 1)?;
+        let first_weekday = first.weekday().num_days_from_sunday();
+        let mut day = 1 + (self.weekday + 7 - first_weekday) % 7 + (self.week - 1) * 7;
+
+        // The 5th week means the last one (which may be the 4th)
+        let date = loop {
+            match NaiveDate::from_ymd_opt(year, self.month, day) {
+                Some(x) => break x,
+                None if day > 7 => day -= 7,
+                None => return None,
+            }
+        };
+
+        Some(add_seconds(
+            &date.and_time(NaiveTime::MIN),
+            (self.time - offset) as i64,
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct DaylightSaving {
+    /// Offset from UTC in seconds (positive east of UTC).
+    offset: i32,
+    start: TransitionRule,
+    end: TransitionRule,
+}
+
+#[derive(Clone, Copy)]
+pub struct TimeZone {
+    text: FixedText<MAX_TZ_LEN>,
+    /// Offset of the standard time from UTC in seconds (positive east of UTC).
+    std_offset: i32,
+    dst: Option<DaylightSaving>,
+}
+
+impl TimeZone {
+    /// UTC without daylight saving time.
+    pub fn utc() -> Self {
+        // Cannot fail because the string is valid
+        Self::parse("UTC0").unwrap()
+    }
+
+    /// Returns 'None' if the TZ string is invalid or not supported.
+    pub fn parse(input: &str) -> Option<Self> {
+        let text = FixedText::new(input)?;
+        let mut parser = Parser {
+            input: input.as_bytes(),
+            position: 0,
+        };
+
+        parser.name()?;
+        let std_offset = -parser.time(24)?;
+
+        let dst = if parser.at_end() {
+            None
+        } else {
+            parser.name()?;
+            // One hour ahead of the standard time by default
+            let offset = if parser.peek() == Some(b',') {
+                std_offset + 3600
+            } else {
+                -parser.time(24)?
+            };
+
+            parser.expect(b',')?;
+            let start = parser.rule()?;
+            parser.expect(b',')?;
+            let end = parser.rule()?;
+
+            Some(DaylightSaving { offset, start, end })
+        };
+
+        if !parser.at_end() {
+            return None;
+        }
+
+        Some(Self {
+            text,
+            std_offset,
+            dst,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.text.as_str()
+    }
+
+    /// Gets the offset from UTC in seconds (positive east of UTC) at the given time (UTC).
+    pub fn offset(&self, utc: &NaiveDateTime) -> i32 {
+        let dst = match self.dst {
+            Some(x) => x,
+            None => return self.std_offset,
+        };
+
+        // The rules refer to the local year
+        let year = add_seconds(utc, self.std_offset as i64).year();
+        let start = dst.start.to_utc(year, self.std_offset);
+        let end = dst.end.to_utc(year, dst.offset);
+
+        let in_dst = match (start, end) {
+            // Northern hemisphere
+            (Some(start), Some(end)) if start <= end => start <= *utc && *utc < end,
+            // Southern hemisphere (daylight saving time at the turn of the year)
+            (Some(start), Some(end)) => *utc < end || start <= *utc,
+            _ => false,
+        };
+
+        if in_dst {
+            dst.offset
+        } else {
+            self.std_offset
+        }
+    }
+
+    pub fn utc_to_local(&self, utc: &NaiveDateTime) -> NaiveDateTime {
+        add_seconds(utc, self.offset(utc) as i64)
+    }
+
+    /// Converts a local time to UTC.
+    ///
+    /// A local time occurring twice (when the daylight saving time ends) is taken as standard
+    /// time. A local time skipped when the daylight saving time starts is shifted by the
+    /// difference of the offsets.
+    pub fn local_to_utc(&self, local: &NaiveDateTime) -> NaiveDateTime {
+        let standard = add_seconds(local, -self.std_offset as i64);
+        add_seconds(local, -self.offset(&standard) as i64)
+    }
+
+    pub fn serialize(&self, writer: &mut Writer) {
+        self.text.serialize(writer);
+    }
+
+    pub fn deserialize(reader: &mut Reader) -> Option<Self> {
+        let text = FixedText::<MAX_TZ_LEN>::deserialize(reader)?;
+        Self::parse(text.as_str())
+    }
+}
+
+/// Formats an offset in seconds like '+01:00'.
+pub struct OffsetDisplay(pub i32);
+
+impl core::fmt::Display for OffsetDisplay {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let minutes = self.0.unsigned_abs() / 60;
+        write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+/// Adds the given number of seconds (may be negative) to a date and time.
+pub fn add_seconds(datetime: &NaiveDateTime, seconds: i64) -> NaiveDateTime {
+    let total = datetime.num_seconds_from_midnight() as i64 + seconds;
+    let days = datetime.date().num_days_from_ce() as i64 + total.div_euclid(SECONDS_PER_DAY);
+
+    // Cannot fail because the RTC only supports years within the range of 'NaiveDate'
+    let date = NaiveDate::from_num_days_from_ce_opt(days as i32).unwrap();
+    let time =
+        NaiveTime::from_num_seconds_from_midnight_opt(total.rem_euclid(SECONDS_PER_DAY) as u32, 0)
+            .unwrap();
+    date.and_time(time)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn accept(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        if self.accept(c) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Skips a zone name: At least 3 letters, or any alphanumeric characters and signs in angle
+    /// brackets (e.g. '<+03>').
+    fn name(&mut self) -> Option<()> {
+        let start = self.position;
+
+        if self.accept(b'<') {
+            while self
+                .peek()
+                .is_some_and(|x| x.is_ascii_alphanumeric() || x == b'+' || x == b'-')
+            {
+                self.position += 1;
+            }
+            if self.position == start + 1 {
+                return None;
+            }
+            self.expect(b'>')
+        } else {
+            while self.peek().is_some_and(|x| x.is_ascii_alphabetic()) {
+                self.position += 1;
+            }
+            if self.position - start >= 3 {
+                Some(())
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Parses a number of 1 to 'max_digits' digits.
+    fn number(&mut self, max_digits: usize) -> Option<u32> {
+        let start = self.position;
+        let mut value = 0;
+
+        while let Some(digit) = self.peek().filter(|x| x.is_ascii_digit()) {
+            if self.position - start == max_digits {
+                return None;
+            }
+            value = value * 10 + (digit - b'0') as u32;
+            self.position += 1;
+        }
+
+        if self.position > start {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Parses '[+|-]hh[:mm[:ss]]' (hours up to 'max_hours') to seconds.
+    fn time(&mut self, max_hours: u32) -> Option<i32> {
+        let negative = if self.accept(b'-') {
+            true
+        } else {
+            self.accept(b'+');
+            false
+        };
+
+        let hours = self.number(3).filter(|x| *x <= max_hours)?;
+        let mut seconds = hours * 3600;
+        for factor in [60, 1] {
+            if !self.accept(b':') {
+                break;
+            }
+            seconds += self.number(2).filter(|x| *x < 60)? * factor;
+        }
+
+        if negative {
+            Some(-(seconds as i32))
+        } else {
+            Some(seconds as i32)
+        }
+    }
+
+    /// Parses 'Mm.w.d[/time]' (the time being 02:00 by default).
+    fn rule(&mut self) -> Option<TransitionRule> {
+        self.expect(b'M')?;
+        let month = self.number(2).filter(|x| (1..=12).contains(x))?;
+        self.expect(b'.')?;
+        let week = self.number(1).filter(|x| (1..=5).contains(x))?;
+        self.expect(b'.')?;
+        let weekday = self.number(1).filter(|x| *x <= 6)?;
+
+        let time = if self.accept(b'/') {
+            self.time(167)?
+        } else {
+            2 * 3600
+        };
+
+        Some(TransitionRule {
+            month,
+            week,
+            weekday,
+            time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn cet() -> TimeZone {
+        TimeZone::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap()
+    }
+
+    #[test]
+    fn supported_tz_strings_are_parsed() {
+        let est = TimeZone::parse("EST5EDT,M3.2.0/2:00:00,M11.1.0").unwrap();
+        assert_eq!(est.offset(&datetime(2024, 1, 1, 12, 0)), -5 * 3600);
+        assert_eq!(est.offset(&datetime(2024, 7, 1, 12, 0)), -4 * 3600);
+
+        let india = TimeZone::parse("<+0530>-5:30").unwrap();
+        assert_eq!(india.offset(&datetime(2024, 7, 1, 12, 0)), 5 * 3600 + 1800);
+        assert_eq!(india.as_str(), "<+0530>-5:30");
+
+        let utc = TimeZone::utc();
+        assert_eq!(utc.offset(&datetime(2024, 7, 1, 12, 0)), 0);
+    }
+
+    #[test]
+    fn invalid_tz_strings_are_rejected() {
+        for input in [
+            "",
+            "CE-1",
+            "CET",
+            "CET-1CEST",
+            "CET-1CEST,M3.5.0",
+            "CET-1CEST,M13.5.0,M10.5.0",
+            "CET-1CEST,M3.6.0,M10.5.0",
+            "CET-1CEST,M3.5.7,M10.5.0",
+            "CET-1CEST,J60,M10.5.0",
+            "CET-25",
+            "CET-1:60",
+            "UTC0x",
+            "<>0",
+        ] {
+            assert!(TimeZone::parse(input).is_none(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn cet_switches_at_01_00_utc() {
+        let tz = cet();
+        // Last Sundays of March and October 2024
+        assert_eq!(tz.offset(&datetime(2024, 3, 31, 0, 59)), 3600);
+        assert_eq!(tz.offset(&datetime(2024, 3, 31, 1, 0)), 7200);
+        assert_eq!(tz.offset(&datetime(2024, 10, 27, 0, 59)), 7200);
+        assert_eq!(tz.offset(&datetime(2024, 10, 27, 1, 0)), 3600);
+
+        assert_eq!(
+            tz.utc_to_local(&datetime(2024, 3, 31, 1, 0)),
+            datetime(2024, 3, 31, 3, 0)
+        );
+        assert_eq!(
+            tz.utc_to_local(&datetime(2024, 12, 31, 23, 30)),
+            datetime(2025, 1, 1, 0, 30)
+        );
+    }
+
+    #[test]
+    fn southern_hemisphere_has_daylight_saving_time_at_the_turn_of_the_year() {
+        let tz = TimeZone::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(tz.offset(&datetime(2024, 1, 15, 12, 0)), 11 * 3600);
+        assert_eq!(tz.offset(&datetime(2024, 7, 15, 12, 0)), 10 * 3600);
+
+        // Ends on 7 April 2024 at 03:00 AEDT, starts on 6 October 2024 at 02:00 AEST
+        assert_eq!(tz.offset(&datetime(2024, 4, 6, 15, 59)), 11 * 3600);
+        assert_eq!(tz.offset(&datetime(2024, 4, 6, 16, 0)), 10 * 3600);
+        assert_eq!(tz.offset(&datetime(2024, 10, 5, 15, 59)), 10 * 3600);
+        assert_eq!(tz.offset(&datetime(2024, 10, 5, 16, 0)), 11 * 3600);
+
+        // New Year in local time, but not yet in UTC
+        assert_eq!(tz.offset(&datetime(2024, 12, 31, 14, 0)), 11 * 3600);
+    }
+
+    #[test]
+    fn local_times_are_converted_to_utc() {
+        let tz = cet();
+        assert_eq!(
+            tz.local_to_utc(&datetime(2024, 7, 1, 12, 0)),
+            datetime(2024, 7, 1, 10, 0)
+        );
+        assert_eq!(
+            tz.local_to_utc(&datetime(2024, 1, 1, 0, 30)),
+            datetime(2023, 12, 31, 23, 30)
+        );
+    }
+
+    #[test]
+    fn ambiguous_local_time_is_taken_as_standard_time() {
+        // 02:30 occurs twice on 27 October 2024
+        assert_eq!(
+            cet().local_to_utc(&datetime(2024, 10, 27, 2, 30)),
+            datetime(2024, 10, 27, 1, 30)
+        );
+    }
+
+    #[test]
+    fn skipped_local_time_is_shifted() {
+        // 02:30 does not exist on 31 March 2024, it is taken as 01:30 standard time
+        let tz = cet();
+        let utc = tz.local_to_utc(&datetime(2024, 3, 31, 2, 30));
+        assert_eq!(utc, datetime(2024, 3, 31, 0, 30));
+        assert_eq!(tz.utc_to_local(&utc), datetime(2024, 3, 31, 1, 30));
+    }
+
+    #[test]
+    fn offsets_are_formatted_in_hours_and_minutes() {
+        assert_eq!(format!("{}", OffsetDisplay(3600)), "+01:00");
+        assert_eq!(format!("{}", OffsetDisplay(-(3 * 3600 + 1800))), "-03:30");
+        assert_eq!(format!("{}", OffsetDisplay(0)), "+00:00");
+    }
+}
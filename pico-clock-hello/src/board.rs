@@ -0,0 +1,83 @@
+//! Hardware of the "Pico Clock Green" kit as used by the display FSM.
+
+use crate::buzzer;
+use crate::flash::XipFlash;
+use crate::freertos::{self, Mutex};
+use crate::internal_rtc;
+use crate::light_sensor::LightSensor;
+use crate::rtc::Rtc;
+use crate::sqw;
+
+use pico_clock_core::buttons::{self, ButtonEvent};
+use pico_clock_core::hardware::Hardware;
+use pico_clock_core::melody::Melody;
+use pico_clock_core::settings::{self, Settings};
+
+// Time
+use embedded_time::duration::Milliseconds;
+
+use core::ops::DerefMut;
+
+pub struct Board {
+    settings: &'static Mutex<Settings>,
+    rtc: &'static Mutex<Rtc>,
+    light_sensor: LightSensor,
+}
+
+impl Board {
+    pub fn new(
+        settings: &'static Mutex<Settings>,
+        rtc: &'static Mutex<Rtc>,
+        light_sensor: LightSensor,
+    ) -> Self {
+        Self {
+            settings,
+            rtc,
+            light_sensor,
+        }
+    }
+}
+
+impl Hardware for Board {
+    type Rtc = Rtc;
+
+    fn settings(&self) -> impl DerefMut<Target = Settings> + '_ {
+        self.settings.lock()
+    }
+
+    fn save_settings(&mut self, settings: &Settings) {
+        settings::store::save(&mut XipFlash, settings);
+    }
+
+    fn rtc(&self) -> impl DerefMut<Target = Rtc> + '_ {
+        self.rtc.lock()
+    }
+
+    fn is_rtc_fallback_active(&self) -> bool {
+        internal_rtc::is_fallback_active()
+    }
+
+    fn colon_visible(&self) -> bool {
+        sqw::colon_visible()
+    }
+
+    fn tick_count(&self) -> Milliseconds {
+        freertos::tick_count()
+    }
+
+    fn take_button_event(&mut self) -> Option<ButtonEvent> {
+        buttons::take_event()
+    }
+
+    fn read_light_level(&mut self) -> Option<u16> {
+        self.light_sensor.read()
+    }
+
+    fn play_melody(&mut self, melody: Melody, repeat: bool) {
+        buzzer::play(melody, repeat);
+    }
+
+    fn stop_melody(&mut self) {
+        buzzer::stop();
+    }
+}
@@ -0,0 +1,177 @@
+//! Driver for the three buttons of the "Pico Clock Green" kit (active low with pull-ups).
+//!
+//! Edges are recorded by the GPIO interrupt. The button task debounces them (the level is only
+//! taken after it has been stable for some time), detects short and long presses and repeats
+//! while a button is held down. The resulting events are taken by the display FSM.
+
+use crate::freertos;
+use crate::supervisor;
+
+use pico_clock_core::buttons::{push_event, Button, ButtonEvent};
+
+use embedded_hal::digital::v2::InputPin;
+use pico::hal;
+use pico::hal::gpio::{bank0, Interrupt, Pin, PullUpInput};
+use pico::hal::pac;
+
+// Time
+use embedded_time::duration::Milliseconds;
+
+// Interrupt handler concurrency
+use core::cell::{Cell, RefCell};
+use cortex_m::interrupt;
+use cortex_m::interrupt::{CriticalSection, Mutex};
+
+/// Time the level must be stable after the last edge.
+const DEBOUNCE_TIME: Milliseconds = Milliseconds(30);
+/// Time a button must be held down for a long press.
+const LONG_PRESS_TIME: Milliseconds = Milliseconds(800);
+/// Period of the repeated events while a button is held down after a long press.
+const REPEAT_PERIOD: Milliseconds = Milliseconds(150);
+/// Period in which the button task checks the buttons.
+const POLL_PERIOD: Milliseconds = Milliseconds(10);
+
+const BUTTONS: [Button; 3] = [Button::Set, Button::Up, Button::Down];
+
+pub struct ButtonPins {
+    /// Upper button (K0).
+    pub set: Pin<bank0::Gpio15, PullUpInput>,
+    /// Middle button (K1).
+    pub up: Pin<bank0::Gpio17, PullUpInput>,
+    /// Lower button (K2).
+    pub down: Pin<bank0::Gpio2, PullUpInput>,
+}
+
+static BUTTON_PINS: Mutex<RefCell<Option<ButtonPins>>> = Mutex::new(RefCell::new(None));
+/// Tick count of the last edge of each button not processed yet (in the order of 'BUTTONS').
+static LAST_EDGES: Mutex<Cell<[Option<Milliseconds>; 3]>> = Mutex::new(Cell::new([None; 3]));
+
+/// Debounced state of a button.
+#[derive(Clone, Copy)]
+struct ButtonState {
+    /// Tick count when the button has been pressed (if it is held down).
+    pressed_at: Option<Milliseconds>,
+    /// Tick count of the long press or the last repeat (if sent for the current press).
+    repeated_at: Option<Milliseconds>,
+}
+
+/// Enables the interrupts of the buttons and starts the button task.
+pub fn start(pins: ButtonPins) {
+    for interrupt in [Interrupt::EdgeLow, Interrupt::EdgeHigh] {
+        pins.set.set_interrupt_enabled(interrupt, true);
+        pins.up.set_interrupt_enabled(interrupt, true);
+        pins.down.set_interrupt_enabled(interrupt, true);
+    }
+    interrupt::free(|cs| BUTTON_PINS.borrow(cs).replace(Some(pins)));
+
+    unsafe {
+        pac::NVIC::unmask(hal::pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    let check_in = supervisor::register("ButtonTask", Milliseconds(500));
+    freertos::create_task(
+        move || {
+            let mut states = [ButtonState {
+                pressed_at: None,
+                repeated_at: None,
+            }; 3];
+
+            loop {
+                check_in.check_in();
+                let now = freertos::tick_count();
+
+                for (index, state) in states.iter_mut().enumerate() {
+                    update_button(BUTTONS[index], index, state, now);
+                }
+
+                freertos::delay(POLL_PERIOD);
+            }
+        },
+        &freertos::TaskParameters {
+            name: "ButtonTask",
+            stack_depth: 512, // Is actually 2048 bytes because portSTACK_TYPE is uint32_t
+            priority: crate::BUTTON_TASK_PRIORITY,
+        },
+    );
+}
+
+/// Records the edges of the buttons (called by the GPIO interrupt handler).
+pub fn handle_interrupt(cs: &CriticalSection) {
+    if let Some(pins) = BUTTON_PINS.borrow(cs).borrow_mut().as_mut() {
+        let mut last_edges = LAST_EDGES.borrow(cs).get();
+
+        for interrupt in [Interrupt::EdgeLow, Interrupt::EdgeHigh] {
+            let edges = [
+                pins.set.interrupt_status(interrupt),
+                pins.up.interrupt_status(interrupt),
+                pins.down.interrupt_status(interrupt),
+            ];
+            pins.set.clear_interrupt(interrupt);
+            pins.up.clear_interrupt(interrupt);
+            pins.down.clear_interrupt(interrupt);
+
+            for (last_edge, edge) in last_edges.iter_mut().zip(edges) {
+                if edge {
+                    *last_edge = Some(freertos::tick_count_from_isr());
+                }
+            }
+        }
+
+        LAST_EDGES.borrow(cs).set(last_edges);
+    }
+}
+
+/// Updates the debounced state of a button and generates its events.
+fn update_button(button: Button, index: usize, state: &mut ButtonState, now: Milliseconds) {
+    // The level is taken once it has been stable for the debounce time
+    let pressed = interrupt::free(|cs| {
+        let mut last_edges = LAST_EDGES.borrow(cs).get();
+        let last_edge = last_edges[index]?;
+        // The tick count wraps around
+        if now.0.wrapping_sub(last_edge.0) < DEBOUNCE_TIME.0 {
+            return None;
+        }
+
+        last_edges[index] = None;
+        LAST_EDGES.borrow(cs).set(last_edges);
+        BUTTON_PINS
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map(|pins| is_pressed(pins, button))
+    });
+
+    match (pressed, state.pressed_at) {
+        (Some(true), None) => {
+            state.pressed_at = Some(now);
+            state.repeated_at = None;
+        }
+        (Some(false), Some(_)) => {
+            if state.repeated_at.is_none() {
+                push_event(ButtonEvent::Press(button));
+            }
+            state.pressed_at = None;
+        }
+        (_, Some(pressed_at)) => match state.repeated_at {
+            None if now.0.wrapping_sub(pressed_at.0) >= LONG_PRESS_TIME.0 => {
+                push_event(ButtonEvent::LongPress(button));
+                state.repeated_at = Some(now);
+            }
+            Some(repeated_at) if now.0.wrapping_sub(repeated_at.0) >= REPEAT_PERIOD.0 => {
+                push_event(ButtonEvent::Repeat(button));
+                state.repeated_at = Some(now);
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+fn is_pressed(pins: &ButtonPins, button: Button) -> bool {
+    // Reading the level cannot fail
+    match button {
+        Button::Set => pins.set.is_low().unwrap(),
+        Button::Up => pins.up.is_low().unwrap(),
+        Button::Down => pins.down.is_low().unwrap(),
+    }
+}
@@ -0,0 +1,163 @@
+//! Driver for the buzzer of the "Pico Clock Green" kit.
+//!
+//! The buzzer is passive, so tones are generated by PWM (slice 7, channel A on GPIO14). Melodies
+//! are played by a task of their own, so the timing of the tones does not depend on the period
+//! of the display FSM. Other tasks control it by requests.
+
+use crate::freertos;
+use crate::supervisor;
+
+use pico_clock_core::melody::Melody;
+
+use embedded_hal::PwmPin;
+use pico::hal::gpio::{bank0, Pin, PinMode, ValidPinMode};
+use pico::hal::pwm::{FreeRunning, Pwm7, Slice};
+
+// Time
+use embedded_time::duration::Milliseconds;
+use embedded_time::rate::Hertz;
+
+// Interrupt handler concurrency
+use core::cell::Cell;
+use cortex_m::interrupt;
+use cortex_m::interrupt::Mutex;
+
+/// Divider of the system clock for the PWM counter.
+/// With a system clock of 125 MHz, the counter does not overflow for tones down to 30 Hz.
+const PWM_DIVIDER: u8 = 64;
+
+/// Period in which the buzzer task checks for requests and the end of the current tone.
+const POLL_PERIOD: Milliseconds = Milliseconds(10);
+
+#[derive(Clone, Copy)]
+enum BuzzerRequest {
+    Play { melody: Melody, repeat: bool },
+    Stop,
+}
+
+static BUZZER_REQUEST: Mutex<Cell<Option<BuzzerRequest>>> = Mutex::new(Cell::new(None));
+
+/// Starts playing the melody (replacing the one being played). If 'repeat' is set, it is played
+/// until 'stop' is called.
+pub fn play(melody: Melody, repeat: bool) {
+    interrupt::free(|cs| {
+        BUZZER_REQUEST
+            .borrow(cs)
+            .set(Some(BuzzerRequest::Play { melody, repeat }))
+    });
+}
+
+/// Stops the melody being played (if any).
+pub fn stop() {
+    interrupt::free(|cs| BUZZER_REQUEST.borrow(cs).set(Some(BuzzerRequest::Stop)));
+}
+
+fn take_request() -> Option<BuzzerRequest> {
+    interrupt::free(|cs| BUZZER_REQUEST.borrow(cs).take())
+}
+
+/// Melody being played.
+struct Playing {
+    melody: Melody,
+    repeat: bool,
+    /// Index of the current tone.
+    index: usize,
+    /// Tick count when the current tone has been started.
+    started_at: Milliseconds,
+}
+
+struct Pwm {
+    slice: Slice<Pwm7, FreeRunning>,
+    /// Frequency of the PWM counter in Hz.
+    counter_frequency: u32,
+}
+
+impl Pwm {
+    /// Outputs a square wave with the given frequency (silence if zero).
+    fn set_frequency(&mut self, frequency: u16) {
+        if frequency == 0 {
+            self.slice.channel_a.set_duty(0);
+            return;
+        }
+
+        let top = (self.counter_frequency / frequency as u32).clamp(2, 0x10000) - 1;
+        self.slice.set_top(top as u16);
+        self.slice.set_counter(0);
+        // 50% duty cycle
+        self.slice.channel_a.set_duty((top / 2) as u16);
+    }
+}
+
+/// Sets up the PWM and starts the buzzer task.
+pub fn start<M: PinMode + ValidPinMode<bank0::Gpio14>>(
+    mut slice: Slice<Pwm7, FreeRunning>,
+    pin: Pin<bank0::Gpio14, M>,
+    system_clock: Hertz,
+) {
+    slice.default_config();
+    slice.set_div_int(PWM_DIVIDER);
+    slice.enable();
+    // The pin is kept in the PWM function for the lifetime of the program
+    let pin = slice.channel_a.output_to(pin);
+
+    let mut pwm = Pwm {
+        slice,
+        counter_frequency: system_clock.0 / PWM_DIVIDER as u32,
+    };
+
+    let check_in = supervisor::register("BuzzerTask", Milliseconds(500));
+    freertos::create_task(
+        move || {
+            let _pin = pin;
+            let mut playing: Option<Playing> = None;
+
+            loop {
+                check_in.check_in();
+                let now = freertos::tick_count();
+
+                match take_request() {
+                    Some(BuzzerRequest::Play { melody, repeat }) => {
+                        pwm.set_frequency(melody.tones()[0].frequency);
+                        playing = Some(Playing {
+                            melody,
+                            repeat,
+                            index: 0,
+                            started_at: now,
+                        });
+                    }
+                    Some(BuzzerRequest::Stop) => {
+                        pwm.set_frequency(0);
+                        playing = None;
+                    }
+                    None => {}
+                }
+
+                if let Some(current) = &mut playing {
+                    let tone = current.melody.tones()[current.index];
+                    // The tick count wraps around
+                    if now.0.wrapping_sub(current.started_at.0) >= tone.duration as u32 {
+                        current.index += 1;
+                        if current.index >= current.melody.tones().len() && current.repeat {
+                            current.index = 0;
+                        }
+
+                        if let Some(next) = current.melody.tones().get(current.index) {
+                            pwm.set_frequency(next.frequency);
+                            current.started_at = now;
+                        } else {
+                            pwm.set_frequency(0);
+                            playing = None;
+                        }
+                    }
+                }
+
+                freertos::delay(POLL_PERIOD);
+            }
+        },
+        &freertos::TaskParameters {
+            name: "BuzzerTask",
+            stack_depth: 512, // Is actually 2048 bytes because portSTACK_TYPE is uint32_t
+            priority: crate::BUZZER_TASK_PRIORITY,
+        },
+    );
+}
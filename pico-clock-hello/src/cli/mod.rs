@@ -1,5 +1,3 @@
-mod line_input;
-
 use embedded_hal::serial::Read as HalRead;
 use embedded_hal::serial::Write as HalWrite;
 
@@ -11,18 +9,54 @@ use core::fmt::Write;
 use core::result::Result;
 use nb::block;
 
-use line_input::{LineInput, LineInputResult};
+use crate::buzzer;
+use crate::flash::XipFlash;
+use crate::freertos::Mutex;
+use crate::internal_rtc;
+use crate::rtc::{self, HourMode, Rtc, RtcError, TimeSource};
+use crate::sqw;
+use crate::supervisor::CheckIn;
+
+use pico_clock_core::alarm::{self, Alarm, AlarmLabel, AlarmRequest, Weekdays};
+use pico_clock_core::brightness::{Brightness, MAX_BRIGHTNESS};
+use pico_clock_core::chime::QuietHours;
+use pico_clock_core::cli::datetime::{self, DateTimeInput, DateTimeParseError};
+use pico_clock_core::cli::line_input::{LineInput, LineInputResult};
+use pico_clock_core::display_fsm::Transition;
+use pico_clock_core::drift::{MAX_DEVIATION, MAX_MEASUREMENTS};
+use pico_clock_core::melody::Melody;
+use pico_clock_core::night::{NightMode, NightSchedule};
+use pico_clock_core::playlist::{self, PlaylistEntry, Screen};
+use pico_clock_core::settings::{self, DateFormat, ScrollText, Settings, MAX_TEXTS};
+use pico_clock_core::temperature::TemperatureUnit;
+use pico_clock_core::text::{TextBitmap, TextRenderError};
+use pico_clock_core::timer::{self, TimerRequest};
+use pico_clock_core::timezone::{OffsetDisplay, TimeZone, MAX_TZ_LEN};
+
+use ds323x::{NaiveDateTime, Timelike};
 
 pub trait Timer {
     fn sleep_ms(&self, delay_ms: u32);
 }
 
-pub fn run<T: HalRead<u8> + HalWrite<u8> + Write>(uart: &mut T) -> ! {
+/// Resources shared with other tasks that are accessed by commands.
+pub struct Context<'a> {
+    pub rtc: &'a Mutex<Rtc>,
+    pub settings: &'a Mutex<Settings>,
+}
+
+pub fn run<T: HalRead<u8> + HalWrite<u8> + Write>(
+    uart: &mut T,
+    context: &Context,
+    check_in: &CheckIn,
+) -> ! {
     let mut input = LineInput::<100>::new();
 
     print_prompt(uart);
 
     loop {
+        check_in.check_in();
+
         match uart.read() {
             Result::Ok(c) => {
                 match input.feed(c) {
@@ -34,7 +68,7 @@ pub fn run<T: HalRead<u8> + HalWrite<u8> + Write>(uart: &mut T) -> ! {
                     }
                     LineInputResult::Complete(line) => {
                         print_newline(uart);
-                        process_line(uart, line);
+                        process_line(uart, context, line);
                         print_prompt(uart);
                     }
                 }
@@ -58,19 +92,185 @@ fn print_newline<T: Write>(uart: &mut T) {
     write!(uart, "\r\n").unwrap();
 }
 
-fn process_line<T: Write>(uart: &mut T, line: &str) {
+fn process_line<T: Write>(uart: &mut T, context: &Context, line: &str) {
     let line = line.trim();
     if line.len() == 0 {
         return;
     }
 
     let mut iter = line.split_whitespace();
-    match iter.next().unwrap() {
+    let command = iter.next().unwrap();
+    match command {
         "settext" => {
-            write!(uart, "To be implemented\r\n").unwrap();
+            // Whitespace inside of the text is kept as it is
+            let arg = line[command.len()..].trim();
+            match parse_text_number(arg) {
+                Some((index, text)) => set_text(uart, context, index, text),
+                None => set_text(uart, context, 0, arg),
+            }
         }
         "settime" => {
-            write!(uart, "To be implemented\r\n").unwrap();
+            if let (Some(arg), None) = (iter.next(), iter.next()) {
+                set_time(uart, context, arg);
+            } else {
+                write!(uart, "Exactly one argument expected\r\n").unwrap();
+            }
+        }
+        "sync" => {
+            if let (Some(arg), None) = (iter.next(), iter.next()) {
+                sync_time(uart, context, arg);
+            } else {
+                write!(uart, "Exactly one argument expected\r\n").unwrap();
+            }
+        }
+        "status" => print_status(uart, context),
+        "rtc" => match (iter.next(), iter.next(), iter.next()) {
+            (None, ..) => print_rtc(uart, context),
+            (Some("calibrate"), arg, None) => calibrate_rtc(uart, context, arg),
+            _ => write!(uart, "Unknown rtc command\r\n").unwrap(),
+        },
+        "alarm" => match iter.next() {
+            Some("add") => {
+                if let (Some(time), Some(days)) = (iter.next(), iter.next()) {
+                    // The label is the rest of the line (whitespace inside of it is kept)
+                    let label = &line[offset_after(line, days)..];
+                    add_alarm(uart, context, time, days, label.trim());
+                } else {
+                    write!(uart, "Time and days expected\r\n").unwrap();
+                }
+            }
+            Some("list") => list_alarms(uart, context),
+            Some("del") => {
+                if let (Some(arg), None) = (iter.next(), iter.next()) {
+                    delete_alarm(uart, context, arg);
+                } else {
+                    write!(uart, "Exactly one argument expected\r\n").unwrap();
+                }
+            }
+            Some("snooze") => alarm::request(AlarmRequest::Snooze),
+            Some("dismiss") => alarm::request(AlarmRequest::Dismiss),
+            _ => write!(uart, "Unknown alarm command\r\n").unwrap(),
+        },
+        "timer" => match (iter.next(), iter.next(), iter.next()) {
+            (Some("countdown"), Some(arg), None) => start_countdown(uart, arg),
+            (Some("stopwatch"), None, _) => timer::request(TimerRequest::CountUp),
+            (Some("start"), None, _) => timer::request(TimerRequest::Start),
+            (Some("stop"), None, _) => timer::request(TimerRequest::Stop),
+            (Some("reset"), None, _) => timer::request(TimerRequest::Reset),
+            (Some("off"), None, _) => timer::request(TimerRequest::Off),
+            _ => write!(uart, "Unknown timer command\r\n").unwrap(),
+        },
+        "units" => match (iter.next(), iter.next()) {
+            (Some("c"), None) => set_temperature_unit(context, TemperatureUnit::Celsius),
+            (Some("f"), None) => set_temperature_unit(context, TemperatureUnit::Fahrenheit),
+            _ => write!(uart, "Expected 'c' or 'f'\r\n").unwrap(),
+        },
+        "hourmode" => match (iter.next(), iter.next()) {
+            (Some("12"), None) => set_hour_mode(context, HourMode::H12),
+            (Some("24"), None) => set_hour_mode(context, HourMode::H24),
+            _ => write!(uart, "Expected '12' or '24'\r\n").unwrap(),
+        },
+        "dateformat" => match (iter.next(), iter.next()) {
+            (Some("dd.mm"), None) => set_date_format(context, DateFormat::DayMonth),
+            (Some("mm/dd"), None) => set_date_format(context, DateFormat::MonthDay),
+            _ => write!(uart, "Expected 'dd.mm' or 'mm/dd'\r\n").unwrap(),
+        },
+        "playlist" => match (iter.next(), iter.next(), iter.next(), iter.next()) {
+            (None, ..) | (Some("list"), None, ..) => list_playlist(uart, context),
+            (Some("add"), Some(screen), duration, None) => {
+                add_playlist_entry(uart, context, screen, duration)
+            }
+            (Some("del"), Some(arg), None, _) => delete_playlist_entry(uart, context, arg),
+            (Some("reset"), None, ..) => {
+                context.settings.lock().playlist = playlist::default_playlist();
+                save_settings(context);
+            }
+            _ => write!(uart, "Unknown playlist command\r\n").unwrap(),
+        },
+        "colon" => match (iter.next(), iter.next()) {
+            (Some("blink"), None) => modify_settings(context, |x| x.colon_blink = true),
+            (Some("steady"), None) => modify_settings(context, |x| x.colon_blink = false),
+            _ => write!(uart, "Expected 'blink' or 'steady'\r\n").unwrap(),
+        },
+        "secondsbar" => match (iter.next(), iter.next()) {
+            (Some("on"), None) => modify_settings(context, |x| x.seconds_bar = true),
+            (Some("off"), None) => modify_settings(context, |x| x.seconds_bar = false),
+            _ => write!(uart, "Expected 'on' or 'off'\r\n").unwrap(),
+        },
+        "transition" => {
+            let transition = match (iter.next(), iter.next()) {
+                (Some("none"), None) => Some(Transition::None),
+                (Some("slide"), None) => Some(Transition::Slide),
+                (Some("fade"), None) => Some(Transition::FadeByRow),
+                _ => None,
+            };
+            if let Some(transition) = transition {
+                modify_settings(context, |x| x.transition = transition);
+            } else {
+                write!(uart, "Expected 'none', 'slide' or 'fade'\r\n").unwrap();
+            }
+        }
+        "chime" => match (iter.next(), iter.next(), iter.next()) {
+            (None, ..) => print_chime(uart, context),
+            (Some("on"), None, _) => modify_settings(context, |x| x.hourly_chime = true),
+            (Some("off"), None, _) => modify_settings(context, |x| x.hourly_chime = false),
+            (Some("quiet"), Some("off"), None) => {
+                modify_settings(context, |x| x.quiet_hours = None)
+            }
+            (Some("quiet"), Some(arg), None) => match QuietHours::parse(arg) {
+                Some(quiet_hours) => {
+                    modify_settings(context, |x| x.quiet_hours = Some(quiet_hours))
+                }
+                None => write!(uart, "Invalid hours (expected e.g. '22-7')\r\n").unwrap(),
+            },
+            _ => write!(uart, "Unknown chime command\r\n").unwrap(),
+        },
+        "play" => {
+            // The melody is the rest of the line
+            let arg = line[command.len()..].trim();
+            play_melody(uart, arg);
+        }
+        "brightness" => match (iter.next(), iter.next()) {
+            (None, _) => {
+                let brightness = context.settings.lock().brightness;
+                write!(uart, "Brightness {}\r\n", brightness).unwrap();
+            }
+            (Some("auto"), None) => modify_settings(context, |x| x.brightness = Brightness::Auto),
+            (Some(arg), None) => match arg.parse() {
+                Ok(percent) if percent <= MAX_BRIGHTNESS => {
+                    modify_settings(context, |x| x.brightness = Brightness::Fixed(percent))
+                }
+                _ => write!(uart, "Expected 0 to 100 or 'auto'\r\n").unwrap(),
+            },
+            _ => write!(uart, "Exactly one argument expected\r\n").unwrap(),
+        },
+        "night" => match (iter.next(), iter.next(), iter.next()) {
+            (None, ..) => {
+                let (night_mode, night_schedule) = {
+                    let settings = context.settings.lock();
+                    (settings.night_mode, settings.night_schedule)
+                };
+                write!(uart, "Night mode {} ({})\r\n", night_mode, night_schedule).unwrap();
+            }
+            (Some("off"), None, _) => modify_settings(context, |x| x.night_mode = NightMode::Off),
+            (Some("dim"), None, _) => modify_settings(context, |x| x.night_mode = NightMode::Dim),
+            (Some("blank"), None, _) => {
+                modify_settings(context, |x| x.night_mode = NightMode::Blank)
+            }
+            (Some("time"), None, _) => {
+                modify_settings(context, |x| x.night_mode = NightMode::TimeOnly)
+            }
+            (Some("schedule"), Some(arg), None) => set_night_schedule(uart, context, arg),
+            _ => write!(uart, "Unknown night command\r\n").unwrap(),
+        },
+        "tz" => {
+            // The TZ string is the rest of the line
+            let arg = line[command.len()..].trim();
+            if arg.is_empty() {
+                print_time_zone(uart, context);
+            } else {
+                set_time_zone(uart, context, arg);
+            }
         }
         "help" => print_help(uart),
         _ => {
@@ -81,17 +281,762 @@ fn process_line<T: Write>(uart: &mut T, line: &str) {
     }
 }
 
+/// Returns the offset following the given token (which must be a slice of the line).
+fn offset_after(line: &str, token: &str) -> usize {
+    token.as_ptr() as usize - line.as_ptr() as usize + token.len()
+}
+
+fn set_time<T: Write>(uart: &mut T, context: &Context, arg: &str) {
+    let time_zone = context.settings.lock().time_zone;
+    let mut rtc = context.rtc.lock();
+
+    // The input is local time (a time without date keeps the local date)
+    let result = match datetime::parse(arg) {
+        Ok(DateTimeInput::Time(time)) => rtc::get_local_datetime(&mut *rtc, &time_zone)
+            .and_then(|x| rtc::set_local_datetime(&mut *rtc, &time_zone, &x.date().and_time(time))),
+        Ok(DateTimeInput::DateTime(datetime)) => {
+            rtc::set_local_datetime(&mut *rtc, &time_zone, &datetime)
+        }
+        Err(DateTimeParseError::InvalidFormat) => {
+            write!(
+                uart,
+                "Invalid format (expected 'HH:MM', 'HH:MM:SS' or 'YYYY-MM-DDTHH:MM:SS')\r\n"
+            )
+            .unwrap();
+            return;
+        }
+        Err(DateTimeParseError::OutOfRange(field)) => {
+            write!(uart, "Value out of range: {}\r\n", field).unwrap();
+            return;
+        }
+    };
+
+    drop(rtc);
+
+    // The deviation accumulated until now is unknown
+    modify_settings(context, |x| x.drift_log.discard_interval());
+
+    // The next due alarm depends on the time
+    if result.is_err() || program_alarm(context).is_err() {
+        write!(uart, "Failed to access the RTC\r\n").unwrap();
+    }
+}
+
+/// Sets the RTC to a Unix timestamp in milliseconds (sent by the host tool, which compensates
+/// the transmission delay).
+fn sync_time<T: Write>(uart: &mut T, context: &Context, arg: &str) {
+    let millis = match datetime::parse_unix_millis(arg) {
+        Ok(x) => x,
+        Err(_) => {
+            write!(
+                uart,
+                "Invalid timestamp (expected milliseconds since 1970)\r\n"
+            )
+            .unwrap();
+            return;
+        }
+    };
+
+    // The RTC only keeps full seconds, so it is set at the start of the next one (writing the
+    // seconds restarts the sub-second countdown of the RTC)
+    let seconds = millis.div_ceil(1000);
+    let datetime = match datetime::from_unix_seconds(seconds) {
+        Ok(x) => x,
+        Err(_) => {
+            write!(uart, "Value out of range: year\r\n").unwrap();
+            return;
+        }
+    };
+    let wait = (seconds * 1000 - millis) as u32;
+    if wait > 0 {
+        crate::freertos::delay(Milliseconds(wait));
+    }
+
+    let result = set_synchronized_time(&mut context.rtc.lock(), &datetime);
+
+    // The next due alarm depends on the time
+    let (previous, millis_since_second, aging_offset) = match result {
+        Ok(x) if program_alarm(context).is_ok() => x,
+        _ => {
+            write!(uart, "Failed to access the RTC\r\n").unwrap();
+            return;
+        }
+    };
+
+    let deviation = datetime::to_unix_seconds(&previous) - datetime::to_unix_seconds(&datetime);
+    let deviation_millis = millis_since_second
+        .map(|x| deviation * 1000 + x as i64)
+        .filter(|x| x.abs() <= MAX_DEVIATION as i64);
+    match deviation_millis {
+        Some(deviation) => {
+            let deviation = deviation as i32;
+            modify_settings(context, |x| {
+                x.drift_log
+                    .record_sync(seconds as u32, deviation, aging_offset)
+            });
+            write!(uart, "Synchronized (deviation {:+} ms)\r\n", deviation).unwrap();
+        }
+        None => {
+            // Too inaccurate for measuring the drift, or the time was wrong
+            modify_settings(context, |x| x.drift_log.discard_interval());
+            write!(uart, "Synchronized (deviation {:+} s)\r\n", deviation).unwrap();
+        }
+    }
+}
+
+/// Sets the RTC and returns the time it had before (with the milliseconds since that second,
+/// if known) and the aging offset (0 if not supported by the RTC).
+fn set_synchronized_time(
+    rtc: &mut Rtc,
+    datetime: &NaiveDateTime,
+) -> Result<(NaiveDateTime, Option<u32>, i8), RtcError> {
+    let (previous, millis_since_second) = loop {
+        let before = sqw::millis_since_second();
+        let previous = rtc.get_datetime()?;
+        // Repeated if the seconds have been incremented while reading
+        if sqw::millis_since_second() >= before {
+            break (previous, before);
+        }
+    };
+    // The deviation of the internal RTC is not measured (when the external one has failed)
+    let millis_since_second = millis_since_second.filter(|_| !internal_rtc::is_fallback_active());
+
+    rtc.set_datetime(datetime)?;
+    let aging_offset = match rtc.get_aging_offset() {
+        Err(RtcError::Unsupported) => 0,
+        result => result?,
+    };
+    Ok((previous, millis_since_second, aging_offset))
+}
+
+/// Prints the state of the clock as 'key=value' lines (read by the host tool).
+fn print_status<T: Write>(uart: &mut T, context: &Context) {
+    let (time_zone, alarms, brightness, night_mode, drift_log) = {
+        let settings = context.settings.lock();
+        (
+            settings.time_zone,
+            settings.alarms,
+            settings.brightness,
+            settings.night_mode,
+            settings.drift_log,
+        )
+    };
+    let (now, aging_offset) = {
+        let mut rtc = context.rtc.lock();
+        (rtc.get_datetime(), rtc.get_aging_offset())
+    };
+
+    match now {
+        Ok(now) => {
+            let local = time_zone.utc_to_local(&now);
+            write!(uart, "utc={}T{}\r\n", now.date(), now.time()).unwrap();
+            write!(uart, "local={}T{}\r\n", local.date(), local.time()).unwrap();
+            write!(uart, "offset={}\r\n", OffsetDisplay(time_zone.offset(&now))).unwrap();
+        }
+        Err(_) => write!(uart, "rtc=error\r\n").unwrap(),
+    }
+    let source = if internal_rtc::is_fallback_active() {
+        "internal"
+    } else {
+        rtc::NAME
+    };
+    write!(uart, "source={}\r\n", source).unwrap();
+    write!(uart, "tz={}\r\n", time_zone.as_str()).unwrap();
+    write!(uart, "alarms={}\r\n", alarms.iter().flatten().count()).unwrap();
+    write!(uart, "brightness={}\r\n", brightness).unwrap();
+    write!(uart, "night={}\r\n", night_mode).unwrap();
+    if let Ok(aging_offset) = aging_offset {
+        write!(uart, "aging={}\r\n", aging_offset).unwrap();
+        match drift_log.drift(aging_offset) {
+            Some(drift) => write!(uart, "drift_ppm={}\r\n", drift).unwrap(),
+            None => write!(uart, "drift_ppm=unknown\r\n").unwrap(),
+        }
+    }
+}
+
+fn print_rtc<T: Write>(uart: &mut T, context: &Context) {
+    let drift_log = context.settings.lock().drift_log;
+    let aging_offset = match context.rtc.lock().get_aging_offset() {
+        Ok(x) => x,
+        Err(RtcError::Unsupported) => {
+            write!(uart, "Not supported by the RTC\r\n").unwrap();
+            return;
+        }
+        Err(_) => {
+            write!(uart, "Failed to access the RTC\r\n").unwrap();
+            return;
+        }
+    };
+
+    write!(uart, "Aging offset {}\r\n", aging_offset).unwrap();
+    write!(
+        uart,
+        "{} of {} measurements taken with it\r\n",
+        drift_log.count(aging_offset),
+        MAX_MEASUREMENTS
+    )
+    .unwrap();
+    match (
+        drift_log.drift(aging_offset),
+        drift_log.recommended_aging_offset(aging_offset),
+    ) {
+        (Some(drift), Some(recommended)) => {
+            write!(uart, "Drift {} ppm\r\n", drift).unwrap();
+            write!(uart, "Recommended aging offset {}\r\n", recommended).unwrap();
+        }
+        _ => write!(
+            uart,
+            "Drift unknown (synchronize at least twice, 6 hours apart)\r\n"
+        )
+        .unwrap(),
+    }
+}
+
+/// Sets the aging offset of the RTC, or the recommended one if 'arg' is 'None'.
+fn calibrate_rtc<T: Write>(uart: &mut T, context: &Context, arg: Option<&str>) {
+    let drift_log = context.settings.lock().drift_log;
+    let mut rtc = context.rtc.lock();
+
+    let aging_offset = match arg {
+        Some(arg) => match arg.parse::<i8>() {
+            Ok(x) => x,
+            Err(_) => {
+                write!(uart, "Expected an offset from -128 to 127\r\n").unwrap();
+                return;
+            }
+        },
+        None => {
+            let recommended = rtc
+                .get_aging_offset()
+                .ok()
+                .and_then(|x| drift_log.recommended_aging_offset(x));
+            match recommended {
+                Some(x) => x,
+                None => {
+                    write!(uart, "No drift measured yet\r\n").unwrap();
+                    return;
+                }
+            }
+        }
+    };
+
+    let result = rtc.set_aging_offset(aging_offset);
+    drop(rtc);
+
+    // The drift changes with the offset
+    modify_settings(context, |x| x.drift_log.discard_interval());
+
+    match result {
+        Ok(()) => write!(uart, "Aging offset set to {}\r\n", aging_offset).unwrap(),
+        Err(RtcError::Unsupported) => write!(uart, "Not supported by the RTC\r\n").unwrap(),
+        Err(_) => write!(uart, "Failed to access the RTC\r\n").unwrap(),
+    }
+}
+
+/// Splits a text number of the form '#N' from the start of the text (if present).
+fn parse_text_number(arg: &str) -> Option<(usize, &str)> {
+    let rest = arg.strip_prefix('#')?;
+    let (number, text) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    match number.parse::<usize>() {
+        Ok(x) if (1..=MAX_TEXTS).contains(&x) => Some((x - 1, text.trim())),
+        _ => None,
+    }
+}
+
+fn set_text<T: Write>(uart: &mut T, context: &Context, index: usize, text: &str) {
+    // Render once to check whether the text can be displayed at all
+    if let Err(x) = TextBitmap::from_str(text) {
+        let reason = match x.error {
+            TextRenderError::TextTooLong => "Text too long",
+            TextRenderError::UnsupportedCharacter => "Unsupported character",
+        };
+        write!(uart, "{} at character {}\r\n", reason, x.position + 1).unwrap();
+        return;
+    }
+
+    if let Some(text) = ScrollText::new(text) {
+        // Takes effect when the text is scrolled the next time
+        context.settings.lock().texts[index] = text;
+        save_settings(context);
+    } else {
+        write!(uart, "Text too long\r\n").unwrap();
+    }
+}
+
+fn add_alarm<T: Write>(uart: &mut T, context: &Context, time: &str, days: &str, label: &str) {
+    let time = match datetime::parse(time) {
+        Ok(DateTimeInput::Time(time)) if time.second() == 0 => time,
+        Err(DateTimeParseError::OutOfRange(field)) => {
+            write!(uart, "Value out of range: {}\r\n", field).unwrap();
+            return;
+        }
+        _ => {
+            write!(uart, "Invalid time (expected 'HH:MM')\r\n").unwrap();
+            return;
+        }
+    };
+
+    let weekdays = if let Some(x) = Weekdays::parse(days) {
+        x
+    } else {
+        write!(
+            uart,
+            "Invalid days (expected 'daily', 'weekdays', 'weekend' or e.g. 'mon,wed,fri')\r\n"
+        )
+        .unwrap();
+        return;
+    };
+
+    let label = if label.is_empty() { "Alarm" } else { label };
+    if TextBitmap::from_str(label).is_err() {
+        write!(uart, "Label cannot be displayed\r\n").unwrap();
+        return;
+    }
+    let label = if let Some(x) = AlarmLabel::new(label) {
+        x
+    } else {
+        write!(uart, "Label too long\r\n").unwrap();
+        return;
+    };
+
+    {
+        let mut settings = context.settings.lock();
+        if let Some(slot) = settings.alarms.iter_mut().find(|x| x.is_none()) {
+            *slot = Some(Alarm {
+                time,
+                weekdays,
+                label,
+            });
+        } else {
+            write!(uart, "Too many alarms (delete one first)\r\n").unwrap();
+            return;
+        }
+    }
+
+    save_settings(context);
+    if program_alarm(context).is_err() {
+        write!(uart, "Failed to access the RTC\r\n").unwrap();
+    }
+}
+
+fn list_alarms<T: Write>(uart: &mut T, context: &Context) {
+    let alarms = context.settings.lock().alarms;
+
+    if alarms.iter().all(|x| x.is_none()) {
+        write!(uart, "No alarms\r\n").unwrap();
+    }
+    for (index, alarm) in alarms.iter().enumerate() {
+        if let Some(alarm) = alarm {
+            write!(
+                uart,
+                "  {}: {:02}:{:02} {} {}\r\n",
+                index + 1,
+                alarm.time.hour(),
+                alarm.time.minute(),
+                alarm.weekdays,
+                alarm.label.as_str()
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn delete_alarm<T: Write>(uart: &mut T, context: &Context, arg: &str) {
+    let index = match arg.parse::<usize>() {
+        Ok(x) if x >= 1 => x - 1,
+        _ => {
+            write!(uart, "Invalid alarm number\r\n").unwrap();
+            return;
+        }
+    };
+
+    {
+        let mut settings = context.settings.lock();
+        if let Some(slot @ Some(_)) = settings.alarms.get_mut(index) {
+            *slot = None;
+            // Keep the numbering consistent with the order in which the alarms are stored
+            settings.alarms[index..].rotate_left(1);
+        } else {
+            write!(uart, "No such alarm\r\n").unwrap();
+            return;
+        }
+    }
+
+    save_settings(context);
+    if program_alarm(context).is_err() {
+        write!(uart, "Failed to access the RTC\r\n").unwrap();
+    }
+}
+
+fn list_playlist<T: Write>(uart: &mut T, context: &Context) {
+    let playlist = context.settings.lock().playlist;
+
+    for (index, entry) in playlist.iter().flatten().enumerate() {
+        write!(uart, "  {}: {}\r\n", index + 1, entry).unwrap();
+    }
+}
+
+fn add_playlist_entry<T: Write>(
+    uart: &mut T,
+    context: &Context,
+    screen: &str,
+    duration: Option<&str>,
+) {
+    let screen = if let Some(x) = Screen::parse(screen) {
+        x
+    } else {
+        write!(
+            uart,
+            "Invalid screen (expected 'time', 'date', 'temp', 'text1' to 'text{}' or 'countdown')\r\n",
+            MAX_TEXTS
+        )
+        .unwrap();
+        return;
+    };
+
+    let duration = match duration.map(|x| x.parse::<u8>()) {
+        None => None,
+        Some(Ok(x)) if x > 0 => Some(x),
+        Some(_) => {
+            write!(uart, "Invalid duration (expected 1 to 255)\r\n").unwrap();
+            return;
+        }
+    };
+
+    {
+        let mut settings = context.settings.lock();
+        if let Some(slot) = settings.playlist.iter_mut().find(|x| x.is_none()) {
+            *slot = Some(PlaylistEntry::new(screen, duration));
+        } else {
+            write!(uart, "Playlist full (delete an entry first)\r\n").unwrap();
+            return;
+        }
+    }
+
+    save_settings(context);
+}
+
+fn delete_playlist_entry<T: Write>(uart: &mut T, context: &Context, arg: &str) {
+    let index = match arg.parse::<usize>() {
+        Ok(x) if x >= 1 => x - 1,
+        _ => {
+            write!(uart, "Invalid entry number\r\n").unwrap();
+            return;
+        }
+    };
+
+    {
+        let mut settings = context.settings.lock();
+        if settings.playlist.iter().flatten().count() <= 1 {
+            write!(uart, "The last entry cannot be deleted\r\n").unwrap();
+            return;
+        }
+        if let Some(slot @ Some(_)) = settings.playlist.get_mut(index) {
+            *slot = None;
+            settings.playlist[index..].rotate_left(1);
+        } else {
+            write!(uart, "No such entry\r\n").unwrap();
+            return;
+        }
+    }
+
+    save_settings(context);
+}
+
+fn set_temperature_unit(context: &Context, unit: TemperatureUnit) {
+    context.settings.lock().temperature_unit = unit;
+    save_settings(context);
+}
+
+fn set_hour_mode(context: &Context, hour_mode: HourMode) {
+    context.settings.lock().hour_mode = hour_mode;
+    save_settings(context);
+}
+
+fn print_time_zone<T: Write>(uart: &mut T, context: &Context) {
+    let time_zone = context.settings.lock().time_zone;
+    let now = context.rtc.lock().get_datetime();
+
+    write!(uart, "Time zone '{}'", time_zone.as_str()).unwrap();
+    if let Ok(now) = now {
+        write!(uart, ", UTC{}", OffsetDisplay(time_zone.offset(&now))).unwrap();
+    }
+    write!(uart, "\r\n").unwrap();
+}
+
+fn set_time_zone<T: Write>(uart: &mut T, context: &Context, arg: &str) {
+    let time_zone = match TimeZone::parse(arg) {
+        Some(x) => x,
+        None => {
+            write!(
+                uart,
+                "Invalid or unsupported TZ string (expected e.g. 'CET-1CEST,M3.5.0,M10.5.0/3')\r\n"
+            )
+            .unwrap();
+            return;
+        }
+    };
+
+    modify_settings(context, |x| x.time_zone = time_zone);
+
+    // The RTC keeps UTC, but the alarms are due at local time
+    if program_alarm(context).is_err() {
+        write!(uart, "Failed to access the RTC\r\n").unwrap();
+    }
+}
+
+fn set_date_format(context: &Context, date_format: DateFormat) {
+    context.settings.lock().date_format = date_format;
+    save_settings(context);
+}
+
+fn start_countdown<T: Write>(uart: &mut T, arg: &str) {
+    match datetime::parse_duration(arg) {
+        Ok(0) => write!(uart, "Duration must not be zero\r\n").unwrap(),
+        Ok(seconds) => timer::request(TimerRequest::CountDown(Milliseconds(seconds * 1000))),
+        Err(DateTimeParseError::InvalidFormat) => {
+            write!(uart, "Invalid format (expected 'MM:SS' or 'HH:MM:SS')\r\n").unwrap()
+        }
+        Err(DateTimeParseError::OutOfRange(field)) => {
+            write!(uart, "Value out of range: {}\r\n", field).unwrap()
+        }
+    }
+}
+
+fn set_night_schedule<T: Write>(uart: &mut T, context: &Context, arg: &str) {
+    let parse_time = |input| match datetime::parse(input) {
+        Ok(DateTimeInput::Time(time)) if time.second() == 0 => Some(time),
+        _ => None,
+    };
+
+    let schedule = arg.split_once('-').and_then(|(start, end)| {
+        Some(NightSchedule {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    });
+
+    match schedule {
+        Some(schedule) => modify_settings(context, |x| x.night_schedule = schedule),
+        None => write!(uart, "Invalid schedule (expected e.g. '23:00-06:30')\r\n").unwrap(),
+    }
+}
+
+fn print_chime<T: Write>(uart: &mut T, context: &Context) {
+    let (hourly_chime, quiet_hours) = {
+        let settings = context.settings.lock();
+        (settings.hourly_chime, settings.quiet_hours)
+    };
+
+    let state = if hourly_chime { "on" } else { "off" };
+    match quiet_hours {
+        Some(quiet_hours) => write!(
+            uart,
+            "Hourly chime {}, quiet hours {}\r\n",
+            state, quiet_hours
+        )
+        .unwrap(),
+        None => write!(uart, "Hourly chime {}, no quiet hours\r\n", state).unwrap(),
+    }
+}
+
+fn play_melody<T: Write>(uart: &mut T, arg: &str) {
+    if arg == "stop" {
+        buzzer::stop();
+        return;
+    }
+
+    let melody = match Melody::builtin(arg) {
+        Some(melody) => Ok(melody),
+        None => Melody::parse(arg),
+    };
+    match melody {
+        Ok(melody) => buzzer::play(melody, false),
+        Err(err) => write!(uart, "{}\r\n", err).unwrap(),
+    }
+}
+
+/// Programs the RTC to the next due alarm.
+fn program_alarm(context: &Context) -> Result<(), RtcError> {
+    // Copy to avoid holding both mutexes at the same time
+    let (alarms, time_zone) = {
+        let settings = context.settings.lock();
+        (settings.alarms, settings.time_zone)
+    };
+
+    alarm::program_next(&mut *context.rtc.lock(), &alarms, &time_zone)
+}
+
+/// Changes the settings and stores them persistently.
+fn modify_settings<F: FnOnce(&mut Settings)>(context: &Context, func: F) {
+    func(&mut context.settings.lock());
+    save_settings(context);
+}
+
+/// Stores the current settings persistently.
+fn save_settings(context: &Context) {
+    // Copy to release the mutex before the (lengthy) flash operation
+    let settings = *context.settings.lock();
+    settings::store::save(&mut XipFlash, &settings);
+}
+
 fn print_help<T: Write>(uart: &mut T) {
     write!(uart, "Supported commands:\r\n").unwrap();
     write!(uart, "  help            Print this help\r\n").unwrap();
     write!(
         uart,
-        "  settime <time>  Set the time (format: 'HH:MM:SS')\r\n"
+        "  settime <time>  Set the local time (format: 'HH:MM', 'HH:MM:SS' or 'YYYY-MM-DDTHH:MM:SS')\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  sync <ms>       Set the time to a Unix timestamp in milliseconds (used by the host tool)\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  status          Print the time and the main settings as 'key=value' lines\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  rtc             Show the aging offset of the RTC and the drift measured by 'sync'\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  rtc calibrate [offset]\r\n                  Set the recommended (or the given) aging offset (-128 to 127)\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  settext [#n] <text>\r\n                  Set the scrolling text n (default 1, without leading and trailing whitespace)\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  alarm add <time> <days> [label]\r\n                  Add an alarm (time: 'HH:MM', days: 'daily', 'weekdays', 'weekend' or e.g. 'mon,wed,fri')\r\n"
+    )
+    .unwrap();
+    write!(uart, "  alarm list      List the alarms\r\n").unwrap();
+    write!(
+        uart,
+        "  alarm del <n>   Delete the alarm with the given number\r\n"
+    )
+    .unwrap();
+    write!(uart, "  alarm snooze    Snooze the ringing alarm\r\n").unwrap();
+    write!(uart, "  alarm dismiss   Dismiss the ringing alarm\r\n").unwrap();
+    write!(
+        uart,
+        "  timer countdown <duration>\r\n                  Start a countdown (format: 'MM:SS' or 'HH:MM:SS')\r\n"
+    )
+    .unwrap();
+    write!(uart, "  timer stopwatch Start the stopwatch\r\n").unwrap();
+    write!(
+        uart,
+        "  timer start|stop|reset\r\n                  Control the countdown or stopwatch\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  timer off       Switch the countdown or stopwatch off and show the time\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  units c|f       Show the temperature in degrees Celsius or Fahrenheit\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  hourmode 12|24  Show the time in 12-hour or 24-hour format\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  dateformat dd.mm|mm/dd\r\n                  Set the order of day and month of the date\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  playlist [list] List the screens shown in rotation\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  playlist add <screen> [duration]\r\n                  Append a screen (time, date, temp, text1-text{}, countdown) shown for\r\n                  the duration in seconds (or scrolled as many times for a text)\r\n",
+        MAX_TEXTS
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  playlist del <n>\r\n                  Delete the entry with the given number\r\n"
+    )
+    .unwrap();
+    write!(uart, "  playlist reset  Restore the default playlist\r\n").unwrap();
+    write!(uart, "  colon blink|steady\r\n                  Blink the colon of the time with the seconds or show it steadily\r\n").unwrap();
+    write!(
+        uart,
+        "  secondsbar on|off\r\n                  Show the seconds as a bar below the time\r\n"
+    )
+    .unwrap();
+    write!(uart, "  transition none|slide|fade\r\n                  Set the effect when the minute changes\r\n").unwrap();
+    write!(
+        uart,
+        "  chime           Show the settings of the hourly chime\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  chime on|off    Switch the hourly chime on or off\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  chime quiet <from>-<to>|off\r\n                  Set the hours without chime (e.g. '22-7') or remove them\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  play <melody>   Play 'alarm', 'timer', 'chime' or tones (e.g. 'C5:150 E5:150 -:100 G5:300')\r\n"
+    )
+    .unwrap();
+    write!(uart, "  play stop       Stop the melody being played\r\n").unwrap();
+    write!(
+        uart,
+        "  brightness      Show the brightness of the display\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  brightness <0-100|auto>\r\n                  Set the brightness in percent or adjust it to the ambient light\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  night           Show the night mode and its schedule\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  night off|dim|blank|time\r\n                  Set the night mode (dimmed, switched off or only the time dimmed)\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  night schedule <from>-<to>\r\n                  Set the night (e.g. '23:00-06:30')\r\n"
+    )
+    .unwrap();
+    write!(
+        uart,
+        "  tz              Show the time zone and the current offset from UTC\r\n"
     )
     .unwrap();
     write!(
         uart,
-        "  settext <text>  Set the scrolling text (without leading and trailing whitespace)\r\n"
+        "  tz <TZ string>  Set the time zone as POSIX TZ string of up to {} characters\r\n                  (e.g. 'CET-1CEST,M3.5.0,M10.5.0/3', 'UTC0')\r\n",
+        MAX_TZ_LEN
     )
     .unwrap();
 }
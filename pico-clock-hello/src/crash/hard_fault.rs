@@ -0,0 +1,28 @@
+//! HardFault handler recording the stacked exception frame and the interrupted task.
+
+use super::{CrashReason, CrashRecord, Registers};
+use crate::freertos;
+
+use cortex_m_rt::{exception, ExceptionFrame};
+
+#[exception]
+unsafe fn HardFault(frame: &ExceptionFrame) -> ! {
+    let record = CrashRecord {
+        reason: CrashReason::HardFault,
+        task_name: freertos::current_task_name(),
+        registers: Some(Registers {
+            r0: frame.r0(),
+            r1: frame.r1(),
+            r2: frame.r2(),
+            r3: frame.r3(),
+            r12: frame.r12(),
+            lr: frame.lr(),
+            pc: frame.pc(),
+            xpsr: frame.xpsr(),
+        }),
+    };
+
+    super::store(&record);
+    super::write_to_uart(&record);
+    super::reset();
+}
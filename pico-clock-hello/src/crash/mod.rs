@@ -0,0 +1,265 @@
+//! Crash record that survives a watchdog reset, so the cause of an unexpected reset can be
+//! reported after reboot.
+//!
+//! The record is placed in the '.uninit' RAM section which is neither cleared nor initialized
+//! by the startup code. A magic number and a checksum tell whether it contains valid data.
+
+pub mod hard_fault;
+
+use crate::freertos::{TaskName, MAX_TASK_NAME_LEN};
+
+use pico::hal::pac;
+
+use core::fmt;
+use core::fmt::Write;
+use core::mem::MaybeUninit;
+
+const CRASH_RECORD_MAGIC: u32 = 0xC0DE_DEAD;
+
+#[link_section = ".uninit.CRASH_RECORD"]
+static mut CRASH_RECORD: MaybeUninit<RawCrashRecord> = MaybeUninit::uninit();
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CrashReason {
+    HardFault,
+    /// A supervised task did not check in within its deadline (see 'supervisor').
+    TaskStalled,
+}
+
+impl CrashReason {
+    fn to_raw(self) -> u32 {
+        match self {
+            CrashReason::HardFault => 1,
+            CrashReason::TaskStalled => 2,
+        }
+    }
+
+    fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            1 => Some(CrashReason::HardFault),
+            2 => Some(CrashReason::TaskStalled),
+            _ => None,
+        }
+    }
+}
+
+/// Registers stacked by the processor on exception entry.
+#[derive(Clone, Copy)]
+pub struct Registers {
+    pub r0: u32,
+    pub r1: u32,
+    pub r2: u32,
+    pub r3: u32,
+    pub r12: u32,
+    pub lr: u32,
+    pub pc: u32,
+    pub xpsr: u32,
+}
+
+impl Registers {
+    fn to_raw(self) -> [u32; 8] {
+        [
+            self.r0, self.r1, self.r2, self.r3, self.r12, self.lr, self.pc, self.xpsr,
+        ]
+    }
+
+    fn from_raw(raw: &[u32; 8]) -> Self {
+        Self {
+            r0: raw[0],
+            r1: raw[1],
+            r2: raw[2],
+            r3: raw[3],
+            r12: raw[4],
+            lr: raw[5],
+            pc: raw[6],
+            xpsr: raw[7],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct CrashRecord {
+    pub reason: CrashReason,
+    /// Task running at the time of the crash ('None' if the scheduler was not running).
+    pub task_name: Option<TaskName>,
+    /// Only available if the crash was caused by an exception.
+    pub registers: Option<Registers>,
+}
+
+impl fmt::Display for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let task_name = self.task_name.as_ref().map(|x| x.as_str());
+        match (self.reason, task_name) {
+            (CrashReason::HardFault, Some(task_name)) => {
+                write!(f, "HardFault in task '{}'\r\n", task_name)?
+            }
+            (CrashReason::HardFault, None) => write!(f, "HardFault outside of any task\r\n")?,
+            (CrashReason::TaskStalled, Some(task_name)) => write!(
+                f,
+                "stalled task '{}' (missed watchdog check-in)\r\n",
+                task_name
+            )?,
+            (CrashReason::TaskStalled, None) => {
+                write!(f, "stalled task (missed watchdog check-in)\r\n")?
+            }
+        }
+
+        if let Some(r) = &self.registers {
+            write!(
+                f,
+                "  PC=0x{:08X} LR=0x{:08X} xPSR=0x{:08X}\r\n",
+                r.pc, r.lr, r.xpsr
+            )?;
+            write!(
+                f,
+                "  R0=0x{:08X} R1=0x{:08X} R2=0x{:08X} R3=0x{:08X} R12=0x{:08X}\r\n",
+                r.r0, r.r1, r.r2, r.r3, r.r12
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Memory layout of the record (every bit pattern is valid, so it can be read even if it has
+/// never been written).
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct RawCrashRecord {
+    magic: u32,
+    reason: u32,
+    task_name_len: u32,
+    task_name: [u8; MAX_TASK_NAME_LEN],
+    registers_valid: u32,
+    registers: [u32; 8],
+    checksum: u32,
+}
+
+impl RawCrashRecord {
+    fn checksum(&self) -> u32 {
+        let mut checksum = self.magic ^ self.reason ^ self.task_name_len ^ self.registers_valid;
+        for (i, x) in self.task_name.iter().enumerate() {
+            checksum ^= (*x as u32) << ((i % 4) * 8);
+        }
+        for x in self.registers.iter() {
+            checksum = checksum.rotate_left(1) ^ x;
+        }
+        !checksum
+    }
+}
+
+fn raw_record_ptr() -> *mut RawCrashRecord {
+    core::ptr::addr_of_mut!(CRASH_RECORD).cast()
+}
+
+/// Stores the record to be reported after the next reset (overwriting any previous one).
+pub fn store(record: &CrashRecord) {
+    let mut raw = RawCrashRecord {
+        magic: CRASH_RECORD_MAGIC,
+        reason: record.reason.to_raw(),
+        task_name_len: 0,
+        task_name: [0; MAX_TASK_NAME_LEN],
+        registers_valid: 0,
+        registers: [0; 8],
+        checksum: 0,
+    };
+    if let Some(task_name) = &record.task_name {
+        let name = task_name.as_bytes();
+        raw.task_name_len = name.len() as u32;
+        raw.task_name[0..name.len()].clone_from_slice(name);
+    }
+    if let Some(registers) = &record.registers {
+        raw.registers_valid = 1;
+        raw.registers = registers.to_raw();
+    }
+    raw.checksum = raw.checksum();
+
+    unsafe { core::ptr::write_volatile(raw_record_ptr(), raw) };
+}
+
+/// Takes the record stored before the last reset (if any). The record is invalidated so it is
+/// only reported once.
+pub fn take() -> Option<CrashRecord> {
+    let mut raw = unsafe { core::ptr::read_volatile(raw_record_ptr()) };
+    let valid = raw.magic == CRASH_RECORD_MAGIC && raw.checksum == raw.checksum();
+
+    raw.magic = 0;
+    unsafe { core::ptr::write_volatile(raw_record_ptr(), raw) };
+
+    if !valid {
+        return None;
+    }
+
+    let task_name_len = raw.task_name_len as usize;
+    Some(CrashRecord {
+        reason: CrashReason::from_raw(raw.reason)?,
+        task_name: if task_name_len > 0 && task_name_len <= MAX_TASK_NAME_LEN {
+            Some(TaskName::from_bytes(&raw.task_name[0..task_name_len]))
+        } else {
+            None
+        },
+        registers: if raw.registers_valid != 0 {
+            Some(Registers::from_raw(&raw.registers))
+        } else {
+            None
+        },
+    })
+}
+
+/// Writes the record to the UART bypassing the driver (which is owned by the CLI task).
+///
+/// Intended to be called from a fault context: It does neither block on any lock nor rely on
+/// interrupts. Nothing is written if the UART has not been enabled yet.
+pub fn write_to_uart(record: &CrashRecord) {
+    let mut uart = RawUart;
+    if uart.is_enabled() {
+        write!(uart, "\r\n*** {}", record).unwrap_or(());
+        uart.flush();
+    }
+}
+
+/// Tells whether the last reset was caused by the watchdog counting down to zero (as opposed
+/// to a reset triggered by software or a power-on reset).
+pub fn watchdog_timeout_occurred() -> bool {
+    unsafe { (*pac::WATCHDOG::ptr()).reason.read().timer().bit_is_set() }
+}
+
+/// Resets the whole chip through the watchdog (the crash record is retained).
+pub fn reset() -> ! {
+    unsafe {
+        (*pac::WATCHDOG::ptr())
+            .ctrl
+            .write(|w| w.trigger().set_bit());
+    }
+
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
+/// Polling access to the UART0 registers.
+struct RawUart;
+
+impl RawUart {
+    fn is_enabled(&self) -> bool {
+        unsafe {
+            (*pac::RESETS::ptr()).reset_done.read().uart0().bit_is_set()
+                && (*pac::UART0::ptr()).uartcr.read().uarten().bit_is_set()
+        }
+    }
+
+    fn flush(&mut self) {
+        unsafe { while (*pac::UART0::ptr()).uartfr.read().busy().bit_is_set() {} }
+    }
+}
+
+impl Write for RawUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let uart = unsafe { &*pac::UART0::ptr() };
+        for c in s.bytes() {
+            while uart.uartfr.read().txff().bit_is_set() {}
+            uart.uartdr.write(|w| unsafe { w.data().bits(c) });
+        }
+        Ok(())
+    }
+}
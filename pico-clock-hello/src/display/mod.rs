@@ -1,111 +1,73 @@
 //! Driver for the dot matrix LED display of the "Pico Clock Green" kit:
 //! https://www.waveshare.com/pico-clock-green.htm
 
-pub mod data;
 pub mod pins;
+mod refresh;
 
-use crate::freertos;
+pub use refresh::handle_interrupt;
 
-use embedded_hal::digital::v2::OutputPin;
-use embedded_hal::digital::v2::PinState;
+use pico::hal::pac;
 
 // Time
-use embedded_time::duration::Milliseconds;
+use embedded_time::rate::Hertz;
 
-// Interrupt handler concurrency
-use core::cell::Cell;
-use cortex_m::interrupt;
-use cortex_m::interrupt::Mutex;
+use crate::display::pins::{OutputDisablePin, Pins};
+use crate::display::refresh::Refresh;
 
-use crate::display::data::{Data, RawData, RAW_HEIGHT, RAW_WIDTH};
-use crate::display::pins::Pins;
-
-/// TODO:
-/// - Use an abstraction of a FreeRTOS mutex.
-/// - Not a static variable anymore: Put the mutex on the heap with an Rc/Arc equivalent?
-///   (each display instance having its own mutex)
-static SYS_TICK_DATA: Mutex<Cell<RawData>> = Mutex::new(Cell::new([0; RAW_HEIGHT]));
+use pico_clock_core::display::data::{Data, Geometry};
+use pico_clock_core::display::DisplayOutput;
 
 /// Abstraction of the dot matrix LED display.
 pub struct Display {
     data: Data,
+    /// Brightness in percent.
+    brightness: u8,
+    refresh: Refresh,
 }
 
 impl Display {
-    pub fn new(mut pins: Pins) -> Self {
-        pins.output_disable.into_push_pull_output();
-        pins.serial_data.into_push_pull_output();
-        pins.clock.into_push_pull_output();
-        pins.latch.into_push_pull_output();
-        pins.address
-            .iter_mut()
-            .for_each(|x| x.into_push_pull_output());
-
-        // Disable output by default
-        pins.output_disable.set_high().unwrap();
-
-        freertos::create_task(
-            move || {
-                loop {
-                    let mut raw_data = [0; RAW_HEIGHT];
-                    interrupt::free(|cs| {
-                        raw_data = SYS_TICK_DATA.borrow(cs).get();
-                    });
-
-                    for row in 0..RAW_HEIGHT {
-                        pins.output_disable.set_high().unwrap();
-                        Display::select_row(&mut pins, row);
-                        Display::write_row(&mut pins, raw_data[row]);
-                        pins.output_disable.set_low().unwrap();
-
-                        // Required loop frequency: Refresh rate multiplied by 8 rows
-                        freertos::delay(Milliseconds(1));
-                    }
-                }
-            },
-            &freertos::TaskParameters {
-                name: "DisplayTask",
-                stack_depth: 1024, // Is actually 4096 bytes because portSTACK_TYPE is uint32_t
-                priority: crate::DISPLAY_TASK_PRIORITY,
-            },
+    /// The display is refreshed by PIO0 and DMA channels 0 to 3 (raising DMA_IRQ_0). The
+    /// brightness is controlled by the time /OE (GPIO13) is low in each row.
+    pub fn new(
+        pins: Pins,
+        output_disable: OutputDisablePin,
+        pio0: pac::PIO0,
+        dma: pac::DMA,
+        resets: &mut pac::RESETS,
+        system_clock: Hertz,
+    ) -> Self {
+        let geometry = Geometry::PICO_CLOCK_GREEN;
+        let refresh = Refresh::start(
+            pins,
+            output_disable,
+            geometry,
+            pio0,
+            dma,
+            resets,
+            system_clock,
         );
 
-        Self { data: Data::new() }
+        Self {
+            data: Data::with_geometry(geometry),
+            brightness: 100,
+            refresh,
+        }
     }
+}
 
-    pub fn modify_data<F>(&mut self, func: F)
+impl DisplayOutput for Display {
+    fn modify_data<F>(&mut self, func: F)
     where
         F: FnOnce(&mut Data),
     {
         func(&mut self.data);
-        interrupt::free(|cs| SYS_TICK_DATA.borrow(cs).replace(self.data.raw_data));
     }
 
-    fn select_row(pins: &mut Pins, row: usize) {
-        assert!(row < RAW_HEIGHT);
-        pins.address[0]
-            .set_state(PinState::from((row & 1) != 0))
-            .unwrap();
-        pins.address[1]
-            .set_state(PinState::from((row & 2) != 0))
-            .unwrap();
-        pins.address[2]
-            .set_state(PinState::from((row & 4) != 0))
-            .unwrap();
+    fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(100);
     }
 
-    fn write_row(pins: &mut Pins, mut raw_data: u32) {
-        for _ in 0..RAW_WIDTH {
-            pins.clock.set_low().unwrap();
-            pins.serial_data
-                .set_state(PinState::from((raw_data & 1) != 0))
-                .unwrap();
-            pins.clock.set_high().unwrap();
-
-            raw_data >>= 1;
-        }
-
-        pins.latch.set_high().unwrap();
-        pins.latch.set_low().unwrap();
+    fn present(&mut self) {
+        self.refresh.present(&self.data.raw_data, self.brightness);
     }
 }
@@ -1,17 +1,12 @@
 //! Set of pins to be used for the display.
 
 use pico::hal::gpio::dynpin::DynPin;
+use pico::hal::gpio::{bank0, Pin, PushPullOutput};
 
-/// Output pins.
-pub struct Pins {
-    // /OE
-    pub output_disable: DynPin,
-    // SDI
-    pub serial_data: DynPin,
-    // CLK
-    pub clock: DynPin,
-    // LE
-    pub latch: DynPin,
-    // A0..A2
-    pub address: [DynPin; 3],
-}
+use pico_clock_core::display::matrix;
+
+/// /OE (driven by the PIO to control the brightness).
+pub type OutputDisablePin = Pin<bank0::Gpio13, PushPullOutput>;
+
+/// Output pins (except /OE), with the address lines A0..A2.
+pub type Pins = matrix::Pins<DynPin, 3>;
@@ -0,0 +1,392 @@
+//! Refresh of the display by PIO0 and DMA, running entirely in hardware.
+//!
+//! Two state machines work in lockstep, synchronized by IRQ flag 0:
+//! - The shifter (SM0) shifts the columns of a row into the shift registers (clock on SM0's
+//!   side-set pin).
+//! - The row driver (SM1) latches them, selects the row by the address lines and enables the
+//!   output for the on-time given by the brightness. Meanwhile, the shifter already shifts in the
+//!   next row.
+//!
+//! Each state machine is fed by a pair of DMA channels: The first one transfers one word per row
+//! into the TX FIFO and then chains to the second one, which restarts the first one at the start
+//! of the buffer.
+//!
+//! The columns are double-buffered: A frame is composed in the back buffer and swapped to the
+//! front between refresh cycles (see 'pico_clock_core::display::swap'). The restart of the
+//! columns raises DMA_IRQ_0, whose handler performs the swap and notifies the producer.
+//!
+//! A row is shown for ROW_STEPS + 9 cycles of the 2 MHz PIO clock, so the 8 rows are refreshed at
+//! about 250 Hz.
+
+use crate::display::pins::{OutputDisablePin, Pins};
+use crate::freertos::{self, Notifier};
+
+use pico::hal::gpio::dynpin::{DynPin, DYN_FUNCTION_PIO0};
+use pico::hal::pac;
+use pico::hal::pio::{PIOBuilder, PIOExt, PinDir, PinState, ShiftDirection, Tx, ValidStateMachine};
+use pio::{
+    Assembler, InstructionOperands, JmpCondition, MovDestination, MovOperation, MovSource,
+    OutDestination, SetDestination, SideSet, WaitSource,
+};
+
+// Time
+use embedded_time::duration::Milliseconds;
+use embedded_time::rate::Hertz;
+
+// Interrupt handler concurrency
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::interrupt::{self, CriticalSection, Mutex};
+
+use pico_clock_core::display::data::{Geometry, RawData, RAW_HEIGHT};
+use pico_clock_core::display::swap::{CycleStart, Swap};
+
+const PIO_CLOCK_HZ: u32 = 2_000_000;
+
+/// Cycles of a row that are split into on-time and off-time according to the brightness.
+const ROW_STEPS: u32 = 1000;
+
+/// Pins written by the row driver per row, starting with LE (bit 0) and /OE (bit 1).
+const ROW_PINS: u8 = 11;
+/// Bits of the on-time and the off-time in a control word (above the pins).
+const STEPS_BITS: u8 = 10;
+
+const COLUMNS_CHANNEL: usize = 0;
+const COLUMNS_RESTART_CHANNEL: usize = 1;
+const CONTROL_CHANNEL: usize = 2;
+const CONTROL_RESTART_CHANNEL: usize = 3;
+
+/// Index in the notification array of the producer waiting for a swap.
+const SWAP_NOTIFICATION_INDEX: u32 = 1;
+/// Time to wait for a swap (a few refresh cycles) before assuming the refresh has stopped.
+const SWAP_TIMEOUT: Milliseconds = Milliseconds(20);
+
+/// Front and back buffer with the columns of each row (read by DMA).
+static COLUMNS: [[AtomicU32; RAW_HEIGHT]; 2] =
+    [const { [const { AtomicU32::new(0) }; RAW_HEIGHT] }; 2];
+/// Control word of each row (read by DMA): Pins written by the row driver, on-time and off-time.
+static CONTROL: [AtomicU32; RAW_HEIGHT] = [const { AtomicU32::new(0) }; RAW_HEIGHT];
+
+/// Start addresses of the buffers, written into the DMA channels when restarting them.
+static COLUMNS_START: AtomicU32 = AtomicU32::new(0);
+static CONTROL_START: AtomicU32 = AtomicU32::new(0);
+
+static SWAP: Mutex<Cell<Swap>> = Mutex::new(Cell::new(Swap::new()));
+/// Producer to be notified when a swap is done.
+static PRODUCER: Mutex<Cell<Option<Notifier>>> = Mutex::new(Cell::new(None));
+/// DMA registers, used by the interrupt handler after starting the refresh.
+static DMA: Mutex<RefCell<Option<pac::DMA>>> = Mutex::new(RefCell::new(None));
+
+pub struct Refresh {
+    geometry: Geometry,
+    /// Bit of each address line in the pins of a control word.
+    address_bits: [u8; 3],
+}
+
+impl Refresh {
+    /// Starts the refresh (showing a blank display until 'present' is called).
+    ///
+    /// The pins are used by PIO0: /OE must follow LE, and the address lines must be within the
+    /// 11 pins starting with LE.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        mut pins: Pins,
+        output_disable: OutputDisablePin,
+        geometry: Geometry,
+        pio0: pac::PIO0,
+        dma: pac::DMA,
+        resets: &mut pac::RESETS,
+        system_clock: Hertz,
+    ) -> Self {
+        geometry.validate();
+
+        let mut output_disable: DynPin = output_disable.into();
+        let clock = pin_number(&mut pins.clock);
+        let serial_data = pin_number(&mut pins.serial_data);
+        let latch = pin_number(&mut pins.latch);
+        assert!(pin_number(&mut output_disable) == latch + 1);
+        let mut address_bits = [0; 3];
+        for (bit, pin) in address_bits.iter_mut().zip(pins.address.iter_mut()) {
+            let number = pin_number(pin);
+            assert!(number > latch + 1 && number < latch + ROW_PINS);
+            *bit = number - latch;
+        }
+        // The row driver writes all of its pins, so the ones of the shifter must be outside
+        let row_pins = latch..latch + ROW_PINS;
+        assert!(!row_pins.contains(&clock) && !row_pins.contains(&serial_data));
+
+        let refresh = Self {
+            geometry,
+            address_bits,
+        };
+        refresh.write_control(0);
+
+        let (mut pio, sm0, sm1, _, _) = pio0.split(resets);
+        let divisor = system_clock.0 as f32 / PIO_CLOCK_HZ as f32;
+
+        let shifter = pio.install(&shifter_program()).unwrap();
+        let (mut shifter, _, shifter_tx) = PIOBuilder::from_program(shifter)
+            .out_pins(serial_data, 1)
+            .side_set_pin_base(clock)
+            .out_shift_direction(ShiftDirection::Right)
+            .clock_divisor(divisor)
+            .build(sm0);
+        shifter.set_pindirs([(clock, PinDir::Output), (serial_data, PinDir::Output)]);
+        // Number of columns minus 1, kept in Y
+        shifter.exec_instruction(
+            InstructionOperands::SET {
+                destination: SetDestination::Y,
+                data: (geometry.raw_width - 1) as u8,
+            }
+            .encode(),
+        );
+
+        let row_driver = pio.install(&row_driver_program()).unwrap();
+        let (mut row_driver, _, row_driver_tx) = PIOBuilder::from_program(row_driver)
+            .out_pins(latch, ROW_PINS)
+            .set_pins(latch, 2)
+            .out_shift_direction(ShiftDirection::Right)
+            .clock_divisor(divisor)
+            .build(sm1);
+        // Output disabled until the first row is latched
+        row_driver.set_pins([(latch, PinState::Low), (latch + 1, PinState::High)]);
+        row_driver.set_pindirs(
+            [latch, latch + 1]
+                .into_iter()
+                .chain(address_bits.iter().map(|bit| latch + bit))
+                .map(|pin| (pin, PinDir::Output)),
+        );
+
+        // DMA out of reset
+        resets.reset.modify(|_, w| w.dma().clear_bit());
+        while resets.reset_done.read().dma().bit_is_clear() {}
+
+        let rows = geometry.raw_height as u32;
+        start_dma_loop(
+            &dma,
+            COLUMNS_CHANNEL,
+            COLUMNS_RESTART_CHANNEL,
+            &COLUMNS_START,
+            COLUMNS[Swap::new().front()].as_ptr() as u32,
+            rows,
+            &shifter_tx,
+            true,
+        );
+        start_dma_loop(
+            &dma,
+            CONTROL_CHANNEL,
+            CONTROL_RESTART_CHANNEL,
+            &CONTROL_START,
+            CONTROL.as_ptr() as u32,
+            rows,
+            &row_driver_tx,
+            false,
+        );
+        dma.inte0
+            .write(|w| unsafe { w.bits(1 << COLUMNS_RESTART_CHANNEL) });
+        interrupt::free(|cs| DMA.borrow(cs).replace(Some(dma)));
+        unsafe {
+            pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+        }
+
+        // The state machines, FIFOs and DMA channels keep running on their own
+        shifter.synchronize_with(&mut row_driver);
+        shifter.start();
+        row_driver.start();
+
+        refresh
+    }
+
+    /// Shows the frame from the next refresh cycle on and waits until then. Brightness in percent
+    /// (0 switches the display off).
+    ///
+    /// The frame is dropped if a former swap is still pending (i.e. the refresh has stopped).
+    pub fn present(&mut self, raw_data: &RawData, brightness: u8) {
+        if !wait_for_swap() {
+            return;
+        }
+        let back = interrupt::free(|cs| SWAP.borrow(cs).get().back());
+
+        let brightness = brightness.min(100);
+        let mask = if brightness > 0 {
+            self.geometry.active_mask()
+        } else {
+            0
+        };
+        for (row, columns) in COLUMNS[back]
+            .iter()
+            .enumerate()
+            .take(self.geometry.raw_height)
+        {
+            columns.store(raw_data[row] & mask, Ordering::Relaxed);
+        }
+        // Not double-buffered (a brightness changing within a cycle is not noticeable)
+        self.write_control(brightness);
+
+        interrupt::free(|cs| {
+            PRODUCER
+                .borrow(cs)
+                .set(Some(Notifier::for_current_task(SWAP_NOTIFICATION_INDEX)));
+            let mut swap = SWAP.borrow(cs).get();
+            swap.request();
+            SWAP.borrow(cs).set(swap);
+        });
+
+        if !wait_for_swap() {
+            interrupt::free(|cs| {
+                let mut swap = SWAP.borrow(cs).get();
+                swap.cancel();
+                SWAP.borrow(cs).set(swap);
+            });
+        }
+    }
+
+    fn write_control(&self, brightness: u8) {
+        let on_time = ROW_STEPS * brightness as u32 / 100;
+        let off_time = ROW_STEPS - on_time;
+
+        for (row, control_word) in CONTROL.iter().enumerate().take(self.geometry.raw_height) {
+            // LE high and /OE high: Latch the row while the output is disabled
+            let mut control = 0b11;
+            for (line, bit) in self.address_bits.iter().enumerate() {
+                if (row >> line) & 1 != 0 {
+                    control |= 1 << bit;
+                }
+            }
+            control |= on_time << ROW_PINS;
+            control |= off_time << (ROW_PINS + STEPS_BITS);
+
+            control_word.store(control, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Performs the swap at the start of each refresh cycle (called by the DMA interrupt handler).
+pub fn handle_interrupt(cs: &CriticalSection) {
+    if let Some(dma) = DMA.borrow(cs).borrow().as_ref() {
+        dma.ints0
+            .write(|w| unsafe { w.bits(1 << COLUMNS_RESTART_CHANNEL) });
+
+        let mut swap = SWAP.borrow(cs).get();
+        match swap.cycle_start() {
+            CycleStart::Schedule(index) => {
+                COLUMNS_START.store(COLUMNS[index].as_ptr() as u32, Ordering::Relaxed)
+            }
+            CycleStart::Swapped => {
+                if let Some(producer) = PRODUCER.borrow(cs).get() {
+                    producer.notify_from_isr();
+                }
+            }
+            CycleStart::Nothing => {}
+        }
+        SWAP.borrow(cs).set(swap);
+    }
+}
+
+/// Waits until a pending swap (if any) is done. Returns false on timeout.
+fn wait_for_swap() -> bool {
+    let is_pending = || interrupt::free(|cs| SWAP.borrow(cs).get().is_pending());
+
+    // A notification may be left over from a former timeout (taking clears it)
+    for _ in 0..2 {
+        if !is_pending() {
+            return true;
+        }
+        freertos::take_notification(SWAP_NOTIFICATION_INDEX, SWAP_TIMEOUT);
+    }
+    !is_pending()
+}
+
+/// Shifts out the columns of a row (lowest bit first) and waits until they are latched.
+fn shifter_program() -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    let mut a = Assembler::new_with_side_set(SideSet::new(false, 1, false));
+    let mut column = a.label();
+
+    a.pull_with_side_set(false, true, 0);
+    // Delay: The row driver sets LE low after being released by the IRQ
+    a.mov_with_delay_and_side_set(MovDestination::X, MovOperation::None, MovSource::Y, 2, 0);
+    a.bind(&mut column);
+    a.out_with_side_set(OutDestination::PINS, 1, 0);
+    // Rising edge of the clock
+    a.jmp_with_side_set(JmpCondition::XDecNonZero, &mut column, 1);
+    a.irq_with_side_set(false, true, 0, false, 0);
+    a.assemble_program()
+}
+
+/// Latches the row shifted in, selects it and enables the output for the on-time.
+fn row_driver_program() -> pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }> {
+    let mut a = Assembler::new();
+    let mut on = a.label();
+    let mut off = a.label();
+
+    a.pull(false, true);
+    a.wait(1, WaitSource::IRQ, 0);
+    a.out(OutDestination::PINS, ROW_PINS);
+    // LE low and /OE low
+    a.set(SetDestination::PINS, 0b00);
+    a.out(OutDestination::X, STEPS_BITS);
+    a.bind(&mut on);
+    a.jmp(JmpCondition::XDecNonZero, &mut on);
+    // /OE high
+    a.set(SetDestination::PINS, 0b10);
+    a.out(OutDestination::X, STEPS_BITS);
+    a.bind(&mut off);
+    a.jmp(JmpCondition::XDecNonZero, &mut off);
+    a.assemble_program()
+}
+
+fn pin_number(pin: &mut DynPin) -> u8 {
+    pin.try_into_mode(DYN_FUNCTION_PIO0).unwrap();
+    pin.id().num
+}
+
+/// Transfers the buffer into the TX FIFO over and over again. If 'interrupt' is set, each
+/// restart raises DMA_IRQ_0.
+#[allow(clippy::too_many_arguments)]
+fn start_dma_loop<SM: ValidStateMachine>(
+    dma: &pac::DMA,
+    channel: usize,
+    restart_channel: usize,
+    start: &'static AtomicU32,
+    buffer: u32,
+    words: u32,
+    tx: &Tx<SM>,
+    interrupt: bool,
+) {
+    start.store(buffer, Ordering::Relaxed);
+
+    let transfer = &dma.ch[channel];
+    transfer.ch_read_addr.write(|w| unsafe { w.bits(buffer) });
+    transfer
+        .ch_write_addr
+        .write(|w| unsafe { w.bits(tx.fifo_address() as u32) });
+    transfer.ch_trans_count.write(|w| unsafe { w.bits(words) });
+    transfer.ch_al1_ctrl.write(|w| unsafe {
+        w.data_size().size_word();
+        w.incr_read().set_bit();
+        w.incr_write().clear_bit();
+        w.treq_sel().bits(tx.dreq_value());
+        w.chain_to().bits(restart_channel as u8);
+        w.irq_quiet().set_bit();
+        w.en().set_bit()
+    });
+
+    let restart = &dma.ch[restart_channel];
+    restart
+        .ch_read_addr
+        .write(|w| unsafe { w.bits(start.as_ptr() as u32) });
+    restart
+        .ch_write_addr
+        .write(|w| unsafe { w.bits(transfer.ch_al3_read_addr_trig.as_ptr() as u32) });
+    restart.ch_trans_count.write(|w| unsafe { w.bits(1) });
+    // Starts the transfer (chaining to itself means no chaining)
+    restart.ch_ctrl_trig.write(|w| unsafe {
+        w.data_size().size_word();
+        w.incr_read().clear_bit();
+        w.incr_write().clear_bit();
+        w.treq_sel().permanent();
+        w.chain_to().bits(restart_channel as u8);
+        w.irq_quiet().bit(!interrupt);
+        w.en().set_bit()
+    });
+}
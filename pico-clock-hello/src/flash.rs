@@ -0,0 +1,149 @@
+//! Erasing and programming the QSPI flash the program itself is executed from (XIP).
+//!
+//! While the flash is being erased or programmed, it cannot be accessed through XIP. Therefore
+//! the operation itself runs from RAM using the boot ROM functions, with interrupts disabled and
+//! the scheduler suspended. Afterwards XIP is restored by a RAM copy of the second stage boot
+//! loader (the same way as the Pico SDK does).
+
+use crate::freertos;
+
+use pico_clock_core::flash::{Flash, FLASH_SIZE, PAGE_SIZE, SECTOR_SIZE};
+
+use cortex_m::interrupt;
+
+const XIP_BASE: u32 = 0x1000_0000;
+
+const BLOCK_SIZE: u32 = 65536;
+const BLOCK_ERASE_CMD: u8 = 0xD8;
+
+/// Gets flash content (read through XIP).
+pub fn read(offset: u32, len: u32) -> &'static [u8] {
+    assert!(offset + len <= FLASH_SIZE);
+
+    unsafe { core::slice::from_raw_parts((XIP_BASE + offset) as *const u8, len as usize) }
+}
+
+/// Erases whole sectors.
+pub fn erase(offset: u32, len: u32) {
+    assert!(offset.is_multiple_of(SECTOR_SIZE) && len.is_multiple_of(SECTOR_SIZE));
+    assert!(offset + len <= FLASH_SIZE);
+
+    run_from_ram(offset, core::ptr::null(), len);
+}
+
+/// Programs whole pages (that must have been erased before).
+pub fn program(offset: u32, data: &[u8]) {
+    let len = data.len() as u32;
+    assert!(offset.is_multiple_of(PAGE_SIZE) && len.is_multiple_of(PAGE_SIZE));
+    assert!(offset + len <= FLASH_SIZE);
+
+    run_from_ram(offset, data.as_ptr(), len);
+}
+
+/// The flash as used by the settings store.
+pub struct XipFlash;
+
+impl Flash for XipFlash {
+    fn read(&self, offset: u32, len: u32) -> &[u8] {
+        read(offset, len)
+    }
+
+    fn erase(&mut self, offset: u32, len: u32) {
+        erase(offset, len);
+    }
+
+    fn program(&mut self, offset: u32, data: &[u8]) {
+        program(offset, data);
+    }
+}
+
+/// Pointers to the boot ROM functions (looked up in advance because the lookup code is located
+/// in flash).
+struct RomFunctions {
+    connect_internal_flash: extern "C" fn(),
+    flash_exit_xip: extern "C" fn(),
+    flash_range_erase: extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: extern "C" fn(),
+}
+
+impl RomFunctions {
+    fn lookup() -> Self {
+        unsafe {
+            let connect_internal_flash: extern "C" fn() =
+                core::mem::transmute(rom_func_lookup(*b"IF"));
+            let flash_exit_xip: extern "C" fn() = core::mem::transmute(rom_func_lookup(*b"EX"));
+            let flash_range_erase: extern "C" fn(u32, usize, u32, u8) =
+                core::mem::transmute(rom_func_lookup(*b"RE"));
+            let flash_range_program: extern "C" fn(u32, *const u8, usize) =
+                core::mem::transmute(rom_func_lookup(*b"RP"));
+            let flash_flush_cache: extern "C" fn() = core::mem::transmute(rom_func_lookup(*b"FC"));
+
+            Self {
+                connect_internal_flash,
+                flash_exit_xip,
+                flash_range_erase,
+                flash_range_program,
+                flash_flush_cache,
+            }
+        }
+    }
+}
+
+/// Looks up a function in the boot ROM function table (see RP2040 datasheet, section 2.8.3).
+unsafe fn rom_func_lookup(code: [u8; 2]) -> *const u32 {
+    // The ROM stores 16 bit pointers to the lookup function and the function table
+    const ROM_FUNC_TABLE_PTR: *const u16 = 0x0000_0014 as *const u16;
+    const ROM_TABLE_LOOKUP_PTR: *const u16 = 0x0000_0018 as *const u16;
+
+    let rom_table_lookup: extern "C" fn(*const u16, u32) -> *const u32 =
+        core::mem::transmute(*ROM_TABLE_LOOKUP_PTR as usize);
+    let func_table = *ROM_FUNC_TABLE_PTR as usize as *const u16;
+
+    let func = rom_table_lookup(func_table, u16::from_le_bytes(code) as u32);
+    assert!(!func.is_null());
+    func
+}
+
+fn run_from_ram(offset: u32, data: *const u8, len: u32) {
+    let functions = RomFunctions::lookup();
+
+    // Copy of the second stage boot loader (its last word is a checksum)
+    let mut boot2 = [0u32; 64];
+    for (i, x) in crate::BOOT2.chunks(4).enumerate() {
+        boot2[i] = u32::from_le_bytes([x[0], x[1], x[2], x[3]]);
+    }
+    // Thumb mode requires setting the LSB of the function address
+    let boot2_entry: extern "C" fn() =
+        unsafe { core::mem::transmute((boot2.as_ptr() as usize) | 1) };
+
+    freertos::with_scheduler_suspended(|| {
+        interrupt::free(|_| unsafe {
+            flash_operation(&functions, boot2_entry, offset, data, len);
+        });
+    });
+}
+
+/// Erases ('data' is null) or programs the flash.
+///
+/// Must not call any function located in flash and must not access anything located in flash.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_operation(
+    functions: &RomFunctions,
+    boot2_entry: extern "C" fn(),
+    offset: u32,
+    data: *const u8,
+    len: u32,
+) {
+    (functions.connect_internal_flash)();
+    (functions.flash_exit_xip)();
+    if data.is_null() {
+        (functions.flash_range_erase)(offset, len as usize, BLOCK_SIZE, BLOCK_ERASE_CMD);
+    } else {
+        (functions.flash_range_program)(offset, data, len as usize);
+    }
+    // Also invalidates the XIP cache (which may contain stale flash content now)
+    (functions.flash_flush_cache)();
+    boot2_entry();
+}
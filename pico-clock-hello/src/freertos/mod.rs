@@ -1,6 +1,8 @@
+mod mutex;
 mod native;
 pub mod opaque_box;
 
+pub use mutex::Mutex;
 use opaque_box::OpaqueBox;
 
 // FFI
@@ -8,6 +10,9 @@ use core::ffi::c_void;
 
 use embedded_time::duration::Milliseconds;
 
+/// Maximum task name length (configMAX_TASK_NAME_LEN without the null terminator).
+pub const MAX_TASK_NAME_LEN: usize = 15;
+
 pub struct TaskParameters<'a> {
     pub name: &'a str,
     pub stack_depth: u16,
@@ -15,8 +20,6 @@ pub struct TaskParameters<'a> {
 }
 
 pub fn create_task<F: FnOnce() + Send + 'static>(task_func: F, params: &TaskParameters) {
-    const MAX_NAME_LEN: usize = 15;
-
     unsafe {
         assert!(native::freertos_sizeof_size_t() == core::mem::size_of::<usize>() as u8);
         assert!(
@@ -24,7 +27,7 @@ pub fn create_task<F: FnOnce() + Send + 'static>(task_func: F, params: &TaskPara
                 == core::mem::size_of_val(&params.stack_depth)
         );
         assert!(native::freertos_sizeof_BaseType_t() == core::mem::size_of_val(&params.priority));
-        assert!(native::freertos_configMAX_TASK_NAME_LEN() == MAX_NAME_LEN + 1);
+        assert!(native::freertos_configMAX_TASK_NAME_LEN() == MAX_TASK_NAME_LEN + 1);
 
         // Backup closure to the heap.
         let task_func_on_heap = OpaqueBox::new(task_func);
@@ -37,11 +40,11 @@ pub fn create_task<F: FnOnce() + Send + 'static>(task_func: F, params: &TaskPara
         // 'alloc_error_handler' attribute.
 
         // Prepare null-terminated task name (assuming configMAX_TASK_NAME_LEN is 16)
-        let mut name: [u8; MAX_NAME_LEN + 1] = [0; MAX_NAME_LEN + 1];
-        let name_len = if params.name.len() < MAX_NAME_LEN {
+        let mut name: [u8; MAX_TASK_NAME_LEN + 1] = [0; MAX_TASK_NAME_LEN + 1];
+        let name_len = if params.name.len() < MAX_TASK_NAME_LEN {
             params.name.len()
         } else {
-            MAX_NAME_LEN
+            MAX_TASK_NAME_LEN
         };
         name[0..name_len].clone_from_slice(&params.name.as_bytes()[0..name_len]);
 
@@ -73,6 +76,114 @@ pub fn delay(duration: Milliseconds) {
     }
 }
 
+/// Delay for running code at a fixed rate, regardless of how long it takes (as long as that is
+/// shorter than the period).
+pub struct PeriodicDelay {
+    previous_wake_time: u32,
+    period: Milliseconds,
+}
+
+impl PeriodicDelay {
+    /// The first period starts now.
+    pub fn new(period: Milliseconds) -> Self {
+        Self {
+            previous_wake_time: unsafe { native::xTaskGetTickCount() },
+            period,
+        }
+    }
+
+    /// Waits until one period after the previous wake time.
+    pub fn wait(&mut self) {
+        let delayed =
+            unsafe { native::xTaskDelayUntil(&mut self.previous_wake_time, self.period.0) };
+
+        // Start over instead of catching up with the missed periods
+        if delayed == 0 {
+            self.previous_wake_time = unsafe { native::xTaskGetTickCount() };
+        }
+    }
+}
+
+/// Moves a value to the FreeRTOS heap where it stays for the rest of the program (e.g. to share
+/// it between tasks).
+pub fn leak<T: 'static>(value: T) -> &'static mut T {
+    // The FreeRTOS heap (heap_4) aligns blocks to portBYTE_ALIGNMENT (8 bytes).
+    assert!(core::mem::align_of::<T>() <= 8);
+
+    let raw_ptr = OpaqueBox::new(value).into_raw();
+    assert!((raw_ptr as usize) & (core::mem::align_of::<T>() - 1) == 0);
+
+    unsafe { &mut *raw_ptr }
+}
+
+/// Gets the time since the scheduler has been started (wraps around after about 49 days).
+pub fn tick_count() -> Milliseconds {
+    unsafe {
+        assert!(native::freertos_sizeof_TickType_t() == core::mem::size_of::<u32>());
+
+        Milliseconds(native::xTaskGetTickCount())
+    }
+}
+
+/// Same as 'tick_count', but to be called from interrupt handlers.
+pub fn tick_count_from_isr() -> Milliseconds {
+    unsafe { Milliseconds(native::xTaskGetTickCountFromISR()) }
+}
+
+/// Handle to notify a task from an interrupt handler, using one entry of its notification array
+/// (index below configTASK_NOTIFICATION_ARRAY_ENTRIES, 0 is used by the stream buffers).
+#[derive(Clone, Copy)]
+pub struct Notifier {
+    task_handle: native::TaskHandle,
+    index: u32,
+}
+
+// The task handle is only passed to FreeRTOS
+unsafe impl Send for Notifier {}
+
+impl Notifier {
+    /// Notifier of the calling task.
+    pub fn for_current_task(index: u32) -> Self {
+        Self {
+            task_handle: unsafe { native::xTaskGetCurrentTaskHandle() },
+            index,
+        }
+    }
+
+    /// Wakes the task if it waits in 'take_notification' (otherwise, the notification is kept).
+    /// To be called from interrupt handlers.
+    pub fn notify_from_isr(&self) {
+        let mut higher_priority_task_woken = 0;
+        unsafe {
+            native::vTaskGenericNotifyGiveFromISR(
+                self.task_handle,
+                self.index,
+                &mut higher_priority_task_woken,
+            );
+        }
+
+        // portYIELD_FROM_ISR
+        if higher_priority_task_woken != 0 {
+            cortex_m::peripheral::SCB::set_pendsv();
+        }
+    }
+}
+
+/// Waits for a notification of the calling task (see 'Notifier'). Returns false on timeout.
+pub fn take_notification(index: u32, timeout: Milliseconds) -> bool {
+    unsafe { native::ulTaskGenericNotifyTake(index, native::PD_TRUE, timeout.0) != 0 }
+}
+
+/// Suspends the scheduler (no other task is executed, but interrupts remain enabled) while
+/// executing the given function.
+pub fn with_scheduler_suspended<F: FnOnce() -> R, R>(func: F) -> R {
+    unsafe { native::vTaskSuspendAll() };
+    let result = func();
+    unsafe { native::xTaskResumeAll() };
+
+    result
+}
+
 pub fn start_scheduler() -> ! {
     unsafe {
         native::vTaskStartScheduler();
@@ -81,3 +192,58 @@ pub fn start_scheduler() -> ! {
     // Should not be reached (except if there's not enough heap memory left)
     panic!("Not enough heap memory");
 }
+
+/// Copy of a task name (without the need to access the task control block anymore).
+#[derive(Clone, Copy)]
+pub struct TaskName {
+    data: [u8; MAX_TASK_NAME_LEN],
+    len: usize,
+}
+
+impl TaskName {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut data = [0; MAX_TASK_NAME_LEN];
+        let len = bytes
+            .iter()
+            .take(MAX_TASK_NAME_LEN)
+            .take_while(|x| **x != 0)
+            .count();
+        data[0..len].clone_from_slice(&bytes[0..len]);
+
+        Self { data, len }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[0..self.len]
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Task names are always created from 'str' but may have been truncated inside of a
+        // multi-byte character.
+        core::str::from_utf8(self.as_bytes()).unwrap_or("?")
+    }
+}
+
+/// Gets the name of the currently running task.
+///
+/// Returns 'None' if the scheduler has not been started yet. Does neither block nor allocate,
+/// so it may also be called from an exception handler (then the name of the interrupted task
+/// is returned).
+pub fn current_task_name() -> Option<TaskName> {
+    const TASK_SCHEDULER_NOT_STARTED: i32 = 1;
+
+    unsafe {
+        if native::xTaskGetSchedulerState() == TASK_SCHEDULER_NOT_STARTED {
+            return None;
+        }
+
+        let name_ptr = native::pcTaskGetName(core::ptr::null_mut());
+        if name_ptr.is_null() {
+            return None;
+        }
+
+        // The name is null-terminated and at most configMAX_TASK_NAME_LEN bytes long.
+        let name = core::slice::from_raw_parts(name_ptr, MAX_TASK_NAME_LEN + 1);
+        Some(TaskName::from_bytes(name))
+    }
+}
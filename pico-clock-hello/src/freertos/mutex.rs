@@ -0,0 +1,71 @@
+//! Abstraction of a FreeRTOS mutex protecting some data (similar to 'std::sync::Mutex').
+
+use super::native;
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+pub struct Mutex<T: ?Sized> {
+    handle: native::QueueHandle,
+    data: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        let handle = unsafe { native::xQueueCreateMutex(native::QUEUE_TYPE_MUTEX) };
+        assert!(!handle.is_null()); // Assuming that there's always enough heap memory
+
+        Self {
+            handle,
+            data: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Blocks until the mutex is available.
+    ///
+    /// Must only be called from a task (not from an interrupt handler) and not while the
+    /// calling task is already holding the mutex (FreeRTOS mutexes are not recursive).
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        let status = unsafe { native::xQueueSemaphoreTake(self.handle, native::PORT_MAX_DELAY) };
+        assert!(status == native::PD_TRUE); // Cannot time out with portMAX_DELAY
+
+        MutexGuard { mutex: self }
+    }
+}
+
+/// The mutex is only used to hand out exclusive access to the data.
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            native::xQueueGenericSend(
+                self.mutex.handle,
+                core::ptr::null(),
+                0,
+                native::QUEUE_SEND_TO_BACK,
+            );
+        }
+    }
+}
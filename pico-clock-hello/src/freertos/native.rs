@@ -4,6 +4,14 @@ use core::ffi::c_void;
 // Dummy placeholder types for better type safety
 pub enum TaskControlBlock {}
 pub type TaskHandle = *mut TaskControlBlock;
+pub enum QueueDefinition {}
+pub type QueueHandle = *mut QueueDefinition;
+
+// Constants from the FreeRTOS header files
+pub const PORT_MAX_DELAY: u32 = 0xFFFFFFFF;
+pub const QUEUE_TYPE_MUTEX: u8 = 1;
+pub const QUEUE_SEND_TO_BACK: i32 = 0;
+pub const PD_TRUE: i32 = 1;
 
 #[link(name = "freertos", kind = "static")]
 extern "C" {
@@ -21,9 +29,48 @@ extern "C" {
 
     // Should be 32 bit, except if configUSE_16_BIT_TICKS is set to 1
     pub fn vTaskDelay(xTicksToDelay: u32);
+    // Returns pdFALSE (0) if the wake time had already passed
+    pub fn xTaskDelayUntil(pxPreviousWakeTime: *mut u32, xTimeIncrement: u32) -> i32;
 
     pub fn vTaskStartScheduler();
 
+    pub fn vTaskSuspendAll();
+    pub fn xTaskResumeAll() -> i32;
+
+    // Should be 32 bit, except if configUSE_16_BIT_TICKS is set to 1
+    pub fn xTaskGetTickCount() -> u32;
+    pub fn xTaskGetTickCountFromISR() -> u32;
+
+    // Returns taskSCHEDULER_NOT_STARTED (1), taskSCHEDULER_RUNNING (2) or taskSCHEDULER_SUSPENDED (0)
+    pub fn xTaskGetSchedulerState() -> i32;
+
+    // Passing a null handle returns the name of the calling (i.e. currently running) task
+    pub fn pcTaskGetName(task_handle: TaskHandle) -> *const u8;
+
+    pub fn xTaskGetCurrentTaskHandle() -> TaskHandle;
+
+    // Used to implement the ulTaskNotifyTakeIndexed and vTaskNotifyGiveIndexedFromISR macros
+    pub fn ulTaskGenericNotifyTake(
+        index_to_wait_on: u32,
+        clear_count_on_exit: i32,
+        ticks_to_wait: u32,
+    ) -> u32;
+    pub fn vTaskGenericNotifyGiveFromISR(
+        task_handle: TaskHandle,
+        index_to_notify: u32,
+        higher_priority_task_woken: *mut i32,
+    );
+
+    // Used to implement the xSemaphoreCreateMutex, xSemaphoreTake and xSemaphoreGive macros
+    pub fn xQueueCreateMutex(queue_type: u8) -> QueueHandle;
+    pub fn xQueueSemaphoreTake(queue: QueueHandle, ticks_to_wait: u32) -> i32;
+    pub fn xQueueGenericSend(
+        queue: QueueHandle,
+        item: *const c_void,
+        ticks_to_wait: u32,
+        copy_position: i32,
+    ) -> i32;
+
     pub fn xTaskCreate(
         task_func: extern "C" fn(*mut c_void),
         name: *const u8,  // see also configMAX_TASK_NAME_LEN
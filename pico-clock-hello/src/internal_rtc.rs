@@ -0,0 +1,187 @@
+//! RTC peripheral of the RP2040, used to keep the time while the external RTC cannot be
+//! accessed (e.g. if it is missing or the I2C bus is disturbed), or as the only RTC.
+//!
+//! It is seeded from the external RTC at startup and reconciled with it periodically while the
+//! external RTC works. Its time is lost at a reset (the RP2040 is not battery backed).
+
+use crate::freertos;
+use crate::rtc::RtcError;
+
+use pico::hal::clocks::RtcClock;
+use pico::hal::pac;
+use pico::hal::rtc::{DateTime, DayOfWeek, RealTimeClock};
+
+use ds323x::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use embedded_time::duration::Milliseconds;
+
+// Interrupt handler concurrency
+use core::cell::{Cell, RefCell};
+use cortex_m::interrupt;
+use cortex_m::interrupt::Mutex;
+
+/// Period after which the time is taken from the external RTC again (even if both agree within a
+/// second, to keep the fractions of the seconds aligned).
+#[cfg(not(feature = "rtc-rp2040"))]
+const RECONCILE_PERIOD: Milliseconds = Milliseconds(10 * 60 * 1000);
+
+#[derive(Clone, Copy)]
+struct InternalRtcState {
+    /// Whether the time has been set (from the external RTC or by the user).
+    valid: bool,
+    /// Tick count of the last reconciliation.
+    reconciled_at: Option<Milliseconds>,
+    /// Whether the last access to the external RTC failed (the internal RTC is used instead).
+    fallback: bool,
+}
+
+static INTERNAL_RTC: Mutex<RefCell<Option<RealTimeClock>>> = Mutex::new(RefCell::new(None));
+static STATE: Mutex<Cell<InternalRtcState>> = Mutex::new(Cell::new(InternalRtcState {
+    valid: false,
+    reconciled_at: None,
+    fallback: false,
+}));
+
+/// Starts the internal RTC with the given time (or an arbitrary one if the external RTC cannot
+/// be read, the time being invalid then).
+pub fn start(
+    rtc: pac::RTC,
+    clock: RtcClock,
+    resets: &mut pac::RESETS,
+    initial: Option<NaiveDateTime>,
+) {
+    let datetime = initial.unwrap_or_else(|| {
+        // Cannot fail because the date is valid
+        NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    });
+
+    // Cannot fail because the external RTCs do not support years beyond the range of the RP2040
+    let internal_rtc = RealTimeClock::new(rtc, clock, resets, to_hal_datetime(&datetime)).unwrap();
+
+    interrupt::free(|cs| {
+        INTERNAL_RTC.borrow(cs).replace(Some(internal_rtc));
+        STATE.borrow(cs).set(InternalRtcState {
+            valid: initial.is_some(),
+            reconciled_at: initial.map(|_| freertos::tick_count()),
+            fallback: false,
+        });
+    });
+}
+
+/// Gets the time ('None' if it has never been set).
+pub fn get_datetime() -> Option<NaiveDateTime> {
+    interrupt::free(|cs| {
+        if !STATE.borrow(cs).get().valid {
+            return None;
+        }
+        let internal_rtc = INTERNAL_RTC.borrow(cs).borrow();
+        from_hal_datetime(&internal_rtc.as_ref()?.now().ok()?)
+    })
+}
+
+/// Gets the time because the external RTC could not be read ('None' if it has never been set).
+#[cfg(not(feature = "rtc-rp2040"))]
+pub fn fallback_datetime() -> Option<NaiveDateTime> {
+    interrupt::free(|cs| {
+        let mut state = STATE.borrow(cs).get();
+        state.fallback = true;
+        STATE.borrow(cs).set(state);
+    });
+    get_datetime()
+}
+
+/// Takes the time just read from the external RTC if the reconciliation is due or the clocks
+/// disagree.
+#[cfg(not(feature = "rtc-rp2040"))]
+pub fn reconcile(datetime: &NaiveDateTime) {
+    let now = freertos::tick_count();
+
+    interrupt::free(|cs| {
+        let mut state = STATE.borrow(cs).get();
+        state.fallback = false;
+
+        let mut internal_rtc = INTERNAL_RTC.borrow(cs).borrow_mut();
+        if let Some(internal_rtc) = internal_rtc.as_mut() {
+            // The tick count wraps around
+            let due = state
+                .reconciled_at
+                .is_none_or(|x| now.0.wrapping_sub(x.0) >= RECONCILE_PERIOD.0);
+            // Both clocks count the seconds with a different phase
+            let disagree = internal_rtc
+                .now()
+                .ok()
+                .and_then(|x| from_hal_datetime(&x))
+                .is_none_or(|x| x.signed_duration_since(*datetime).num_seconds().abs() > 1);
+
+            if !state.valid || due || disagree {
+                // The time read from the external RTC is valid (within the range of the RP2040)
+                if internal_rtc.set_datetime(to_hal_datetime(datetime)).is_ok() {
+                    state.valid = true;
+                    state.reconciled_at = Some(now);
+                }
+            }
+        }
+
+        STATE.borrow(cs).set(state);
+    });
+}
+
+/// Sets the time (when set by the user, also if the external RTC cannot be accessed).
+pub fn set_datetime(datetime: &NaiveDateTime) -> Result<(), RtcError> {
+    interrupt::free(|cs| {
+        let mut internal_rtc = INTERNAL_RTC.borrow(cs).borrow_mut();
+        internal_rtc
+            .as_mut()
+            .ok_or(RtcError::Unsupported)?
+            .set_datetime(to_hal_datetime(datetime))
+            .map_err(|_| RtcError::InvalidData)?;
+
+        let mut state = STATE.borrow(cs).get();
+        state.valid = true;
+        state.reconciled_at = Some(freertos::tick_count());
+        STATE.borrow(cs).set(state);
+        Ok(())
+    })
+}
+
+/// Checks whether the internal RTC is used because the last access to the external RTC failed.
+pub fn is_fallback_active() -> bool {
+    interrupt::free(|cs| STATE.borrow(cs).get().fallback)
+}
+
+fn to_hal_datetime(datetime: &NaiveDateTime) -> DateTime {
+    let day_of_week = match datetime.weekday().num_days_from_sunday() {
+        0 => DayOfWeek::Sunday,
+        1 => DayOfWeek::Monday,
+        2 => DayOfWeek::Tuesday,
+        3 => DayOfWeek::Wednesday,
+        4 => DayOfWeek::Thursday,
+        5 => DayOfWeek::Friday,
+        _ => DayOfWeek::Saturday,
+    };
+
+    DateTime {
+        year: datetime.year() as u16,
+        month: datetime.month() as u8,
+        day: datetime.day() as u8,
+        day_of_week,
+        hour: datetime.hour() as u8,
+        minute: datetime.minute() as u8,
+        second: datetime.second() as u8,
+    }
+}
+
+fn from_hal_datetime(datetime: &DateTime) -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(
+        datetime.year as i32,
+        datetime.month as u32,
+        datetime.day as u32,
+    )?
+    .and_hms_opt(
+        datetime.hour as u32,
+        datetime.minute as u32,
+        datetime.second as u32,
+    )
+}
@@ -0,0 +1,24 @@
+//! Photoresistor of the "Pico Clock Green" kit (connected to GPIO26/ADC0), measuring the ambient
+//! light for the automatic brightness.
+
+use embedded_hal::adc::OneShot;
+use pico::hal::adc::Adc;
+use pico::hal::gpio::{bank0, FloatingInput, Pin};
+
+pub type LightSensorPin = Pin<bank0::Gpio26, FloatingInput>;
+
+pub struct LightSensor {
+    adc: Adc,
+    pin: LightSensorPin,
+}
+
+impl LightSensor {
+    pub fn new(adc: Adc, pin: LightSensorPin) -> Self {
+        Self { adc, pin }
+    }
+
+    /// Reads the ambient light level (0 to 4095, increasing with the light).
+    pub fn read(&mut self) -> Option<u16> {
+        nb::block!(self.adc.read(&mut self.pin)).ok()
+    }
+}
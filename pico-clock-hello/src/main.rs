@@ -4,31 +4,48 @@
 #![no_std]
 #![no_main]
 
+// The hardware-independent logic is in the library 'pico-clock-core'
+mod board;
+mod buttons;
+mod buzzer;
 mod cli;
+mod crash;
 mod display;
+mod flash;
 mod freertos;
-mod text;
+mod internal_rtc;
+mod light_sensor;
+mod rtc;
+mod sqw;
+mod supervisor;
 
 use cortex_m_rt::entry;
 use pico::hal;
 use pico::hal::clocks::Clock;
 use pico::hal::pac;
+use pico::hal::pac::interrupt;
 
-use ds323x::Ds323x;
-use ds323x::Hours;
 use embedded_time::rate::Extensions;
 
+use core::fmt::Write;
+
 // Time
 use embedded_time::duration::Milliseconds;
 
-use display::data::DOT_MATRIX_WIDTH;
+use board::Board;
 use display::Display;
-use text::TextBitmap;
+use flash::XipFlash;
+use light_sensor::LightSensor;
+
+use pico_clock_core::display_fsm::{self, DisplayFsm};
+use pico_clock_core::settings;
 
 // Program shall halt on panic
 use panic_halt as _;
 
-const DISPLAY_TASK_PRIORITY: u32 = 3;
+const SUPERVISOR_TASK_PRIORITY: u32 = 4;
+const BUZZER_TASK_PRIORITY: u32 = 3;
+const BUTTON_TASK_PRIORITY: u32 = 3;
 const ANIMATION_TASK_PRIORITY: u32 = 2;
 const CLI_TASK_PRIORITY: u32 = 1;
 
@@ -40,7 +57,7 @@ pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 fn main() -> ! {
     // Peripherals
     let mut pac = pac::Peripherals::take().unwrap();
-    // Watchdog driver needed for clock setup
+    // Watchdog driver needed for clock setup (and task supervision afterwards)
     let mut watchdog = hal::watchdog::Watchdog::new(pac.WATCHDOG);
 
     // Configure the clocks
@@ -68,13 +85,22 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    let mut display = Display::new(display::pins::Pins {
-        output_disable: pins.gpio13.into(),
-        serial_data: pins.gpio11.into(),
-        clock: pins.gpio10.into(),
-        latch: pins.gpio12.into(),
-        address: [pins.gpio16.into(), pins.gpio18.into(), pins.gpio22.into()],
-    });
+    // PWM for the buzzer
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+
+    let mut display = Display::new(
+        display::pins::Pins {
+            serial_data: pins.gpio11.into(),
+            clock: pins.gpio10.into(),
+            latch: pins.gpio12.into(),
+            address: [pins.gpio16.into(), pins.gpio18.into(), pins.gpio22.into()],
+        },
+        pins.gpio13.into_push_pull_output(),
+        pac.PIO0,
+        pac.DMA,
+        &mut pac.RESETS,
+        clocks.system_clock.freq(),
+    );
 
     // Pins for I2C
     let sda_pin = pins.gpio6.into_mode::<hal::gpio::FunctionI2C>();
@@ -102,16 +128,50 @@ fn main() -> ! {
     // UART RX (characters reveived by RP2040) on pin 2 (GPIO1)
     let _rx_pin = pins.gpio1.into_mode::<hal::gpio::FunctionUart>();
 
+    // Report the cause of the last reset if it was a crash
+    if let Some(record) = crash::take() {
+        write!(uart, "\r\nLast reset caused by {}", record).unwrap();
+    } else if crash::watchdog_timeout_occurred() {
+        write!(uart, "\r\nLast reset caused by watchdog timeout\r\n").unwrap();
+    }
+
+    // The RTC is shared between the animation task (reading, alarms) and the CLI task (setting)
+    // (the internal RTC keeps the time if an external one fails later on)
+    let mut rtc = rtc::init(i2c, pac.RTC, clocks.rtc_clock, &mut pac.RESETS);
+    // The colon keeps being shown steadily if the square wave cannot be enabled
+    sqw::start(&mut rtc, pins.gpio3.into_pull_up_input()).unwrap_or(());
+    let rtc = &*freertos::leak(freertos::Mutex::new(rtc));
+
+    // The settings are changed by the CLI task and applied by the animation task
+    let settings = settings::store::load(&XipFlash).unwrap_or_default();
+    let settings = &*freertos::leak(freertos::Mutex::new(settings));
+
+    buzzer::start(pwm_slices.pwm7, pins.gpio14, clocks.system_clock.freq());
+
+    buttons::start(buttons::ButtonPins {
+        set: pins.gpio15.into_pull_up_input(),
+        up: pins.gpio17.into_pull_up_input(),
+        down: pins.gpio2.into_pull_up_input(),
+    });
+
+    // Photoresistor for the automatic brightness
+    let light_sensor = LightSensor::new(
+        hal::adc::Adc::new(pac.ADC, &mut pac.RESETS),
+        pins.gpio26.into_floating_input(),
+    );
+
+    let animation_check_in = supervisor::register("AnimationTask", Milliseconds(2000));
+
     freertos::create_task(
         move || {
-            let mut rtc = Ds323x::new_ds3231(i2c);
-
-            let text_bitmap = TextBitmap::from_str("Hello world!").unwrap();
-            let mut display_fsm = DisplayFsm::new(&text_bitmap, &mut rtc);
+            let mut display_fsm = DisplayFsm::new(Board::new(settings, rtc, light_sensor));
 
+            // Fixed rate, including the time waiting for the display to show each step
+            let mut step_delay = freertos::PeriodicDelay::new(display_fsm::STEP_PERIOD);
             loop {
                 display_fsm.next_step(&mut display);
-                freertos::delay(Milliseconds(120));
+                animation_check_in.check_in();
+                step_delay.wait();
             }
         },
         &freertos::TaskParameters {
@@ -121,9 +181,11 @@ fn main() -> ! {
         },
     );
 
+    let cli_check_in = supervisor::register("CliTask", Milliseconds(2000));
     freertos::create_task(
         move || {
-            cli::run(&mut uart);
+            let context = cli::Context { rtc, settings };
+            cli::run(&mut uart, &context, &cli_check_in);
         },
         &freertos::TaskParameters {
             name: "CliTask",
@@ -132,134 +194,22 @@ fn main() -> ! {
         },
     );
 
-    freertos::start_scheduler();
-}
-
-enum DisplayFsmState {
-    Time,
-    Text,
-}
+    supervisor::start(watchdog, SUPERVISOR_TASK_PRIORITY);
 
-#[derive(PartialEq)]
-enum DisplayFsmStateResult {
-    Continue,
-    Done,
+    freertos::start_scheduler();
 }
 
-struct DisplayFsm<'a, 'b, RtccError> {
-    text_bitmap: &'a TextBitmap,
-    rtcc: &'b mut dyn ds323x::Rtcc<Error = RtccError>,
-    state: DisplayFsmState,
-    step: u64,
+/// The DMA interrupt signals the start of each refresh cycle of the display.
+#[interrupt]
+fn DMA_IRQ_0() {
+    cortex_m::interrupt::free(display::handle_interrupt);
 }
 
-impl<'a, 'b, RtccError> DisplayFsm<'a, 'b, RtccError> {
-    fn new(text_bitmap: &'a TextBitmap, rtcc: &'b mut dyn ds323x::Rtcc<Error = RtccError>) -> Self {
-        Self {
-            text_bitmap,
-            rtcc,
-            state: DisplayFsmState::Time,
-            step: 0,
-        }
-    }
-
-    fn next_step(&mut self, display: &mut Display) {
-        match self.state {
-            DisplayFsmState::Time => {
-                if self.update_time(display, self.step) == DisplayFsmStateResult::Continue {
-                    self.step += 1;
-                } else {
-                    self.state = DisplayFsmState::Text;
-                    self.step = 0;
-                }
-            }
-            DisplayFsmState::Text => {
-                if self.update_text(display, self.step) == DisplayFsmStateResult::Continue {
-                    self.step += 1;
-                } else {
-                    self.state = DisplayFsmState::Time;
-                    self.step = 0;
-                }
-            }
-        }
-    }
-
-    fn update_time(&mut self, display: &mut Display, step: u64) -> DisplayFsmStateResult {
-        if let Some((hours, minutes)) = self.get_hours_and_minutes() {
-            let mut bitmap = TextBitmap::new();
-
-            // Hours
-            if hours >= 10 {
-                bitmap.append_char((0x30 + hours / 10) as char).unwrap();
-            } else {
-                bitmap.append_char(' ').unwrap();
-            }
-            bitmap.append_char((0x30 + hours % 10) as char).unwrap();
-
-            // Separator
-            bitmap.append_char(':').unwrap();
-
-            // Seconds
-            bitmap.append_char((0x30 + minutes / 10) as char).unwrap();
-            bitmap.append_char((0x30 + minutes % 10) as char).unwrap();
-
-            let bitmap_segment = bitmap.segment(0, DOT_MATRIX_WIDTH);
-            let bitmap_data_u32 = bitmap_segment.data.map(|x| x as u32);
-            display.modify_data(|x| x.set_dot_matrix(&bitmap_data_u32));
-        } else {
-            // Show nothing of communication with RTC fails.
-            display.modify_data(|x| x.clear());
-        }
-
-        if step < 40 {
-            DisplayFsmStateResult::Continue
-        } else {
-            DisplayFsmStateResult::Done
-        }
-    }
-
-    fn update_text(&mut self, display: &mut Display, step: u64) -> DisplayFsmStateResult {
-        let bitmap_offset_min: isize = -(DOT_MATRIX_WIDTH as isize);
-        let bitmap_offset_max: isize = self.text_bitmap.width as isize;
-
-        let bitmap_offset = bitmap_offset_min + (step as isize);
-        let bitmap_segment = self.text_bitmap.segment(bitmap_offset, DOT_MATRIX_WIDTH);
-        let bitmap_data_u32 = bitmap_segment.data.map(|x| x as u32);
-        display.modify_data(|x| x.set_dot_matrix(&bitmap_data_u32));
-
-        if bitmap_offset < bitmap_offset_max {
-            DisplayFsmStateResult::Continue
-        } else {
-            DisplayFsmStateResult::Done
-        }
-    }
-
-    fn get_hours_and_minutes(&mut self) -> Option<(u8, u8)> {
-        let hours;
-        let minutes;
-
-        if let Ok(value) = self.rtcc.get_hours() {
-            match value {
-                Hours::AM(x) => {
-                    hours = x;
-                }
-                Hours::PM(x) => {
-                    hours = x;
-                }
-                Hours::H24(x) => {
-                    hours = x;
-                }
-            }
-        } else {
-            return None;
-        }
-
-        if let Ok(value) = self.rtcc.get_minutes() {
-            minutes = value;
-        } else {
-            return None;
-        }
-
-        Some((hours, minutes))
-    }
+/// The GPIO interrupt is shared by the square wave of the RTC and the buttons.
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    cortex_m::interrupt::free(|cs| {
+        sqw::handle_interrupt(cs);
+        buttons::handle_interrupt(cs);
+    });
 }
@@ -0,0 +1,114 @@
+//! DS3231 of the "Pico Clock Green" kit.
+//!
+//! Alarm1 is used for the next due alarm, Alarm2 for snoozing. The alarm flags are polled (the
+//! INT/SQW pin outputs the square wave instead).
+
+use super::{I2cBus, RtcError, TimeSource};
+
+use pico_clock_core::rtc::AlarmSlot;
+
+use ds323x::{
+    Alarm1Matching, Alarm2Matching, Datelike, DayAlarm2, Ds323x, Hours, NaiveDateTime, Rtcc,
+    SqWFreq, Timelike, WeekdayAlarm1,
+};
+
+type Driver = Ds323x<ds323x::interface::I2cInterface<I2cBus>, ds323x::ic::DS3231>;
+
+pub struct Ds3231Rtc {
+    driver: Driver,
+}
+
+impl Ds3231Rtc {
+    pub fn new(i2c: I2cBus) -> Self {
+        Self {
+            driver: Ds323x::new_ds3231(i2c),
+        }
+    }
+}
+
+impl TimeSource for Ds3231Rtc {
+    fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError> {
+        self.driver.get_datetime().map_err(rtc_error)
+    }
+
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        // Always in 24-hour mode
+        self.driver.set_datetime(datetime).map_err(rtc_error)
+    }
+
+    fn get_temperature(&mut self) -> Result<f32, RtcError> {
+        self.driver.get_temperature().map_err(rtc_error)
+    }
+
+    fn set_alarm(&mut self, slot: AlarmSlot, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        let hour = Hours::H24(datetime.hour() as u8);
+        self.clear_alarm(slot)?;
+
+        let result = match slot {
+            AlarmSlot::Next => self.driver.set_alarm1_weekday(
+                WeekdayAlarm1 {
+                    // Same numbering as used by the driver when setting the date
+                    weekday: datetime.weekday().number_from_sunday() as u8,
+                    hour,
+                    minute: datetime.minute() as u8,
+                    second: 0,
+                },
+                Alarm1Matching::AllMatch,
+            ),
+            AlarmSlot::Snooze => self.driver.set_alarm2_day(
+                DayAlarm2 {
+                    day: 1, // Not used
+                    hour,
+                    minute: datetime.minute() as u8,
+                },
+                Alarm2Matching::HoursAndMinutesMatch,
+            ),
+        };
+        result.map_err(rtc_error)
+    }
+
+    fn has_alarm_matched(&mut self, slot: AlarmSlot) -> Result<bool, RtcError> {
+        let result = match slot {
+            AlarmSlot::Next => self.driver.has_alarm1_matched(),
+            AlarmSlot::Snooze => self.driver.has_alarm2_matched(),
+        };
+        result.map_err(rtc_error)
+    }
+
+    fn clear_alarm(&mut self, slot: AlarmSlot) -> Result<(), RtcError> {
+        let result = match slot {
+            AlarmSlot::Next => self.driver.clear_alarm1_matched_flag(),
+            AlarmSlot::Snooze => self.driver.clear_alarm2_matched_flag(),
+        };
+        result.map_err(rtc_error)
+    }
+
+    fn get_aging_offset(&mut self) -> Result<i8, RtcError> {
+        self.driver.get_aging_offset().map_err(rtc_error)
+    }
+
+    /// The offset takes effect with the next temperature conversion, which is started right
+    /// away.
+    fn set_aging_offset(&mut self, offset: i8) -> Result<(), RtcError> {
+        self.driver.set_aging_offset(offset).map_err(rtc_error)?;
+        self.driver.convert_temperature().map_err(rtc_error)?;
+        Ok(())
+    }
+
+    fn enable_square_wave(&mut self) -> Result<(), RtcError> {
+        self.driver
+            .set_square_wave_frequency(SqWFreq::_1Hz)
+            .map_err(rtc_error)?;
+        self.driver
+            .use_int_sqw_output_as_square_wave()
+            .map_err(rtc_error)?;
+        Ok(())
+    }
+}
+
+fn rtc_error<E>(error: ds323x::Error<E, ()>) -> RtcError {
+    match error {
+        ds323x::Error::Comm(_) => RtcError::Bus,
+        _ => RtcError::InvalidData,
+    }
+}
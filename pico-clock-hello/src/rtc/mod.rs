@@ -0,0 +1,154 @@
+//! Real time clock, selected by a cargo feature:
+//!
+//! - 'rtc-ds3231': DS3231 of the "Pico Clock Green" kit (connected to I2C1), the default
+//! - 'rtc-pcf8563': PCF8563 connected to I2C1
+//! - 'rtc-rp2040': Internal RTC of the RP2040 (not battery backed, the time must be set after
+//!   each reset)
+//!
+//! If an external RTC cannot be accessed, the time is taken from the internal RTC of the RP2040.
+
+#[cfg(feature = "rtc-ds3231")]
+pub mod ds3231;
+#[cfg(feature = "rtc-pcf8563")]
+pub mod pcf8563;
+#[cfg(feature = "rtc-rp2040")]
+pub mod rp2040;
+
+pub use pico_clock_core::rtc::{
+    get_local_datetime, set_local_datetime, HourMode, RtcError, TimeSource,
+};
+
+use crate::internal_rtc;
+
+use pico::hal;
+use pico::hal::clocks::RtcClock;
+use pico::hal::pac;
+
+#[cfg(not(feature = "rtc-rp2040"))]
+use ds323x::NaiveDateTime;
+#[cfg(not(feature = "rtc-rp2040"))]
+use pico_clock_core::rtc::AlarmSlot;
+
+use hal::gpio::{bank0, FunctionI2C, Pin};
+
+#[cfg(not(any(
+    feature = "rtc-ds3231",
+    feature = "rtc-pcf8563",
+    feature = "rtc-rp2040"
+)))]
+compile_error!("One of the features 'rtc-ds3231', 'rtc-pcf8563' or 'rtc-rp2040' must be enabled");
+
+#[cfg(any(
+    all(feature = "rtc-ds3231", feature = "rtc-pcf8563"),
+    all(feature = "rtc-ds3231", feature = "rtc-rp2040"),
+    all(feature = "rtc-pcf8563", feature = "rtc-rp2040")
+))]
+compile_error!("Only one RTC feature can be enabled (use '--no-default-features' for others)");
+
+#[cfg(feature = "rtc-ds3231")]
+pub type Rtc = WithFallback<ds3231::Ds3231Rtc>;
+#[cfg(feature = "rtc-pcf8563")]
+pub type Rtc = WithFallback<pcf8563::Pcf8563Rtc>;
+#[cfg(feature = "rtc-rp2040")]
+pub type Rtc = rp2040::Rp2040Rtc;
+
+/// Name of the selected RTC (as reported by the 'status' command).
+#[cfg(feature = "rtc-ds3231")]
+pub const NAME: &str = "ds3231";
+#[cfg(feature = "rtc-pcf8563")]
+pub const NAME: &str = "pcf8563";
+#[cfg(feature = "rtc-rp2040")]
+pub const NAME: &str = "rp2040";
+
+pub type I2cBus = hal::i2c::I2C<
+    hal::pac::I2C1,
+    (
+        Pin<bank0::Gpio6, FunctionI2C>,
+        Pin<bank0::Gpio7, FunctionI2C>,
+    ),
+>;
+
+/// External RTC backed by the internal RTC of the RP2040, which keeps the time if the external
+/// one cannot be accessed.
+#[cfg(not(feature = "rtc-rp2040"))]
+pub struct WithFallback<T: TimeSource> {
+    rtc: T,
+}
+
+#[cfg(not(feature = "rtc-rp2040"))]
+impl<T: TimeSource> WithFallback<T> {
+    /// The internal RTC must have been started.
+    pub fn new(rtc: T) -> Self {
+        Self { rtc }
+    }
+}
+
+#[cfg(not(feature = "rtc-rp2040"))]
+impl<T: TimeSource> TimeSource for WithFallback<T> {
+    fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError> {
+        match self.rtc.get_datetime() {
+            Ok(datetime) => {
+                internal_rtc::reconcile(&datetime);
+                Ok(datetime)
+            }
+            Err(x) => internal_rtc::fallback_datetime().ok_or(x),
+        }
+    }
+
+    /// Also sets the internal RTC (even if the external one cannot be accessed).
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        internal_rtc::set_datetime(datetime).unwrap_or(());
+        self.rtc.set_datetime(datetime)
+    }
+
+    fn get_temperature(&mut self) -> Result<f32, RtcError> {
+        self.rtc.get_temperature()
+    }
+
+    fn set_alarm(&mut self, slot: AlarmSlot, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        self.rtc.set_alarm(slot, datetime)
+    }
+
+    fn has_alarm_matched(&mut self, slot: AlarmSlot) -> Result<bool, RtcError> {
+        self.rtc.has_alarm_matched(slot)
+    }
+
+    fn clear_alarm(&mut self, slot: AlarmSlot) -> Result<(), RtcError> {
+        self.rtc.clear_alarm(slot)
+    }
+
+    fn get_aging_offset(&mut self) -> Result<i8, RtcError> {
+        self.rtc.get_aging_offset()
+    }
+
+    fn set_aging_offset(&mut self, offset: i8) -> Result<(), RtcError> {
+        self.rtc.set_aging_offset(offset)
+    }
+
+    fn enable_square_wave(&mut self) -> Result<(), RtcError> {
+        self.rtc.enable_square_wave()
+    }
+}
+
+/// Creates the selected RTC and starts the internal RTC of the RP2040.
+pub fn init(i2c: I2cBus, internal: pac::RTC, clock: RtcClock, resets: &mut pac::RESETS) -> Rtc {
+    #[cfg(feature = "rtc-ds3231")]
+    let mut rtc = ds3231::Ds3231Rtc::new(i2c);
+    #[cfg(feature = "rtc-pcf8563")]
+    let mut rtc = pcf8563::Pcf8563Rtc::new(i2c);
+
+    #[cfg(not(feature = "rtc-rp2040"))]
+    {
+        // Seeded with the time of the external RTC
+        internal_rtc::start(internal, clock, resets, rtc.get_datetime().ok());
+        WithFallback::new(rtc)
+    }
+
+    #[cfg(feature = "rtc-rp2040")]
+    {
+        // The bus is not used
+        let _ = i2c;
+        internal_rtc::start(internal, clock, resets, None);
+        rp2040::Rp2040Rtc
+    }
+}
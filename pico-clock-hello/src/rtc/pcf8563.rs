@@ -0,0 +1,145 @@
+//! PCF8563 (used by other boards instead of the DS3231), accessed by its registers.
+//!
+//! Its single alarm is used for the next due alarm, snoozing is done by software. It has no
+//! temperature sensor, no aging offset and no square wave suitable for the 'sqw' module.
+
+use super::{I2cBus, RtcError, TimeSource};
+
+use pico_clock_core::rtc::AlarmSlot;
+
+use ds323x::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+const ADDRESS: u8 = 0x51;
+
+const CONTROL_STATUS_2: u8 = 0x01;
+const VL_SECONDS: u8 = 0x02;
+const MINUTE_ALARM: u8 = 0x09;
+
+/// Alarm flag in 'CONTROL_STATUS_2'.
+const AF: u8 = 1 << 3;
+/// Timer flag in 'CONTROL_STATUS_2' (writing 1 keeps it unchanged).
+const TF: u8 = 1 << 2;
+/// Clock integrity not guaranteed (in 'VL_SECONDS').
+const VL: u8 = 1 << 7;
+/// Disables the comparison of an alarm register.
+const AE: u8 = 1 << 7;
+
+pub struct Pcf8563Rtc {
+    i2c: I2cBus,
+}
+
+impl Pcf8563Rtc {
+    pub fn new(i2c: I2cBus) -> Self {
+        Self { i2c }
+    }
+
+    fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), RtcError> {
+        self.i2c
+            .write_read(ADDRESS, &[register], data)
+            .map_err(|_| RtcError::Bus)
+    }
+
+    /// Writes up to 7 consecutive registers.
+    fn write_registers(&mut self, register: u8, data: &[u8]) -> Result<(), RtcError> {
+        let mut buffer = [0u8; 8];
+        buffer[0] = register;
+        buffer[1..=data.len()].copy_from_slice(data);
+        self.i2c
+            .write(ADDRESS, &buffer[..=data.len()])
+            .map_err(|_| RtcError::Bus)
+    }
+}
+
+impl TimeSource for Pcf8563Rtc {
+    fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError> {
+        let mut data = [0u8; 7];
+        self.read_registers(VL_SECONDS, &mut data)?;
+
+        // E.g. after the backup battery has been empty
+        if data[0] & VL != 0 {
+            return Err(RtcError::InvalidData);
+        }
+
+        // The century bit is ignored (only 2000 to 2099 are supported like by the DS3231)
+        NaiveDate::from_ymd_opt(
+            2000 + from_bcd(data[6]) as i32,
+            from_bcd(data[5] & 0x1F),
+            from_bcd(data[3] & 0x3F),
+        )
+        .and_then(|x| {
+            x.and_hms_opt(
+                from_bcd(data[2] & 0x3F),
+                from_bcd(data[1] & 0x7F),
+                from_bcd(data[0] & 0x7F),
+            )
+        })
+        .ok_or(RtcError::InvalidData)
+    }
+
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        if !(2000..=2099).contains(&datetime.year()) {
+            return Err(RtcError::InvalidData);
+        }
+
+        // Also clears the 'VL' flag
+        self.write_registers(
+            VL_SECONDS,
+            &[
+                to_bcd(datetime.second()),
+                to_bcd(datetime.minute()),
+                to_bcd(datetime.hour()),
+                to_bcd(datetime.day()),
+                datetime.weekday().num_days_from_sunday() as u8,
+                to_bcd(datetime.month()),
+                to_bcd(datetime.year() as u32 - 2000),
+            ],
+        )
+    }
+
+    fn set_alarm(&mut self, slot: AlarmSlot, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        if slot != AlarmSlot::Next {
+            return Err(RtcError::Unsupported);
+        }
+
+        self.clear_alarm(slot)?;
+        // Minute, hour and day are compared (not the weekday)
+        self.write_registers(
+            MINUTE_ALARM,
+            &[
+                to_bcd(datetime.minute()),
+                to_bcd(datetime.hour()),
+                to_bcd(datetime.day()),
+                AE,
+            ],
+        )
+    }
+
+    fn has_alarm_matched(&mut self, slot: AlarmSlot) -> Result<bool, RtcError> {
+        if slot != AlarmSlot::Next {
+            return Err(RtcError::Unsupported);
+        }
+
+        let mut control = [0u8];
+        self.read_registers(CONTROL_STATUS_2, &mut control)?;
+        Ok(control[0] & AF != 0)
+    }
+
+    fn clear_alarm(&mut self, slot: AlarmSlot) -> Result<(), RtcError> {
+        if slot != AlarmSlot::Next {
+            return Err(RtcError::Unsupported);
+        }
+
+        let mut control = [0u8];
+        self.read_registers(CONTROL_STATUS_2, &mut control)?;
+        self.write_registers(CONTROL_STATUS_2, &[(control[0] & !AF) | TF])
+    }
+}
+
+fn from_bcd(value: u8) -> u32 {
+    (value >> 4) as u32 * 10 + (value & 0x0F) as u32
+}
+
+fn to_bcd(value: u32) -> u8 {
+    (((value / 10) << 4) | (value % 10)) as u8
+}
@@ -0,0 +1,21 @@
+//! Internal RTC of the RP2040, for boards without an external RTC.
+//!
+//! It is not battery backed, so the time is invalid after each reset until it has been set
+//! (e.g. by the host tool). The alarms are checked by software.
+
+use super::{RtcError, TimeSource};
+use crate::internal_rtc;
+
+use ds323x::NaiveDateTime;
+
+pub struct Rp2040Rtc;
+
+impl TimeSource for Rp2040Rtc {
+    fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError> {
+        internal_rtc::get_datetime().ok_or(RtcError::InvalidData)
+    }
+
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        internal_rtc::set_datetime(datetime)
+    }
+}
@@ -0,0 +1,98 @@
+//! 1 Hz square-wave output of the RTC (INT/SQW of the DS3231 connected to GPIO3), used to blink
+//! the colon in sync with the seconds of the RTC and to measure its deviation with sub-second
+//! resolution.
+//!
+//! The RTC increments the seconds on the falling edge. The output is open drain and low for the
+//! first half of each second. Without a square wave, the colon is shown permanently.
+
+use crate::freertos;
+use crate::rtc::{Rtc, RtcError, TimeSource};
+
+use pico::hal;
+use pico::hal::gpio::{bank0, Interrupt, Pin, PullUpInput};
+use pico::hal::pac;
+
+use embedded_time::duration::Milliseconds;
+
+// Interrupt handler concurrency
+use core::cell::{Cell, RefCell};
+use cortex_m::interrupt::{CriticalSection, Mutex};
+
+pub type SqwPin = Pin<bank0::Gpio3, PullUpInput>;
+
+/// Time after which the square wave is considered not to work anymore (e.g. if the RTC has
+/// been reconfigured or is not connected).
+const EDGE_TIMEOUT: Milliseconds = Milliseconds(1500);
+
+#[derive(Clone, Copy)]
+struct SqwState {
+    /// Tick count of the last edge (or 'None' if no edge has been seen yet).
+    last_edge: Option<Milliseconds>,
+    /// Level after the last edge.
+    high: bool,
+}
+
+static SQW_PIN: Mutex<RefCell<Option<SqwPin>>> = Mutex::new(RefCell::new(None));
+static SQW_STATE: Mutex<Cell<SqwState>> = Mutex::new(Cell::new(SqwState {
+    last_edge: None,
+    high: false,
+}));
+
+/// Configures the RTC to output the 1 Hz square wave and enables the interrupt on both edges.
+pub fn start(rtc: &mut Rtc, pin: SqwPin) -> Result<(), RtcError> {
+    rtc.enable_square_wave()?;
+
+    pin.set_interrupt_enabled(Interrupt::EdgeLow, true);
+    pin.set_interrupt_enabled(Interrupt::EdgeHigh, true);
+    cortex_m::interrupt::free(|cs| SQW_PIN.borrow(cs).replace(Some(pin)));
+
+    unsafe {
+        pac::NVIC::unmask(hal::pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    Ok(())
+}
+
+/// Checks whether the colon shall be shown: During the first half of each second, or always if
+/// the square wave does not work.
+pub fn colon_visible() -> bool {
+    let state = cortex_m::interrupt::free(|cs| SQW_STATE.borrow(cs).get());
+
+    match state.last_edge {
+        Some(last_edge) => {
+            let elapsed = freertos::tick_count().0.wrapping_sub(last_edge.0);
+            !state.high || elapsed > EDGE_TIMEOUT.0
+        }
+        None => true,
+    }
+}
+
+/// Gets the milliseconds elapsed since the RTC has incremented the seconds ('None' if the
+/// square wave does not work).
+pub fn millis_since_second() -> Option<u32> {
+    let state = cortex_m::interrupt::free(|cs| SQW_STATE.borrow(cs).get());
+
+    let elapsed = freertos::tick_count().0.wrapping_sub(state.last_edge?.0);
+    if elapsed > EDGE_TIMEOUT.0 {
+        return None;
+    }
+
+    // The rising edge is in the middle of the second
+    let millis = if state.high { elapsed + 500 } else { elapsed };
+    Some(millis.min(999))
+}
+
+/// Records the edges of the square wave (called by the GPIO interrupt handler).
+pub fn handle_interrupt(cs: &CriticalSection) {
+    if let Some(pin) = SQW_PIN.borrow(cs).borrow_mut().as_mut() {
+        for (interrupt, high) in [(Interrupt::EdgeLow, false), (Interrupt::EdgeHigh, true)] {
+            if pin.interrupt_status(interrupt) {
+                pin.clear_interrupt(interrupt);
+                SQW_STATE.borrow(cs).set(SqwState {
+                    last_edge: Some(freertos::tick_count_from_isr()),
+                    high,
+                });
+            }
+        }
+    }
+}
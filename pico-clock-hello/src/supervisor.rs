@@ -0,0 +1,121 @@
+//! Supervision of tasks using the hardware watchdog.
+//!
+//! Each supervised task has to check in periodically within its own deadline. The supervisor
+//! task keeps feeding the watchdog as long as all of them do. If a task misses its deadline
+//! (e.g. because it hangs in an I2C transfer), the stalled task is recorded in the crash record
+//! and the chip is reset. If the supervisor task itself hangs, the watchdog resets the chip.
+
+use crate::crash::{self, CrashReason, CrashRecord};
+use crate::freertos::{self, TaskName};
+
+use embedded_hal::watchdog::{Watchdog as _, WatchdogEnable as _};
+use pico::hal;
+
+// Time
+use embedded_time::duration::{Microseconds, Milliseconds};
+
+// Interrupt handler concurrency
+use core::cell::RefCell;
+use cortex_m::interrupt;
+use cortex_m::interrupt::Mutex;
+
+const MAX_SUPERVISED_TASKS: usize = 8;
+
+/// Period of the supervision (check of deadlines and feeding the watchdog).
+const SUPERVISION_PERIOD: Milliseconds = Milliseconds(100);
+
+/// Hardware watchdog timeout.
+/// Must be considerably longer than the supervision period because the supervisor task may be
+/// blocked for some time (e.g. while the scheduler is suspended for flash operations).
+const WATCHDOG_TIMEOUT: Milliseconds = Milliseconds(2000);
+
+#[derive(Clone, Copy)]
+struct SupervisedTask {
+    name: TaskName,
+    deadline: Milliseconds,
+    last_check_in: Milliseconds,
+}
+
+static SUPERVISED_TASKS: Mutex<RefCell<[Option<SupervisedTask>; MAX_SUPERVISED_TASKS]>> =
+    Mutex::new(RefCell::new([None; MAX_SUPERVISED_TASKS]));
+
+/// Handle to be used by a supervised task to check in.
+pub struct CheckIn {
+    index: usize,
+}
+
+impl CheckIn {
+    /// Signals that the task is alive. Must be called at least once per deadline.
+    pub fn check_in(&self) {
+        let now = freertos::tick_count();
+        interrupt::free(|cs| {
+            if let Some(task) = &mut SUPERVISED_TASKS.borrow(cs).borrow_mut()[self.index] {
+                task.last_check_in = now;
+            }
+        });
+    }
+}
+
+/// Registers a task to be supervised.
+///
+/// Should be called before the task is created, so the task is supervised from the very
+/// beginning. The deadline starts counting at registration.
+pub fn register(name: &str, deadline: Milliseconds) -> CheckIn {
+    let now = freertos::tick_count();
+    interrupt::free(|cs| {
+        let mut tasks = SUPERVISED_TASKS.borrow(cs).borrow_mut();
+
+        // Assumption: There are never more tasks than slots (otherwise panic)
+        let index = tasks.iter().position(|x| x.is_none()).unwrap();
+        tasks[index] = Some(SupervisedTask {
+            name: TaskName::from_bytes(name.as_bytes()),
+            deadline,
+            last_check_in: now,
+        });
+
+        CheckIn { index }
+    })
+}
+
+/// Enables the watchdog and starts the supervisor task.
+pub fn start(mut watchdog: hal::watchdog::Watchdog, priority: u32) {
+    // Don't reset while halted by a debugger
+    watchdog.pause_on_debug(true);
+    watchdog.start(Microseconds(WATCHDOG_TIMEOUT.0 * 1000));
+
+    freertos::create_task(
+        move || loop {
+            if let Some(name) = find_stalled_task() {
+                let record = CrashRecord {
+                    reason: CrashReason::TaskStalled,
+                    task_name: Some(name),
+                    registers: None,
+                };
+                crash::store(&record);
+                crash::write_to_uart(&record);
+                crash::reset();
+            }
+
+            watchdog.feed();
+            freertos::delay(SUPERVISION_PERIOD);
+        },
+        &freertos::TaskParameters {
+            name: "SupervisorTask",
+            stack_depth: 512, // Is actually 2048 bytes because portSTACK_TYPE is uint32_t
+            priority,
+        },
+    );
+}
+
+fn find_stalled_task() -> Option<TaskName> {
+    let now = freertos::tick_count();
+    interrupt::free(|cs| {
+        SUPERVISED_TASKS
+            .borrow(cs)
+            .borrow()
+            .iter()
+            .flatten()
+            .find(|x| Milliseconds(now.0.wrapping_sub(x.last_check_in.0)) > x.deadline)
+            .map(|x| x.name)
+    })
+}
@@ -0,0 +1,118 @@
+//! Host side of the serial CLI of the "Pico Clock Green" firmware ('pico-clock-hello').
+//!
+//! The firmware echoes every character of a command line, answers with a new line followed by
+//! the output of the command and finishes with the prompt '> '.
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Baud rate of the UART of the firmware (8N1).
+pub const BAUD_RATE: u32 = 115_200;
+
+/// Time to transmit a character (10 bits including start and stop bit).
+pub const CHAR_TIME: Duration = Duration::from_micros(10 * 1_000_000 / BAUD_RATE as u64);
+
+const PROMPT: &[u8] = b"> ";
+
+/// Number of round trips measured before synchronizing (the shortest one is taken).
+const ROUND_TRIPS: usize = 3;
+
+pub struct Clock<P: Read + Write> {
+    port: P,
+}
+
+impl<P: Read + Write> Clock<P> {
+    /// Takes a port with a read timeout (otherwise a missing response blocks forever).
+    pub fn new(port: P) -> Self {
+        Self { port }
+    }
+
+    /// Sends an empty line to skip any pending output (e.g. the reset reason after a reset) and
+    /// to make sure the prompt has been printed.
+    pub fn connect(&mut self) -> io::Result<()> {
+        self.command("").map(|_| ())
+    }
+
+    /// Sends a command line and returns its output (without the echo and the prompt).
+    pub fn command(&mut self, line: &str) -> io::Result<String> {
+        if line.contains(['\r', '\n']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Command must be a single line",
+            ));
+        }
+
+        self.port.write_all(line.as_bytes())?;
+        self.port.write_all(b"\r")?;
+        self.port.flush()?;
+
+        let response = self.read_until_prompt()?;
+        // The output starts after the echoed line
+        let output = match response.find("\r\n") {
+            Some(x) => &response[x + 2..],
+            None => "",
+        };
+        Ok(output.to_string())
+    }
+
+    /// Measures the time from sending an empty line until the prompt has been received.
+    pub fn round_trip(&mut self) -> io::Result<Duration> {
+        let start = std::time::Instant::now();
+        self.command("")?;
+        Ok(start.elapsed())
+    }
+
+    /// Sets the clock to the time of the host and returns the output of the 'sync' command.
+    ///
+    /// The timestamp sent is ahead of the host clock by the estimated time until the firmware
+    /// has received the line: Half of the shortest round trip of an empty line plus the time to
+    /// transmit the command.
+    pub fn sync(&mut self) -> io::Result<String> {
+        let mut round_trip = Duration::MAX;
+        for _ in 0..ROUND_TRIPS {
+            round_trip = round_trip.min(self.round_trip()?);
+        }
+
+        // The length of the timestamp does not change for centuries
+        let line_len = "sync ".len() + 13;
+        let delay = round_trip / 2 + CHAR_TIME * line_len as u32;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?;
+        let millis = (now + delay).as_millis();
+        self.command(&format!("sync {}", millis))
+    }
+
+    /// Reads the 'key=value' lines printed by the 'status' command.
+    pub fn status(&mut self) -> io::Result<Vec<(String, String)>> {
+        let output = self.command("status")?;
+        Ok(parse_status(&output))
+    }
+
+    fn read_until_prompt(&mut self) -> io::Result<String> {
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 64];
+
+        while !response.ends_with(PROMPT) {
+            let len = self.port.read(&mut buffer)?;
+            if len == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            response.extend_from_slice(&buffer[..len]);
+        }
+
+        response.truncate(response.len() - PROMPT.len());
+        // The firmware only sends ASCII characters
+        Ok(String::from_utf8_lossy(&response).into_owned())
+    }
+}
+
+/// Parses 'key=value' lines (other lines are ignored).
+pub fn parse_status(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|x| x.trim_end().split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
@@ -0,0 +1,96 @@
+//! Command line tool for setting the "Pico Clock Green" over its serial port.
+
+use pico_clock_host::{Clock, BAUD_RATE};
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use serialport::{ClearBuffer, SerialPort};
+
+/// Used if neither '--port' nor the environment variable 'PICO_CLOCK_PORT' is given.
+const DEFAULT_PORT: &str = "/dev/ttyUSB0";
+
+/// Longer than the longest command takes ('sync' waits up to a second).
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+const USAGE: &str = "\
+Usage: pico-clock [--port <path>] <command>
+
+Commands:
+  sync                 Set the clock to the time of this computer
+  status               Print the time and the main settings of the clock
+  settext [#n] <text>  Set the scrolling text n (default 1)
+  alarm <args>         Run an alarm command of the clock, e.g.
+                       'alarm add 07:00 weekdays Wake up', 'alarm list', 'alarm del 1'
+
+The port can also be given by the environment variable PICO_CLOCK_PORT.
+";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut port_name =
+        std::env::var("PICO_CLOCK_PORT").unwrap_or_else(|_| DEFAULT_PORT.to_string());
+    if args.first().map(String::as_str) == Some("--port") {
+        if args.len() < 2 {
+            eprint!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+        port_name = args.remove(1);
+        args.remove(0);
+    }
+
+    let line = match command_line(&args) {
+        Some(x) => x,
+        None => {
+            eprint!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let port = match open_port(&port_name) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("Failed to open {}: {}", port_name, x);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(Clock::new(port), &line) {
+        Ok(output) => {
+            print!("{}", output.replace("\r\n", "\n"));
+            ExitCode::SUCCESS
+        }
+        Err(x) => {
+            eprintln!("Communication with the clock failed: {}", x);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Builds the command line sent to the clock ('None' if the arguments are invalid). 'sync' is
+/// handled separately because the timestamp is only taken right before sending.
+fn command_line(args: &[String]) -> Option<String> {
+    let (command, rest) = args.split_first()?;
+    match command.as_str() {
+        "sync" | "status" if rest.is_empty() => Some(command.clone()),
+        "settext" | "alarm" if !rest.is_empty() => Some(args.join(" ")),
+        _ => None,
+    }
+}
+
+fn open_port(name: &str) -> serialport::Result<Box<dyn SerialPort>> {
+    let port = serialport::new(name, BAUD_RATE).timeout(TIMEOUT).open()?;
+    // Output printed before (e.g. after a reset) would be taken as response
+    port.clear(ClearBuffer::Input)?;
+    Ok(port)
+}
+
+fn run(mut clock: Clock<Box<dyn SerialPort>>, line: &str) -> std::io::Result<String> {
+    clock.connect()?;
+    if line == "sync" {
+        clock.sync()
+    } else {
+        clock.command(line)
+    }
+}
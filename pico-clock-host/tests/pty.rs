@@ -0,0 +1,91 @@
+//! Talks to a minimal stand-in of the firmware CLI over a pseudo terminal.
+
+use pico_clock_host::{Clock, CHAR_TIME};
+
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serialport::{SerialPort, TTYPort};
+
+/// Echoes the characters like the firmware and answers the commands used by the tests. Stops
+/// when the line 'quit' is received.
+fn fake_clock(mut port: TTYPort) {
+    let mut line = String::new();
+    let mut buffer = [0u8; 1];
+
+    loop {
+        port.read_exact(&mut buffer).unwrap();
+        let c = buffer[0];
+        if c != b'\r' {
+            line.push(c as char);
+            port.write_all(&buffer).unwrap();
+            continue;
+        }
+
+        port.write_all(b"\r\n").unwrap();
+        let output = match line.split_once(' ').unwrap_or((&line, "")) {
+            ("quit", _) => return,
+            ("", _) => String::new(),
+            ("status", _) => "utc=2024-03-31T01:00:00\r\ntz=UTC0\r\n".to_string(),
+            ("sync", millis) => format!("Synchronized {}\r\n", millis),
+            _ => "Unknown command\r\n".to_string(),
+        };
+        port.write_all(output.as_bytes()).unwrap();
+        port.write_all(b"> ").unwrap();
+        line.clear();
+    }
+}
+
+fn connect() -> (Clock<TTYPort>, thread::JoinHandle<()>) {
+    let (mut host, device) = TTYPort::pair().unwrap();
+    host.set_timeout(Duration::from_secs(2)).unwrap();
+    let device_thread = thread::spawn(move || fake_clock(device));
+
+    let mut clock = Clock::new(host);
+    clock.connect().unwrap();
+    (clock, device_thread)
+}
+
+fn disconnect(mut clock: Clock<TTYPort>, device_thread: thread::JoinHandle<()>) {
+    clock.command("quit").unwrap_err();
+    device_thread.join().unwrap();
+}
+
+#[test]
+fn command_output_excludes_echo_and_prompt() {
+    let (mut clock, device_thread) = connect();
+
+    assert_eq!(clock.command("foo").unwrap(), "Unknown command\r\n");
+    assert_eq!(
+        clock.status().unwrap(),
+        vec![
+            ("utc".to_string(), "2024-03-31T01:00:00".to_string()),
+            ("tz".to_string(), "UTC0".to_string()),
+        ]
+    );
+
+    disconnect(clock, device_thread);
+}
+
+#[test]
+fn sync_sends_compensated_host_time() {
+    let (mut clock, device_thread) = connect();
+
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let output = clock.sync().unwrap();
+    let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let millis: u128 = output
+        .trim_end()
+        .strip_prefix("Synchronized ")
+        .unwrap()
+        .parse()
+        .unwrap();
+    // Ahead of the host time by at least the transmission time of the line 'sync <13 digits>'
+    // (about 1.5 ms)
+    assert!(millis >= (before + 18 * CHAR_TIME).as_millis());
+    assert!(millis <= after.as_millis() + 100);
+
+    disconnect(clock, device_thread);
+}
@@ -0,0 +1,71 @@
+//! Simulated hardware as used by the display FSM.
+
+use crate::buzzer;
+use crate::freertos::{self, Mutex};
+use crate::internal_rtc;
+use crate::light_sensor;
+use crate::rtc::MockRtc;
+use crate::sqw;
+
+use pico_clock_core::buttons::{self, ButtonEvent};
+use pico_clock_core::hardware::Hardware;
+use pico_clock_core::melody::Melody;
+use pico_clock_core::settings::Settings;
+
+use embedded_time::duration::Milliseconds;
+
+use std::ops::DerefMut;
+
+pub struct Board<'a> {
+    settings: &'a Mutex<Settings>,
+    rtc: &'a Mutex<MockRtc>,
+}
+
+impl<'a> Board<'a> {
+    pub fn new(settings: &'a Mutex<Settings>, rtc: &'a Mutex<MockRtc>) -> Self {
+        Self { settings, rtc }
+    }
+}
+
+impl Hardware for Board<'_> {
+    type Rtc = MockRtc;
+
+    fn settings(&self) -> impl DerefMut<Target = Settings> + '_ {
+        self.settings.lock()
+    }
+
+    /// Changes are not persisted (the simulation always starts with the given settings).
+    fn save_settings(&mut self, _settings: &Settings) {}
+
+    fn rtc(&self) -> impl DerefMut<Target = MockRtc> + '_ {
+        self.rtc.lock()
+    }
+
+    fn is_rtc_fallback_active(&self) -> bool {
+        internal_rtc::is_fallback_active()
+    }
+
+    fn colon_visible(&self) -> bool {
+        sqw::colon_visible()
+    }
+
+    fn tick_count(&self) -> Milliseconds {
+        freertos::tick_count()
+    }
+
+    fn take_button_event(&mut self) -> Option<ButtonEvent> {
+        buttons::take_event()
+    }
+
+    fn read_light_level(&mut self) -> Option<u16> {
+        Some(light_sensor::level())
+    }
+
+    fn play_melody(&mut self, melody: Melody, repeat: bool) {
+        buzzer::play(melody, repeat);
+    }
+
+    fn stop_melody(&mut self) {
+        buzzer::stop();
+    }
+}
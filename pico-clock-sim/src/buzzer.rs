@@ -0,0 +1,42 @@
+//! Simulated buzzer: Keeps track of the melody being played (shown in the status line).
+
+use crate::freertos;
+
+use pico_clock_core::melody::Melody;
+
+use embedded_time::duration::Milliseconds;
+
+use std::sync::Mutex;
+
+struct Playing {
+    repeat: bool,
+    /// Tick count when the melody ends (if not repeated).
+    ends_at: Milliseconds,
+}
+
+static PLAYING: Mutex<Option<Playing>> = Mutex::new(None);
+
+/// Starts playing the melody (replacing the one being played). If 'repeat' is set, it is played
+/// until 'stop' is called.
+pub fn play(melody: Melody, repeat: bool) {
+    let duration: u32 = melody.tones().iter().map(|x| x.duration as u32).sum();
+    *PLAYING.lock().unwrap() = Some(Playing {
+        repeat,
+        ends_at: Milliseconds(freertos::tick_count().0 + duration),
+    });
+}
+
+/// Stops the melody being played (if any).
+pub fn stop() {
+    *PLAYING.lock().unwrap() = None;
+}
+
+/// Checks whether a melody is being played.
+pub fn is_playing() -> bool {
+    let now = freertos::tick_count();
+    PLAYING
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|x| x.repeat || now.0 < x.ends_at.0)
+}
@@ -0,0 +1,50 @@
+//! Simulated display: Keeps the frame presented and its brightness for rendering.
+
+use pico_clock_core::display::data::{Data, RawData, RAW_HEIGHT};
+use pico_clock_core::display::DisplayOutput;
+
+pub struct Display {
+    /// Frame being composed.
+    composed: Data,
+    /// Brightness in percent (being composed).
+    composed_brightness: u8,
+    raw_data: RawData,
+    brightness: u8,
+}
+
+impl Display {
+    pub fn new() -> Self {
+        Self {
+            composed: Data::new(),
+            composed_brightness: 100,
+            raw_data: [0; RAW_HEIGHT],
+            brightness: 100,
+        }
+    }
+
+    pub fn raw_data(&self) -> &RawData {
+        &self.raw_data
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+}
+
+impl DisplayOutput for Display {
+    fn modify_data<F>(&mut self, func: F)
+    where
+        F: FnOnce(&mut Data),
+    {
+        func(&mut self.composed);
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.composed_brightness = brightness.min(100);
+    }
+
+    fn present(&mut self) {
+        self.raw_data = self.composed.raw_data;
+        self.brightness = self.composed_brightness;
+    }
+}
@@ -0,0 +1,34 @@
+//! Simulated FreeRTOS: The tick count is the simulated time (advanced by the main loop) and the
+//! mutex is the one of the standard library.
+
+use embedded_time::duration::Milliseconds;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static TICK_COUNT: AtomicU32 = AtomicU32::new(0);
+
+pub fn tick_count() -> Milliseconds {
+    Milliseconds(TICK_COUNT.load(Ordering::Relaxed))
+}
+
+/// Advances the simulated time.
+pub fn advance(duration: Milliseconds) {
+    TICK_COUNT.fetch_add(duration.0, Ordering::Relaxed);
+}
+
+pub struct Mutex<T> {
+    inner: std::sync::Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: std::sync::Mutex::new(value),
+        }
+    }
+
+    pub fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        // Only poisoned if the simulator panicked anyway
+        self.inner.lock().unwrap()
+    }
+}
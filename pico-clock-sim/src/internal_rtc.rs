@@ -0,0 +1,14 @@
+//! Simulated failure of the external RTC (toggled from the keyboard): The time is kept (as by
+//! the internal RTC of the RP2040), but the alarms and the temperature of the mock RTC fail.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static FALLBACK_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_fallback_active() -> bool {
+    FALLBACK_ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn set_fallback_active(active: bool) {
+    FALLBACK_ACTIVE.store(active, Ordering::Relaxed);
+}
@@ -0,0 +1,15 @@
+//! Simulated light sensor, its level is changed from the keyboard.
+
+use std::sync::atomic::{AtomicU16, Ordering};
+
+pub const MAX_LIGHT_LEVEL: u16 = 4095;
+
+static LIGHT_LEVEL: AtomicU16 = AtomicU16::new(MAX_LIGHT_LEVEL / 2);
+
+pub fn level() -> u16 {
+    LIGHT_LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn set_level(level: u16) {
+    LIGHT_LEVEL.store(level.min(MAX_LIGHT_LEVEL), Ordering::Relaxed);
+}
@@ -0,0 +1,298 @@
+//! Simulator of the "Pico Clock Green" for designing the display content on the host.
+//!
+//! The display FSM of the library 'pico-clock-core' runs with simulated hardware: The display is
+//! rendered in the terminal (or written as PNG images), the buttons are operated by the keyboard
+//! and the RTC is a mock driven by the simulated time.
+
+// Simulated hardware
+mod board;
+mod buzzer;
+mod display;
+mod freertos;
+mod internal_rtc;
+mod light_sensor;
+mod rtc;
+mod sqw;
+
+mod png;
+mod render;
+
+use board::Board;
+use display::Display;
+use render::Style;
+use rtc::MockRtc;
+
+use pico_clock_core::buttons::{self, Button, ButtonEvent};
+use pico_clock_core::cli::datetime::{self, DateTimeInput};
+use pico_clock_core::display_fsm::{self, DisplayFsm};
+use pico_clock_core::rtc::{HourMode, TimeSource};
+use pico_clock_core::settings::{ScrollText, Settings};
+use pico_clock_core::timezone::TimeZone;
+
+use ds323x::NaiveDateTime;
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant, SystemTime};
+
+const USAGE: &str = "\
+Usage: pico-clock-sim [options]
+
+Options:
+  --time <YYYY-MM-DDTHH:MM:SS>  Initial time (UTC, default: the time of this computer)
+  --tz <string>                 POSIX time zone, e.g. 'CET-1CEST,M3.5.0,M10.5.0/3'
+  --text <text>                 First scrolling text
+  --hour-mode <12|24>           Hour format
+  --temperature <celsius>       Temperature measured by the RTC (default 21.5)
+  --ascii                       Plain ASCII instead of ANSI colors (frames printed one after
+                                another)
+  --steps <n>                   Simulate n steps as fast as possible (no keyboard input)
+  --png <dir>                   Write each frame as PNG image into the directory
+
+Keys in the interactive mode (each line is taken when Enter is pressed):
+  s u d    Press the button Set, Up or Down
+  S U D    Long press of the button Set, Up or Down
+  + -      Increase or decrease the ambient light
+  f        Let the external RTC fail or work again
+  p        Write the current frame as PNG image
+  q        Quit
+";
+
+/// Change of the light level per key press.
+const LIGHT_STEP: u16 = 512;
+
+struct Options {
+    time: Option<NaiveDateTime>,
+    settings: Settings,
+    temperature: f32,
+    style: Style,
+    steps: Option<u64>,
+    png_dir: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_options(&args) {
+        Ok(x) => x,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(x) => {
+            eprintln!("Simulation failed: {}", x);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        time: None,
+        settings: Settings::new(),
+        temperature: 21.5,
+        style: Style::Ansi,
+        steps: None,
+        png_dir: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--ascii" {
+            options.style = Style::Ascii;
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value of '{}'", arg))?;
+        let invalid = || format!("Invalid value of '{}': '{}'", arg, value);
+        match arg.as_str() {
+            "--time" => match datetime::parse(value) {
+                Ok(DateTimeInput::DateTime(x)) => options.time = Some(x),
+                _ => return Err(invalid()),
+            },
+            "--tz" => options.settings.time_zone = TimeZone::parse(value).ok_or_else(invalid)?,
+            "--text" => options.settings.texts[0] = ScrollText::new(value).ok_or_else(invalid)?,
+            "--hour-mode" => {
+                options.settings.hour_mode = match value.as_str() {
+                    "12" => HourMode::H12,
+                    "24" => HourMode::H24,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--temperature" => options.temperature = value.parse().map_err(|_| invalid())?,
+            "--steps" => options.steps = Some(value.parse().map_err(|_| invalid())?),
+            "--png" => options.png_dir = Some(PathBuf::from(value)),
+            _ => return Err(format!("Unknown option '{}'", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let time = match options.time {
+        Some(x) => x,
+        None => host_time()?,
+    };
+
+    if let Some(dir) = &options.png_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // Shared like in the firmware (where the CLI task accesses them too)
+    let settings = freertos::Mutex::new(options.settings);
+    let rtc = freertos::Mutex::new(MockRtc::new(time, options.temperature));
+
+    let mut display = Display::new();
+    let mut display_fsm = DisplayFsm::new(Board::new(&settings, &rtc));
+
+    // Only read in the interactive mode
+    let keys = options.steps.is_none().then(spawn_key_reader);
+
+    let mut stdout = io::stdout().lock();
+    let started = Instant::now();
+    let mut step: u64 = 0;
+
+    while options.steps.is_none_or(|x| step < x) {
+        if let Some(keys) = &keys {
+            for key in keys.try_iter() {
+                match key {
+                    'q' => return Ok(()),
+                    'p' => {
+                        let dir = options.png_dir.as_deref().unwrap_or(Path::new("."));
+                        write_png(&dir.join(format!("snapshot_{:06}.png", step)), &display)?;
+                    }
+                    _ => handle_key(key),
+                }
+            }
+        }
+
+        display_fsm.next_step(&mut display);
+
+        if let Some(dir) = &options.png_dir {
+            write_png(&dir.join(format!("frame_{:06}.png", step)), &display)?;
+        }
+        if options.steps.is_none() || options.png_dir.is_none() {
+            print_frame(&mut stdout, &display, &rtc, options.style, keys.is_some())?;
+        }
+
+        freertos::advance(display_fsm::STEP_PERIOD);
+        step += 1;
+
+        if keys.is_some() {
+            // Paced to real time
+            let next = Duration::from_millis(step * display_fsm::STEP_PERIOD.0 as u64);
+            std::thread::sleep(next.saturating_sub(started.elapsed()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the time (UTC) of this computer.
+fn host_time() -> io::Result<NaiveDateTime> {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(io::Error::other)?
+        .as_secs();
+    datetime::from_unix_seconds(seconds)
+        .map_err(|_| io::Error::other("Time of this computer out of range"))
+}
+
+/// Reads the keys typed into the terminal (line by line, as it is not switched to raw mode).
+fn spawn_key_reader() -> Receiver<char> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            for key in line.chars() {
+                if sender.send(key).is_err() {
+                    return;
+                }
+            }
+        }
+        // Quits at the end of the input
+        sender.send('q').unwrap_or(());
+    });
+    receiver
+}
+
+fn handle_key(key: char) {
+    let button = match key.to_ascii_lowercase() {
+        's' => Some(Button::Set),
+        'u' => Some(Button::Up),
+        'd' => Some(Button::Down),
+        _ => None,
+    };
+
+    match (button, key) {
+        (Some(button), _) if key.is_ascii_uppercase() => {
+            buttons::push_event(ButtonEvent::LongPress(button))
+        }
+        (Some(button), _) => buttons::push_event(ButtonEvent::Press(button)),
+        (None, '+') => light_sensor::set_level(light_sensor::level().saturating_add(LIGHT_STEP)),
+        (None, '-') => light_sensor::set_level(light_sensor::level().saturating_sub(LIGHT_STEP)),
+        (None, 'f') => internal_rtc::set_fallback_active(!internal_rtc::is_fallback_active()),
+        _ => {}
+    }
+}
+
+fn print_frame<W: Write>(
+    writer: &mut W,
+    display: &Display,
+    rtc: &freertos::Mutex<MockRtc>,
+    style: Style,
+    interactive: bool,
+) -> io::Result<()> {
+    if style == Style::Ansi {
+        // Drawn over the previous frame
+        write!(writer, "\x1b[H\x1b[J")?;
+    }
+    write!(
+        writer,
+        "{}",
+        render::render(display.raw_data(), display.brightness(), style)
+    )?;
+
+    // Cannot fail (the mock RTC keeps the time)
+    let now = rtc.lock().get_datetime().unwrap();
+    write!(
+        writer,
+        "\n{} UTC  brightness {}%  light {}/{}",
+        now,
+        display.brightness(),
+        light_sensor::level(),
+        light_sensor::MAX_LIGHT_LEVEL
+    )?;
+    if internal_rtc::is_fallback_active() {
+        write!(writer, "  RTC failing")?;
+    }
+    if buzzer::is_playing() {
+        write!(writer, "  buzzer on")?;
+    }
+    writeln!(writer)?;
+    if interactive {
+        writeln!(
+            writer,
+            "Keys: s u d, S U D (long), + -, f, p, q (then Enter)"
+        )?;
+    }
+    if style == Style::Ascii {
+        writeln!(writer)?;
+    }
+
+    writer.flush()
+}
+
+fn write_png(path: &Path, display: &Display) -> io::Result<()> {
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+    png::write(&mut file, display.raw_data(), display.brightness())?;
+    file.flush()
+}
@@ -0,0 +1,179 @@
+//! Writing frames as PNG images (uncompressed, so no dependencies are needed).
+
+use crate::render;
+use pico_clock_core::display::data::{Geometry, RawData};
+
+use std::io::{self, Write};
+
+const GEOMETRY: Geometry = Geometry::PICO_CLOCK_GREEN;
+
+/// Size of an LED and the gap around it in pixels.
+const LED_SIZE: usize = 8;
+const GAP: usize = 2;
+const CELL_SIZE: usize = LED_SIZE + GAP;
+
+const BACKGROUND: (u8, u8, u8) = (10, 10, 10);
+const LED_OFF: (u8, u8, u8) = (35, 35, 35);
+
+/// Writes the frame with each LED drawn as a square (the indicators at their positions next to
+/// the dot matrix).
+pub fn write<W: Write>(writer: &mut W, raw_data: &RawData, brightness: u8) -> io::Result<()> {
+    // Only the columns with LEDs (the others are not connected)
+    let width = GEOMETRY.active_columns * CELL_SIZE + GAP;
+    let height = GEOMETRY.raw_height * CELL_SIZE + GAP;
+
+    // Scanlines, each with filter type 0 (none)
+    let mut image = Vec::with_capacity((width * 3 + 1) * height);
+    for y in 0..height {
+        image.push(0);
+        for x in 0..width {
+            let (red, green, blue) = pixel(raw_data, brightness, x, y);
+            image.extend_from_slice(&[red, green, blue]);
+        }
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, color type 2 (RGB), default compression, filter and no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(writer, b"IHDR", &header)?;
+    write_chunk(writer, b"IDAT", &zlib_stored(&image))?;
+    write_chunk(writer, b"IEND", &[])
+}
+
+fn pixel(raw_data: &RawData, brightness: u8, x: usize, y: usize) -> (u8, u8, u8) {
+    if x < GAP || y < GAP {
+        return BACKGROUND;
+    }
+
+    let (column, row) = ((x - GAP) / CELL_SIZE, (y - GAP) / CELL_SIZE);
+    let in_led = (x - GAP) % CELL_SIZE < LED_SIZE && (y - GAP) % CELL_SIZE < LED_SIZE;
+    if !in_led || !render::is_led(row, column) {
+        return BACKGROUND;
+    }
+
+    if raw_data[row] & (1 << column) != 0 {
+        render::led_color(brightness)
+    } else {
+        LED_OFF
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(chunk_type)?;
+    writer.write_all(data)?;
+
+    let crc = crc32(chunk_type.iter().chain(data));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Wraps the data into a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK_LEN: usize = 0xFFFF;
+
+    let mut output = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        // An empty final block
+        output.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        output.push(last as u8);
+        output.extend_from_slice(&len.to_le_bytes());
+        output.extend_from_slice(&(!len).to_le_bytes());
+        output.extend_from_slice(block);
+    }
+
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+fn crc32<'a>(data: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % MODULUS;
+        b = (b + a) % MODULUS;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Extracts the data of a zlib stream of uncompressed deflate blocks.
+    fn unpack_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(stream[..2], [0x78, 0x01]);
+        // The header is a multiple of 31
+        assert_eq!(u16::from_be_bytes([stream[0], stream[1]]) % 31, 0);
+
+        let mut data = Vec::new();
+        let mut position = 2;
+        loop {
+            let last = stream[position] == 1;
+            let len = u16::from_le_bytes([stream[position + 1], stream[position + 2]]);
+            let inverted_len = u16::from_le_bytes([stream[position + 3], stream[position + 4]]);
+            assert_eq!(len, !inverted_len);
+
+            position += 5;
+            data.extend_from_slice(&stream[position..position + len as usize]);
+            position += len as usize;
+            if last {
+                break;
+            }
+        }
+
+        let checksum = u32::from_be_bytes(stream[position..].try_into().unwrap());
+        assert_eq!(checksum, adler32(&data));
+        data
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789".iter()), 0xCBF4_3926);
+        assert_eq!(crc32([].iter()), 0);
+    }
+
+    #[test]
+    fn adler32_matches_known_values() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(&[]), 1);
+        // Both sums wrap around the modulus
+        assert_eq!(adler32(&[0xFF; 6000]), 0xA497_59EA);
+    }
+
+    #[test]
+    fn zlib_stream_contains_the_data() {
+        assert!(unpack_stored(&zlib_stored(&[])).is_empty());
+        assert_eq!(unpack_stored(&zlib_stored(b"abc")), b"abc");
+
+        // Split into several blocks
+        let data: Vec<u8> = (0..150_000).map(|x| (x % 251) as u8).collect();
+        let stream = zlib_stored(&data);
+        assert_eq!(stream.len(), 2 + 3 * 5 + data.len() + 4);
+        assert_eq!(unpack_stored(&stream), data);
+    }
+}
@@ -0,0 +1,161 @@
+//! Rendering of the display content (raw data as shifted out to the LED drivers) as text, either
+//! plain ASCII or with ANSI colors.
+//!
+//! The 22x7 dot matrix occupies the columns 2 to 23 of the rows 1 to 7. The indicators are in
+//! the columns 0 and 1 of each row (two of them share the rows 3 and 4), the weekdays in row 0.
+
+use pico_clock_core::display::data::{Geometry, Indicator, RawData, DOT_MATRIX_WIDTH};
+
+const GEOMETRY: Geometry = Geometry::PICO_CLOCK_GREEN;
+
+/// Width of the column with the labels of the left indicators.
+const LABEL_WIDTH: usize = 12;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Style {
+    Ascii,
+    Ansi,
+}
+
+/// Checks whether an LED of the raw data is connected (the others are never lit).
+pub fn is_led(row: usize, column: usize) -> bool {
+    let x_offset = GEOMETRY.dot_matrix_x_offset;
+    let in_matrix = row >= GEOMETRY.dot_matrix_y_offset
+        && (x_offset..x_offset + DOT_MATRIX_WIDTH).contains(&column);
+    let in_indicator = Indicator::ALL.iter().any(|x| {
+        let (indicator_row, mask) = x.position();
+        indicator_row == row && mask & (1 << column) != 0
+    });
+    in_matrix || in_indicator
+}
+
+/// Renders the display as text lines (the brightness only changes the colors).
+pub fn render(raw_data: &RawData, brightness: u8, style: Style) -> String {
+    let mut output = String::new();
+
+    for (row, raw_row) in raw_data.iter().enumerate() {
+        let (mut left, top): (Vec<_>, Vec<_>) = Indicator::ALL
+            .into_iter()
+            .filter(|x| x.position().0 == row)
+            .map(|x| (x.position().1, label(x)))
+            // In the columns left of the dot matrix or above it
+            .partition(|(mask, _)| *mask >> GEOMETRY.dot_matrix_x_offset == 0);
+        left.sort_by_key(|(mask, _)| *mask);
+
+        let mut label_width = 0;
+        for (index, (mask, label)) in left.into_iter().enumerate() {
+            if index > 0 {
+                output.push(' ');
+                label_width += 1;
+            }
+            output.push_str(&indicator(label, raw_row & mask != 0, brightness, style));
+            label_width += label.chars().count();
+        }
+        output.push_str(&" ".repeat(LABEL_WIDTH.saturating_sub(label_width)));
+
+        if row < GEOMETRY.dot_matrix_y_offset {
+            // The weekdays above the dot matrix
+            for (mask, label) in top {
+                output.push_str(&indicator(label, raw_row & mask != 0, brightness, style));
+                output.push(' ');
+            }
+        } else {
+            for column in 0..DOT_MATRIX_WIDTH {
+                let lit = raw_row & (1 << (column + GEOMETRY.dot_matrix_x_offset)) != 0;
+                output.push_str(&dot(lit, brightness, style));
+            }
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Label of an indicator as printed on the front panel.
+fn label(indicator: Indicator) -> &'static str {
+    match indicator {
+        Indicator::Mon => "MON",
+        Indicator::Tues => "TUE",
+        Indicator::Wed => "WED",
+        Indicator::Thur => "THU",
+        Indicator::Fri => "FRI",
+        Indicator::Sat => "SAT",
+        Indicator::Sun => "SUN",
+        Indicator::MoveOn => "Move on",
+        Indicator::AlarmOn => "Alarm on",
+        Indicator::CountDown => "Count down",
+        Indicator::DegreeF => "°F",
+        Indicator::DegreeC => "°C",
+        Indicator::AM => "AM",
+        Indicator::PM => "PM",
+        Indicator::CountUp => "Count up",
+        Indicator::Hourly => "Hourly",
+        Indicator::AutoLight => "Auto light",
+    }
+}
+
+fn indicator(label: &str, lit: bool, brightness: u8, style: Style) -> String {
+    match style {
+        Style::Ascii if lit => label.to_uppercase(),
+        // Unlit indicators are blanked (keeping the layout)
+        Style::Ascii => ".".repeat(label.chars().count()),
+        Style::Ansi if lit => format!("{}{}\x1b[0m", color(brightness), label),
+        Style::Ansi => format!("\x1b[38;2;60;60;60m{}\x1b[0m", label),
+    }
+}
+
+fn dot(lit: bool, brightness: u8, style: Style) -> String {
+    match style {
+        Style::Ascii if lit => "#".to_string(),
+        Style::Ascii => ".".to_string(),
+        Style::Ansi if lit => format!("{}●\x1b[0m", color(brightness)),
+        Style::Ansi => "\x1b[38;2;60;60;60m·\x1b[0m".to_string(),
+    }
+}
+
+/// 24-bit foreground color of a lit LED.
+fn color(brightness: u8) -> String {
+    let (red, green, blue) = led_color(brightness);
+    format!("\x1b[38;2;{};{};{}m", red, green, blue)
+}
+
+/// Color of a lit LED at the given brightness (green like the LEDs of the kit, never darker
+/// than an unlit one).
+pub fn led_color(brightness: u8) -> (u8, u8, u8) {
+    let scale = |x: u32| (60 + (x - 60) * brightness as u32 / 100) as u8;
+    (scale(60), scale(255), scale(90))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pico_clock_core::display::data::Data;
+
+    #[test]
+    fn ascii_snapshot() {
+        let mut data = Data::new();
+        let mut frame = [0; 7];
+        // Diagonal from the top left and the rightmost column
+        for (row, x) in frame.iter_mut().enumerate() {
+            *x = 1 << row | 1 << (DOT_MATRIX_WIDTH - 1);
+        }
+        data.set_dot_matrix(&frame);
+        for indicator in [Indicator::Wed, Indicator::AlarmOn, Indicator::DegreeC] {
+            data.set_indicator(indicator, true);
+        }
+
+        let expected = [
+            ".......     ... ... WED ... ... ... ... ",
+            "ALARM ON    #....................#",
+            "..........  .#...................#",
+            ".. °C       ..#..................#",
+            ".. ..       ...#.................#",
+            "........    ....#................#",
+            "......      .....#...............#",
+            "..........  ......#..............#",
+        ];
+        let output = render(&data.raw_data, 100, Style::Ascii);
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+    }
+}
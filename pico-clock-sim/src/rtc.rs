@@ -0,0 +1,99 @@
+//! Mock RTC keeping the simulated time: The time it has been set to plus the tick count elapsed
+//! since then.
+
+use crate::freertos;
+use crate::internal_rtc;
+
+use pico_clock_core::rtc::{AlarmSlot, RtcError, TimeSource};
+use pico_clock_core::timezone;
+
+use ds323x::{NaiveDateTime, Timelike};
+
+pub struct MockRtc {
+    /// Time (UTC) at the tick count 'set_at'.
+    datetime: NaiveDateTime,
+    /// Tick count when the time has been set (a multiple of 1000 ms, see 'sqw').
+    set_at: u32,
+    /// Temperature in degrees Celsius.
+    temperature: f32,
+    /// Time each alarm goes off (UTC, once).
+    alarms: [Option<NaiveDateTime>; 2],
+    /// Alarm flags (set when the alarm went off until cleared).
+    matched: [bool; 2],
+}
+
+impl MockRtc {
+    pub fn new(datetime: NaiveDateTime, temperature: f32) -> Self {
+        let mut rtc = Self {
+            datetime,
+            set_at: 0,
+            temperature,
+            alarms: [None; 2],
+            matched: [false; 2],
+        };
+        // Cannot fail
+        rtc.set_datetime(&datetime).unwrap();
+        rtc
+    }
+
+    /// Fails like an external RTC that cannot be accessed.
+    fn check_access(&self) -> Result<(), RtcError> {
+        if internal_rtc::is_fallback_active() {
+            Err(RtcError::Bus)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn now(&self) -> NaiveDateTime {
+        let elapsed = freertos::tick_count().0.wrapping_sub(self.set_at) / 1000;
+        timezone::add_seconds(&self.datetime, elapsed as i64)
+    }
+}
+
+impl TimeSource for MockRtc {
+    /// Works like the external RTC backed by the internal RTC (never fails).
+    fn get_datetime(&mut self) -> Result<NaiveDateTime, RtcError> {
+        Ok(self.now())
+    }
+
+    /// The fractions of the second are dropped.
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        let now = freertos::tick_count().0;
+        // Cannot fail because 0 is a valid nanosecond
+        self.datetime = datetime.with_nanosecond(0).unwrap();
+        self.set_at = now - now % 1000;
+        Ok(())
+    }
+
+    fn get_temperature(&mut self) -> Result<f32, RtcError> {
+        self.check_access()?;
+        Ok(self.temperature)
+    }
+
+    fn set_alarm(&mut self, slot: AlarmSlot, datetime: &NaiveDateTime) -> Result<(), RtcError> {
+        self.check_access()?;
+        self.alarms[slot as usize] = Some(*datetime);
+        self.matched[slot as usize] = false;
+        Ok(())
+    }
+
+    fn has_alarm_matched(&mut self, slot: AlarmSlot) -> Result<bool, RtcError> {
+        self.check_access()?;
+        let now = self.now();
+        if let Some(due) = self.alarms[slot as usize].filter(|x| now >= *x) {
+            // Compared with the seconds set to zero like by the DS3231
+            if now.with_second(0) == due.with_second(0) {
+                self.matched[slot as usize] = true;
+            }
+            self.alarms[slot as usize] = None;
+        }
+        Ok(self.matched[slot as usize])
+    }
+
+    fn clear_alarm(&mut self, slot: AlarmSlot) -> Result<(), RtcError> {
+        self.check_access()?;
+        self.matched[slot as usize] = false;
+        Ok(())
+    }
+}
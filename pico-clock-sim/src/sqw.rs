@@ -0,0 +1,9 @@
+//! Simulated square wave of the RTC (the mock RTC increments the seconds at multiples of 1000 ms
+//! of the tick count).
+
+use crate::freertos;
+
+/// Checks whether the colon shall be shown: During the first half of each second.
+pub fn colon_visible() -> bool {
+    freertos::tick_count().0 % 1000 < 500
+}